@@ -0,0 +1,31 @@
+use methal::lsp::{self, Server};
+use std::io;
+
+// Speaks the Language Server Protocol over stdin and stdout
+fn main() {
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    let mut server = Server::default();
+
+    loop {
+        let body = match lsp::read_message(&mut stdin) {
+            Ok(Some(body)) => body,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("methal-lsp: {e}");
+                std::process::exit(1);
+            }
+        };
+
+        for reply in server.receive(&body) {
+            if let Err(e) = lsp::write_message(&mut stdout, &reply) {
+                eprintln!("methal-lsp: {e}");
+                std::process::exit(1);
+            }
+        }
+
+        if let Some(code) = server.exit_code() {
+            std::process::exit(code);
+        }
+    }
+}
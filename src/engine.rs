@@ -0,0 +1,410 @@
+use crate::evaluator::{Domain, Environment, Value};
+use crate::graph::{self, Cycle, Graph};
+use crate::parser::{Expression, Statement};
+use crate::{Options, Output, execute, objectives};
+use std::collections::HashSet;
+use std::mem;
+
+// A binding whose value differs from the previous update
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub name: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+// A name a statement read, with its value, what was assumed about it and its formula
+type Input = (String, Option<Value>, Option<Domain>, Option<Expression>);
+
+// One statement's result, kept with the values it read so it can be reused while they hold
+#[derive(Debug)]
+struct Entry {
+    statement: Statement,
+    inputs: Vec<Input>,
+    result: Result<Vec<Output>, String>,
+}
+
+// Runs a program and keeps every statement's result, so running an edited version of it
+// only evaluates the statements that changed or read a value that changed
+#[derive(Debug, Default)]
+pub struct Engine {
+    options: Options,
+    entries: Vec<Entry>,
+    cycles: Vec<Cycle>,
+    objectives: HashSet<String>,
+    env: Environment,
+    evaluated: usize,
+}
+
+impl Engine {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    pub fn update(&mut self, statements: Vec<Statement>) -> Vec<Change> {
+        let (order, cycles) = Graph::new(&statements).order();
+        // Whether a let stays a formula depends on the minimize calls anywhere in the file
+        let objectives = objectives(&statements);
+        if objectives != self.objectives {
+            self.entries.clear();
+        }
+        let mut statements: Vec<Option<Statement>> = statements.into_iter().map(Some).collect();
+        let mut cache: Vec<Option<Entry>> =
+            mem::take(&mut self.entries).into_iter().map(Some).collect();
+
+        let mut env = Environment::default();
+        env.set_ieee(self.options.ieee);
+        env.set_objectives(objectives.clone());
+        self.objectives = objectives;
+        self.evaluated = 0;
+
+        for i in order {
+            let statement = statements[i]
+                .take()
+                .expect("each statement is ordered once");
+            let mut names = graph::uses(&statement);
+            // An assumption narrows whatever was assumed about its name before
+            if let Statement::Assume { name, .. } = &statement {
+                names.push(name.clone());
+            }
+            let inputs: Vec<Input> = names
+                .into_iter()
+                .map(|name| {
+                    let value = env.get(&name).cloned();
+                    let domain = env.domain(&name).copied();
+                    let formula = env.formula(&name).cloned();
+                    (name, value, domain, formula)
+                })
+                .collect();
+
+            let cached = cache
+                .iter_mut()
+                .find(|entry| {
+                    entry
+                        .as_ref()
+                        .is_some_and(|e| e.statement == statement && e.inputs == inputs)
+                })
+                .and_then(Option::take);
+
+            let result = match cached {
+                Some(entry) => {
+                    replay(&statement, &entry.result, &mut env);
+                    entry.result
+                }
+                None => {
+                    self.evaluated += 1;
+                    execute(&statement, &mut env, &self.options)
+                }
+            };
+
+            self.entries.push(Entry {
+                statement,
+                inputs,
+                result,
+            });
+        }
+
+        self.cycles = cycles;
+        let changes = changes(&self.env, &env);
+        self.env = env;
+        changes
+    }
+
+    // What each statement gave, in the order they ran
+    pub fn results(&self) -> impl Iterator<Item = (&Statement, &Result<Vec<Output>, String>)> {
+        self.entries
+            .iter()
+            .map(|entry| (&entry.statement, &entry.result))
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = &Output> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.result.as_ref().ok())
+            .flatten()
+    }
+
+    pub fn cycles(&self) -> &[Cycle] {
+        &self.cycles
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.env.get(name)
+    }
+
+    // How many statements the last update had to evaluate rather than reuse
+    pub fn evaluated(&self) -> usize {
+        self.evaluated
+    }
+}
+
+// Binds what the statement bound when it ran, without running it again
+fn replay(statement: &Statement, result: &Result<Vec<Output>, String>, env: &mut Environment) {
+    let Ok(outputs) = result else { return };
+
+    for output in outputs {
+        match output {
+            Output::Solution(name, value) => env.set(name.clone(), value.clone()),
+            Output::Assumption(name, domain) => env.assume(name.clone(), *domain),
+            Output::Formula(name, expr) => {
+                env.remove(name);
+                env.define(name.clone(), expr);
+            }
+            Output::Binding(name, value) if graph::defines(statement).is_some() => {
+                env.set(name.clone(), value.clone());
+                if let Statement::Let {
+                    expr, value: None, ..
+                } = statement
+                {
+                    env.define(name.clone(), expr);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn changes(before: &Environment, after: &Environment) -> Vec<Change> {
+    let mut names: Vec<&String> = before.iter().chain(after.iter()).map(|(n, _)| n).collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (before, after) = (before.get(name), after.get(name));
+            (before != after).then(|| Change {
+                name: name.clone(),
+                before: before.cloned(),
+                after: after.cloned(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::BigInt;
+    use crate::lexer;
+    use crate::parser::Parser;
+
+    fn statements(input: &str) -> Vec<Statement> {
+        let tokens = lexer::tokenize_spanned(input);
+        Parser::with_spans(tokens).run_parser()
+    }
+
+    const PROGRAM: &str =
+        "let g -> 10\nlet m -> 2\nlet w -> m*g\nlet h -> 5\nlet e -> w*h\nlet x in [0, 5]\nx^2 = m";
+
+    #[test]
+    fn reevaluates_only_what_changed() {
+        let mut engine = Engine::default();
+        engine.update(statements(PROGRAM));
+        assert_eq!(engine.evaluated(), 7);
+
+        let changes = engine.update(statements(&PROGRAM.replace("let h -> 5", "let h -> 6")));
+        assert_eq!(engine.evaluated(), 2);
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    name: "e".into(),
+                    before: Some(Value::Number(100.0)),
+                    after: Some(Value::Number(120.0)),
+                },
+                Change {
+                    name: "h".into(),
+                    before: Some(Value::Number(5.0)),
+                    after: Some(Value::Number(6.0)),
+                },
+            ]
+        );
+
+        assert!(
+            engine
+                .update(statements(
+                    PROGRAM.replace("let h -> 5", "let h -> 6").as_str()
+                ))
+                .is_empty()
+        );
+        assert_eq!(engine.evaluated(), 0);
+    }
+
+    #[test]
+    fn resolves_downstream_equations() {
+        let mut engine = Engine::default();
+        engine.update(statements(PROGRAM));
+
+        engine.update(statements(&PROGRAM.replace("let m -> 2", "let m -> 4")));
+        // m, w, e and the roots of x^2 = m
+        assert_eq!(engine.evaluated(), 4);
+        let roots: Vec<&Output> = engine
+            .outputs()
+            .filter(|output| matches!(output, Output::Root(..)))
+            .collect();
+        assert_eq!(roots.len(), 1);
+        assert!(roots[0].value().contains("[1.99"));
+    }
+
+    #[test]
+    fn drops_removed_bindings() {
+        let mut engine = Engine::default();
+        engine.update(statements("let a -> 1; let b -> a + 1"));
+
+        let changes = engine.update(statements("let a -> 1"));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].after, None);
+        assert_eq!(engine.get("b"), None);
+    }
+
+    #[test]
+    fn joins_congruences_into_a_system() {
+        let program = "x ≡ 2 (mod 3)\nx ≡ 3 (mod 5)\nx ≡ 2 (mod 7)";
+        let mut engine = Engine::default();
+        engine.update(statements(program));
+        assert_eq!(
+            engine.get("x"),
+            Some(&Value::residue(BigInt::from(23), BigInt::from(105)))
+        );
+
+        engine.update(statements(&program.replace("3 (mod 5)", "1 (mod 5)")));
+        assert_eq!(
+            engine.get("x"),
+            Some(&Value::residue(BigInt::from(86), BigInt::from(105)))
+        );
+    }
+
+    #[test]
+    fn resolves_when_assumptions_change() {
+        let mut engine = Engine::default();
+        engine.update(statements("x^2 = 4\nassume x > 0"));
+        assert_eq!(engine.get("x"), Some(&Value::Number(2.0)));
+
+        engine.update(statements("x^2 = 4\nassume x < 0"));
+        assert_eq!(engine.evaluated(), 2);
+        assert_eq!(engine.get("x"), Some(&Value::Number(-2.0)));
+    }
+
+    #[test]
+    fn simplifies_by_assumptions() {
+        let mut engine = Engine::default();
+        engine.update(statements(
+            "assume x > 0\nsqrt(x^2)\nassume k >= 1 in Z\nsqrt(y^2)",
+        ));
+        let results: Vec<String> = (engine.results())
+            .map(|(_, result)| match result {
+                Ok(outputs) => outputs[0].to_string(),
+                Err(e) => e.clone(),
+            })
+            .collect();
+        assert_eq!(
+            results,
+            [
+                "assume x > 0",
+                "x",
+                "assume k in Z, k >= 1",
+                "undefined variable 'y'"
+            ]
+        );
+    }
+
+    #[test]
+    fn minimizes_let_formulas() {
+        let program = "let f -> (x - 1)^2 + (y - 2)^2\nminimize(f, x, y)";
+        let mut engine = Engine::default();
+        engine.update(statements(program));
+        let outputs: Vec<String> = engine.outputs().map(|o| o.to_string()).collect();
+        assert_eq!(outputs[0], "let f -> (x - 1)^2 + (y - 2)^2");
+        assert!(
+            outputs[1].starts_with("0 at x = 1, y = 2 (BFGS"),
+            "{outputs:?}"
+        );
+        assert_eq!(engine.get("f"), None);
+
+        engine.update(statements("minimize(x, x)\nargmin(x^2 - 2y, x, y)"));
+        let outputs: Vec<String> = engine.outputs().map(|o| o.value()).collect();
+        assert_eq!(
+            outputs,
+            [
+                "unbounded, x has no minimum",
+                "unbounded, x^2 - 2y has no minimum"
+            ]
+        );
+
+        let program = "let x -> 0\nlet f -> (x - 3)^2 + 1\nminimize(f, x)";
+        let mut engine = Engine::default();
+        engine.update(statements(program));
+        let minimum = |engine: &Engine| engine.outputs().last().unwrap().value();
+        assert!(minimum(&engine).starts_with("1 at x = 3 (BFGS"));
+
+        // f is still 10 at x = 0, but its formula changed
+        engine.update(statements(&program.replace("x - 3", "x + 3")));
+        assert!(minimum(&engine).starts_with("1 at x = -3 (BFGS"));
+
+        // Only objectives stay formulas, elsewhere an unknown name is still an error
+        engine.update(statements("let v -> 10 * tme\nv + 1"));
+        let errors: Vec<&String> = engine
+            .results()
+            .filter_map(|(_, r)| r.as_ref().err())
+            .collect();
+        assert_eq!(errors[0], "let v: undefined variable 'tme'");
+    }
+
+    #[test]
+    fn reports_the_optimum() {
+        let program = "maximize 3x + 2y subject to x + y <= 4, x + 3y <= 6, x >= 0, y >= 0";
+        let mut engine = Engine::default();
+        engine.update(statements(program));
+        let optimum = |engine: &Engine| engine.outputs().last().unwrap().value();
+        assert_eq!(optimum(&engine), "optimal value 12 at x = 4, y = 0");
+        assert_eq!(engine.get("x"), None);
+
+        // The same names can be optimised again
+        engine.update(statements(&format!(
+            "{program}\nminimize x + y subject to x >= 1, y >= 2"
+        )));
+        assert_eq!(optimum(&engine), "optimal value 3 at x = 1, y = 2");
+
+        // Whole numbers only, where the relaxation would reach y = 2.8
+        let program = "assume x in N\nassume y in N\nmaximize y subject to y - x <= 1, 3x + 2y <= 12, 2x + 3y <= 12";
+        engine.update(statements(program));
+        assert!(optimum(&engine).starts_with("optimal value 2 at"));
+    }
+
+    #[test]
+    fn reads_units_only_after_a_space() {
+        let program = "3s + 2 = 8\n2h = 8\nfactor(s^2 - 5s + 6)\nlet g -> 9.81\n2 g\n2g\n5 kN in N";
+        let mut engine = Engine::default();
+        engine.update(statements(program));
+        let outputs: Vec<String> = engine.outputs().map(|o| o.to_string()).collect();
+        assert_eq!(
+            outputs,
+            [
+                "s = 2",
+                "h = 4",
+                "(s - 2)(s - 3)",
+                "let g = 9.81",
+                "0.002 kg",
+                "19.62",
+                "5000 N"
+            ]
+        );
+    }
+
+    #[test]
+    fn tabulates_past_failing_cells() {
+        let mut engine = Engine::default();
+        engine.update(statements("table(1/x, x from -1 to 1 step 1)"));
+        let table = engine.outputs().last().unwrap().value();
+
+        let rows: Vec<&str> = table.lines().skip(2).map(str::trim).collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].ends_with("-1"));
+        assert!(rows[1].ends_with("division by zero in 1/x"), "{table}");
+        assert!(rows[2].ends_with('1'));
+    }
+}
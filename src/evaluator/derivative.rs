@@ -0,0 +1,111 @@
+use crate::evaluator::{Environment, partial};
+use crate::parser::{Expression, Operator, UnaryOperator};
+
+// d/dx of expr by the sum, product, quotient and power rules, tidied by partial evaluation.
+// Parts with no rule, such as x! or 2^x, are an error
+pub fn derivative(expr: &Expression, var: &str) -> Result<Expression, String> {
+    partial::partial_eval(differentiate(expr, var)?, &Environment::default())
+}
+
+fn differentiate(expr: &Expression, var: &str) -> Result<Expression, String> {
+    let mentions = |e: &Expression| e.identifiers().iter().any(|name| name == var);
+    if !mentions(expr) {
+        return Ok(Expression::Number(0.0));
+    }
+
+    match expr {
+        Expression::Ident(_) => Ok(Expression::Number(1.0)),
+        Expression::Binary { lhs, op, rhs } => {
+            let (u, v) = (lhs.as_ref().clone(), rhs.as_ref().clone());
+            let (du, dv) = (differentiate(lhs, var)?, differentiate(rhs, var)?);
+            Ok(match op {
+                Operator::Add | Operator::Sub => binary(du, *op, dv),
+                Operator::Mult | Operator::ElemMult => binary(
+                    binary(du, Operator::Mult, v),
+                    Operator::Add,
+                    binary(u, Operator::Mult, dv),
+                ),
+                Operator::Div => binary(
+                    binary(
+                        binary(du, Operator::Mult, v.clone()),
+                        Operator::Sub,
+                        binary(u, Operator::Mult, dv),
+                    ),
+                    Operator::Div,
+                    binary(v, Operator::Pow, Expression::Number(2.0)),
+                ),
+                // The power rule, for exponents that do not depend on var
+                Operator::Pow if !mentions(rhs) => {
+                    let lower = binary(v.clone(), Operator::Sub, Expression::Number(1.0));
+                    binary(
+                        binary(v, Operator::Mult, binary(u, Operator::Pow, lower)),
+                        Operator::Mult,
+                        du,
+                    )
+                }
+                _ => return Err(format!("cannot differentiate {expr} with respect to {var}")),
+            })
+        }
+        Expression::Unary { op, rhs } => {
+            let du = differentiate(rhs, var)?;
+            match op {
+                // Percent and degree only scale, so they carry over to the derivative
+                UnaryOperator::Neg
+                | UnaryOperator::Plus
+                | UnaryOperator::Percent
+                | UnaryOperator::Degree => Ok(Expression::Unary {
+                    op: *op,
+                    rhs: Box::new(du),
+                }),
+                // u/|u| is the sign of u, undefined where u is 0
+                UnaryOperator::Abs => Ok(binary(
+                    binary(rhs.as_ref().clone(), Operator::Div, expr.clone()),
+                    Operator::Mult,
+                    du,
+                )),
+                _ => Err(format!("cannot differentiate {expr} with respect to {var}")),
+            }
+        }
+        Expression::Call { name, args } if name == "sqrt" && args.len() == 1 => {
+            let du = differentiate(&args[0], var)?;
+            Ok(binary(
+                du,
+                Operator::Div,
+                binary(Expression::Number(2.0), Operator::Mult, expr.clone()),
+            ))
+        }
+        _ => Err(format!("cannot differentiate {expr} with respect to {var}")),
+    }
+}
+
+fn binary(lhs: Expression, op: Operator, rhs: Expression) -> Expression {
+    Expression::Binary {
+        lhs: Box::new(lhs),
+        op,
+        rhs: Box::new(rhs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+    use crate::parser::parse_expression;
+
+    fn d(input: &str, var: &str) -> Result<String, String> {
+        let tokens = lexer::tokenize(input.to_string()).unwrap();
+        let expr = parse_expression(&mut tokens.into_iter().peekable()).unwrap();
+        derivative(&expr, var).map(|d| d.to_string())
+    }
+
+    #[test]
+    fn differentiates_by_the_rules() {
+        assert_eq!(d("x^3 + 2x*y", "x").as_deref(), Ok("3x^2 + 2y"));
+        assert_eq!(d("x^3 + 2x*y", "y").as_deref(), Ok("2x"));
+        assert_eq!(d("1/x", "x").as_deref(), Ok("-1/x^2"));
+        assert_eq!(d("sqrt(x)", "x").as_deref(), Ok("1/(2sqrt(x))"));
+        assert_eq!(d("(x - 1)^2 + 5", "y").as_deref(), Ok("0"));
+        assert!(d("2^x", "x").is_err());
+        assert!(d("x!", "x").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::evaluator::Interval;
+
+// Where an assumed name may lie: between its bounds, and only on whole numbers if integer
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Domain {
+    pub integer: bool,
+    pub lower: Option<Bound>,
+    pub upper: Option<Bound>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bound {
+    pub value: f32,
+    pub strict: bool,
+}
+
+impl Domain {
+    pub fn integers() -> Self {
+        Self {
+            integer: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn above(value: f32, strict: bool) -> Self {
+        Self {
+            lower: Some(Bound { value, strict }),
+            ..Self::default()
+        }
+    }
+
+    pub fn below(value: f32, strict: bool) -> Self {
+        Self {
+            upper: Some(Bound { value, strict }),
+            ..Self::default()
+        }
+    }
+
+    // Both domains at once, keeping the tighter bound on each side
+    pub fn intersect(self, other: Domain) -> Domain {
+        let tighter =
+            |a: Option<Bound>, b: Option<Bound>, inside: fn(f32, f32) -> bool| match (a, b) {
+                (Some(a), Some(b)) if a.value == b.value => Some(Bound {
+                    value: a.value,
+                    strict: a.strict || b.strict,
+                }),
+                (Some(a), Some(b)) => Some(if inside(a.value, b.value) { a } else { b }),
+                (a, b) => a.or(b),
+            };
+
+        Domain {
+            integer: self.integer || other.integer,
+            lower: tighter(self.lower, other.lower, |a, b| a > b),
+            upper: tighter(self.upper, other.upper, |a, b| a < b),
+        }
+    }
+
+    pub fn contains(&self, x: f32) -> bool {
+        let above = self
+            .lower
+            .is_none_or(|b| x > b.value || (!b.strict && x == b.value));
+        let below = self
+            .upper
+            .is_none_or(|b| x < b.value || (!b.strict && x == b.value));
+        above && below && (!self.integer || x.fract() == 0.0)
+    }
+
+    // Whether any point of the interval could be in the domain. Only the closed bounds are
+    // compared, as an enclosure is too wide for strictness to tell anything
+    pub fn meets(&self, interval: &Interval) -> bool {
+        let lo = self.lower.map_or(interval.lo, |b| b.value.max(interval.lo));
+        let hi = self.upper.map_or(interval.hi, |b| b.value.min(interval.hi));
+        match self.integer {
+            true => lo.ceil() <= hi.floor(),
+            false => lo <= hi,
+        }
+    }
+
+    pub fn is_nonnegative(&self) -> bool {
+        self.lower.is_some_and(|b| b.value >= 0.0)
+    }
+
+    pub fn is_nonpositive(&self) -> bool {
+        self.upper.is_some_and(|b| b.value <= 0.0)
+    }
+
+    // The conditions on name as assume statements write them, such as "n in Z, n > 0"
+    pub fn describe(&self, name: &str) -> String {
+        let mut conditions = Vec::new();
+        if self.integer {
+            conditions.push(format!("{name} in Z"));
+        }
+        if let Some(b) = self.lower {
+            let op = if b.strict { ">" } else { ">=" };
+            conditions.push(format!("{name} {op} {}", b.value));
+        }
+        if let Some(b) = self.upper {
+            let op = if b.strict { "<" } else { "<=" };
+            conditions.push(format!("{name} {op} {}", b.value));
+        }
+        if conditions.is_empty() {
+            conditions.push(format!("{name} in R"));
+        }
+        conditions.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersects_domains() {
+        let positive_integers = Domain::integers().intersect(Domain::above(0.0, true));
+        assert!(positive_integers.contains(3.0));
+        assert!(!positive_integers.contains(0.0));
+        assert!(!positive_integers.contains(2.5));
+        assert_eq!(positive_integers.describe("n"), "n in Z, n > 0");
+
+        let narrowed = Domain::above(0.0, false)
+            .intersect(Domain::above(0.0, true))
+            .intersect(Domain::below(5.0, false))
+            .intersect(Domain::below(3.0, false));
+        assert_eq!(narrowed.describe("x"), "x > 0, x <= 3");
+
+        assert!(positive_integers.meets(&Interval::new(1.9, 2.1).unwrap()));
+        assert!(!positive_integers.meets(&Interval::new(1.1, 1.9).unwrap()));
+        assert!(!positive_integers.meets(&Interval::new(-2.1, -1.9).unwrap()));
+    }
+}
@@ -0,0 +1,49 @@
+use crate::parser::Expression;
+use std::fmt;
+
+// Why an expression has no value, pointing at the part of it that failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    DivisionByZero(Expression),
+    // The result is not a number, as for (-1)^0.5
+    Domain(Expression),
+    // The result is too large to represent
+    Overflow(Expression),
+    UndefinedVariable(String),
+    // Mismatched shapes, units and other misuse
+    Invalid(String),
+}
+
+impl EvalError {
+    // The errors that IEEE arithmetic would have turned into inf or NaN
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            EvalError::DivisionByZero(_) | EvalError::Domain(_) | EvalError::Overflow(_)
+        )
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::DivisionByZero(expr) => write!(f, "division by zero in {expr}"),
+            EvalError::Domain(expr) => write!(f, "{expr} is undefined, its result is not a number"),
+            EvalError::Overflow(expr) => write!(f, "{expr} overflows"),
+            EvalError::UndefinedVariable(name) => write!(f, "undefined variable '{name}'"),
+            EvalError::Invalid(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl From<String> for EvalError {
+    fn from(message: String) -> Self {
+        EvalError::Invalid(message)
+    }
+}
+
+impl From<EvalError> for String {
+    fn from(error: EvalError) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,208 @@
+use crate::evaluator::integer::chinese_remainder;
+use crate::evaluator::prime::is_prime;
+use crate::evaluator::{BigInt, Interval, Matrix, Value};
+
+pub const FUNCTIONS: [&str; 12] = [
+    "sqrt",
+    "transpose",
+    "det",
+    "inv",
+    "rank",
+    "solve",
+    "interval",
+    "mod_pow",
+    "binomial",
+    "isprime",
+    "mod_inverse",
+    "crt",
+];
+
+// Exact factorials go up to 10000!, which has 35660 digits
+const MAX_FACTORIAL: i128 = 10_000;
+
+// binomial(n, k) multiplies min(k, n - k) factors, and stops here
+const MAX_BINOMIAL_FACTORS: i128 = 100_000;
+
+pub fn call_function(name: &str, args: Vec<Value>) -> Result<Value, String> {
+    match (name, args.as_slice()) {
+        ("sqrt", [value]) => value.clone().try_pow(Value::Number(0.5)),
+        ("transpose", [value]) => match value {
+            Value::Number(_)
+            | Value::Integer(_)
+            | Value::Residue { .. }
+            | Value::Quantity(_)
+            | Value::Interval(_) => Ok(value.clone()),
+            // A vector is a column, its transpose is a single row
+            Value::Vector(v) => Ok(Value::Matrix(Matrix::new(1, v.len(), v.clone()))),
+            Value::Matrix(m) => Ok(Value::Matrix(m.transpose())),
+        },
+        ("det", [value]) => Ok(Value::Number(value.as_matrix()?.det()?)),
+        ("inv", [value]) => Ok(Value::Matrix(value.as_matrix()?.inverse()?)),
+        ("rank", [value]) => Ok(Value::Number(value.as_matrix()?.rank() as f32)),
+        ("solve", [a, b]) => {
+            let solution = a.as_matrix()?.solve(&b.as_matrix()?)?;
+            match b {
+                Value::Vector(_) => Ok(Value::Vector(solution.transpose().row(0).to_vec())),
+                _ => Ok(Value::Matrix(solution)),
+            }
+        }
+        ("interval", [lo, hi]) => Ok(Value::Interval(Interval::from_decimals(
+            lo.as_number()?,
+            hi.as_number()?,
+        )?)),
+
+        ("mod_pow", [base, exponent, modulus]) => {
+            let (base, exponent, modulus) = (
+                integer(name, base)?,
+                integer(name, exponent)?,
+                integer(name, modulus)?,
+            );
+            if exponent.is_negative() {
+                return Err(String::from("mod_pow needs an exponent of at least 0"));
+            }
+            if modulus.is_zero() {
+                return Err(String::from("mod_pow needs a modulus other than 0"));
+            }
+            Ok(Value::integer(base.mod_pow(&exponent, &modulus)))
+        }
+        ("binomial", [n, k]) => binomial(integer(name, n)?, integer(name, k)?),
+        ("isprime", [n]) => Ok(Value::Number(if is_prime(&integer(name, n)?) {
+            1.0
+        } else {
+            0.0
+        })),
+        ("mod_inverse", [a, m]) => {
+            let (a, m) = (integer(name, a)?, integer(name, m)?);
+            if m.is_zero() {
+                return Err(String::from("mod_inverse needs a modulus other than 0"));
+            }
+            match a.mod_inverse(&m) {
+                Some(inverse) => Ok(Value::integer(inverse)),
+                None => Err(format!("{a} has no inverse mod {m}")),
+            }
+        }
+        ("crt", [residues, moduli]) => crt(residues, moduli),
+
+        ("sqrt" | "transpose" | "det" | "inv" | "rank" | "isprime", _) => {
+            Err(format!("{name} expects 1 argument, got {}", args.len()))
+        }
+        ("solve" | "interval" | "binomial" | "mod_inverse" | "crt", _) => {
+            Err(format!("{name} expects 2 arguments, got {}", args.len()))
+        }
+        ("mod_pow", _) => Err(format!("{name} expects 3 arguments, got {}", args.len())),
+
+        _ => Err(format!("unknown function '{name}'")),
+    }
+}
+
+fn integer(name: &str, value: &Value) -> Result<BigInt, String> {
+    value.as_integer().ok_or(format!(
+        "{name} expects whole numbers, got {}",
+        value.kind()
+    ))
+}
+
+// The single class x ≡ r (mod m) that meets x ≡ residues[i] (mod moduli[i]) for every i
+fn crt(residues: &Value, moduli: &Value) -> Result<Value, String> {
+    let (Value::Vector(residues), Value::Vector(moduli)) = (residues, moduli) else {
+        return Err(String::from(
+            "crt expects a vector of residues and a vector of moduli",
+        ));
+    };
+    if residues.len() != moduli.len() {
+        return Err(format!(
+            "crt got {} residues but {} moduli",
+            residues.len(),
+            moduli.len()
+        ));
+    }
+
+    let mut class = (BigInt::zero(), BigInt::one());
+    for (&r, &m) in residues.iter().zip(moduli) {
+        let r = integer("crt", &Value::Number(r))?;
+        let m = integer("crt", &Value::Number(m))?.abs();
+        if m.is_zero() {
+            return Err(String::from("crt needs moduli other than 0"));
+        }
+        class = chinese_remainder((&class.0, &class.1), (&r, &m)).ok_or_else(|| {
+            format!(
+                "x ≡ {} (mod {}) and x ≡ {r} (mod {m}) have no common solution",
+                class.0, class.1
+            )
+        })?;
+    }
+    Ok(Value::residue(class.0, class.1))
+}
+
+// n choose k, with no way to choose more than there is
+fn binomial(n: BigInt, k: BigInt) -> Result<Value, String> {
+    if n.is_negative() {
+        return Err(format!("binomial expects n of at least 0, got {n}"));
+    }
+    if k.is_negative() || k > n {
+        return Ok(Value::Number(0.0));
+    }
+
+    let k = k.clone().min(n.clone() - k);
+    match k.to_i128() {
+        Some(k) if k <= MAX_BINOMIAL_FACTORS => {
+            // Each partial product is itself a binomial coefficient, so the division is exact
+            let c = (0..k).fold(BigInt::one(), |c, i| {
+                (c * (n.clone() - BigInt::from(i)))
+                    .div_rem(&BigInt::from(i + 1))
+                    .0
+            });
+            Ok(Value::integer(c))
+        }
+        _ => Err(format!("binomial({n}, {k}) is too large to compute")),
+    }
+}
+
+// n! exactly for whole numbers, and gamma(n + 1) in between so that 0.5! = sqrt(pi)/2.
+// Negative whole numbers are poles and give NaN
+pub fn factorial(value: &Value) -> Result<Value, String> {
+    if let Some(n) = value.as_integer().and_then(|n| n.to_i128())
+        && (0..=MAX_FACTORIAL).contains(&n)
+    {
+        let product = (2..=n).fold(BigInt::one(), |acc, k| acc * BigInt::from(k));
+        return Ok(Value::integer(product));
+    }
+    Ok(Value::Number(gamma(value.as_number()? as f64 + 1.0) as f32))
+}
+
+// Lanczos approximation, with the reflection formula for x < 1/2
+fn gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x.fract() == 0.0 && x <= 0.0 {
+        return f64::NAN;
+    }
+    // Past f64::MAX, where the product below would turn into inf * 0
+    if x > 171.7 {
+        return f64::INFINITY;
+    }
+    if x < 0.5 {
+        return std::f64::consts::PI / ((std::f64::consts::PI * x).sin() * gamma(1.0 - x));
+    }
+
+    let x = x - 1.0;
+    let t = x + G + 0.5;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| {
+            sum + c / (x + i as f64 + 1.0)
+        });
+    (2.0 * std::f64::consts::PI).sqrt() * t.powf(x + 0.5) * (-t).exp() * sum
+}
@@ -0,0 +1,552 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+// An integer of any size: a sign and a magnitude in base 2^32 digits, least significant
+// first, with no leading zero digits so that zero has an empty magnitude
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self::from(0)
+    }
+
+    pub fn one() -> Self {
+        Self::from(1)
+    }
+
+    fn new(negative: bool, mut digits: Vec<u32>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Self {
+            negative: negative && !digits.is_empty(),
+            digits,
+        }
+    }
+
+    // Digits in the given radix, without sign or separators
+    pub fn parse_radix(text: &str, radix: u32) -> Option<Self> {
+        if text.is_empty() {
+            return None;
+        }
+        text.chars().try_fold(Self::zero(), |acc, c| {
+            let digit = c.to_digit(radix)?;
+            Some(acc * Self::from(radix as i128) + Self::from(digit as i128))
+        })
+    }
+
+    // Whole floats only, as far as an i128 reaches
+    pub fn from_f32(x: f32) -> Option<Self> {
+        if !x.is_finite() || x.fract() != 0.0 || x.abs() >= i128::MAX as f32 {
+            return None;
+        }
+        Some(Self::from(x as i128))
+    }
+
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.digits.len() > 4 {
+            return None;
+        }
+        let magnitude = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0u128, |acc, &d| (acc << 32) | d as u128);
+        let magnitude = i128::try_from(magnitude).ok()?;
+        Some(if self.negative { -magnitude } else { magnitude })
+    }
+
+    // The nearest float, or an infinity past f32::MAX
+    pub fn to_f32(&self) -> f32 {
+        self.to_f64() as f32
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .digits
+            .iter()
+            .rev()
+            .fold(0.0f64, |acc, &d| acc * 4_294_967_296.0 + d as f64);
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.digits.first().is_none_or(|d| d % 2 == 0)
+    }
+
+    pub fn abs(self) -> Self {
+        Self::new(false, self.digits)
+    }
+
+    // The number of bits in the magnitude
+    pub fn bits(&self) -> u64 {
+        match self.digits.last() {
+            Some(top) => self.digits.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    // Truncating division, as for the primitive integers: the remainder takes the sign of self
+    pub fn div_rem(&self, rhs: &BigInt) -> (BigInt, BigInt) {
+        assert!(!rhs.is_zero(), "integer division by zero");
+        let (q, r) = div_rem_magnitude(&self.digits, &rhs.digits);
+        (
+            Self::new(self.negative != rhs.negative, q),
+            Self::new(self.negative, r),
+        )
+    }
+
+    // The remainder in 0..|m|
+    pub fn rem_euclid(&self, m: &BigInt) -> BigInt {
+        let (_, r) = self.div_rem(m);
+        if r.negative { r + m.clone().abs() } else { r }
+    }
+
+    pub fn pow(&self, mut exponent: u64) -> BigInt {
+        let (mut base, mut result) = (self.clone(), BigInt::one());
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = result * base.clone();
+            }
+            base = base.clone() * base;
+            exponent /= 2;
+        }
+        result
+    }
+
+    // self^exponent mod m, in 0..|m|, for a non-negative exponent
+    pub fn mod_pow(&self, exponent: &BigInt, m: &BigInt) -> BigInt {
+        let mut base = self.rem_euclid(m);
+        let mut result = BigInt::one().rem_euclid(m);
+        for i in 0..exponent.bits() {
+            if exponent.digits[(i / 32) as usize] >> (i % 32) & 1 == 1 {
+                result = (result * base.clone()).rem_euclid(m);
+            }
+            base = (base.clone() * base).rem_euclid(m);
+        }
+        result
+    }
+
+    // The x in 0..|m| with self * x ≡ 1 (mod m), when gcd(self, m) = 1
+    pub fn mod_inverse(&self, m: &BigInt) -> Option<BigInt> {
+        let (g, x, _) = extended_gcd(&self.rem_euclid(m), &m.clone().abs());
+        (g == BigInt::one()).then(|| x.rem_euclid(m))
+    }
+
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let (mut a, mut b) = (self.clone().abs(), other.clone().abs());
+        while !b.is_zero() {
+            let r = a.div_rem(&b).1;
+            a = b;
+            b = r;
+        }
+        a
+    }
+}
+
+// g = gcd(a, b) >= 0 with a * x + b * y = g
+pub fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut r0, mut r1) = (a.clone(), b.clone());
+    let (mut x0, mut x1) = (BigInt::one(), BigInt::zero());
+    let (mut y0, mut y1) = (BigInt::zero(), BigInt::one());
+
+    while !r1.is_zero() {
+        let (q, r) = r0.div_rem(&r1);
+        (r0, r1) = (r1, r);
+        (x0, x1) = (x1.clone(), x0 - q.clone() * x1);
+        (y0, y1) = (y1.clone(), y0 - q * y1);
+    }
+    if r0.is_negative() {
+        (-r0, -x0, -y0)
+    } else {
+        (r0, x0, y0)
+    }
+}
+
+// The class x ≡ r (mod lcm(m, n)) satisfying both x ≡ a (mod m) and x ≡ b (mod n), which
+// needs a ≡ b modulo gcd(m, n) when the moduli are not coprime
+pub fn chinese_remainder(
+    (a, m): (&BigInt, &BigInt),
+    (b, n): (&BigInt, &BigInt),
+) -> Option<(BigInt, BigInt)> {
+    let (g, p, _) = extended_gcd(m, n);
+    let (k, r) = (b.clone() - a.clone()).div_rem(&g);
+    if !r.is_zero() {
+        return None;
+    }
+    let n_g = n.div_rem(&g).0;
+    let lcm = m.clone() * n_g.clone();
+    let x = a.clone() + m.clone() * (k * p).rem_euclid(&n_g);
+    Some((x.rem_euclid(&lcm), lcm))
+}
+
+impl From<i128> for BigInt {
+    fn from(n: i128) -> Self {
+        let mut magnitude = n.unsigned_abs();
+        let mut digits = Vec::new();
+        while magnitude > 0 {
+            digits.push(magnitude as u32);
+            magnitude >>= 32;
+        }
+        Self::new(n < 0, digits)
+    }
+}
+
+fn compare_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, &d) in long.iter().enumerate() {
+        let s = d as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+        sum.push(s as u32);
+        carry = s >> 32;
+    }
+    if carry > 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+// a - b for a >= b
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &d) in a.iter().enumerate() {
+        let mut s = d as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if s < 0 {
+            s += 1 << 32;
+            borrow = 1;
+        }
+        difference.push(s as u32);
+    }
+    difference
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut product = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let p = x as u64 * y as u64 + product[i + j] as u64 + carry;
+            product[i + j] = p as u32;
+            carry = p >> 32;
+        }
+        product[i + b.len()] = carry as u32;
+    }
+    product
+}
+
+fn div_rem_small(a: &[u32], d: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut rem = 0u64;
+    for i in (0..a.len()).rev() {
+        let n = (rem << 32) | a[i] as u64;
+        quotient[i] = (n / d as u64) as u32;
+        rem = n % d as u64;
+    }
+    (quotient, rem as u32)
+}
+
+fn shift_left(a: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return a.to_vec();
+    }
+    let mut shifted = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0;
+    for &d in a {
+        shifted.push((d << shift) | carry);
+        carry = d >> (32 - shift);
+    }
+    shifted.push(carry);
+    shifted
+}
+
+// Long division, Knuth's algorithm D
+fn div_rem_magnitude(u: &[u32], v: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if compare_magnitude(u, v) == Ordering::Less {
+        return (Vec::new(), u.to_vec());
+    }
+    if let [d] = v {
+        let (q, r) = div_rem_small(u, *d);
+        return (q, vec![r]);
+    }
+
+    // Scale both so the divisor's top digit has its high bit set, which keeps each
+    // estimated quotient digit at most two too large
+    let n = v.len();
+    let m = u.len() - n;
+    let shift = v[n - 1].leading_zeros();
+    let vn = &shift_left(v, shift)[..n];
+    let mut un = shift_left(u, shift);
+    if un.len() == u.len() {
+        un.push(0);
+    }
+
+    let base = 1u64 << 32;
+    let mut quotient = vec![0u32; m + 1];
+    for j in (0..=m).rev() {
+        let top = ((un[j + n] as u64) << 32) | un[j + n - 1] as u64;
+        let mut qhat = top / vn[n - 1] as u64;
+        let mut rhat = top % vn[n - 1] as u64;
+        while qhat >= base || qhat * vn[n - 2] as u64 > ((rhat << 32) | un[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += vn[n - 1] as u64;
+            if rhat >= base {
+                break;
+            }
+        }
+
+        let mut borrow = 0i64;
+        for i in 0..n {
+            let p = qhat * vn[i] as u64;
+            let t = un[i + j] as i64 - borrow - (p & 0xFFFF_FFFF) as i64;
+            un[i + j] = t as u32;
+            borrow = (p >> 32) as i64 - (t >> 32);
+        }
+        let t = un[j + n] as i64 - borrow;
+        un[j + n] = t as u32;
+
+        quotient[j] = qhat as u32;
+        // Subtracted once too often, add the divisor back
+        if t < 0 {
+            quotient[j] -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let s = un[i + j] as u64 + vn[i] as u64 + carry;
+                un[i + j] = s as u32;
+                carry = s >> 32;
+            }
+            un[j + n] = un[j + n].wrapping_add(carry as u32);
+        }
+    }
+
+    let rem = (0..n)
+        .map(|i| match shift {
+            0 => un[i],
+            _ => (un[i] >> shift) | (un[i + 1] << (32 - shift)),
+        })
+        .collect();
+    (quotient, rem)
+}
+
+impl Add for BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: BigInt) -> BigInt {
+        if self.negative == rhs.negative {
+            return BigInt::new(self.negative, add_magnitude(&self.digits, &rhs.digits));
+        }
+        match compare_magnitude(&self.digits, &rhs.digits) {
+            Ordering::Less => BigInt::new(rhs.negative, sub_magnitude(&rhs.digits, &self.digits)),
+            _ => BigInt::new(self.negative, sub_magnitude(&self.digits, &rhs.digits)),
+        }
+    }
+}
+
+impl Sub for BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: BigInt) -> BigInt {
+        self + -rhs
+    }
+}
+
+impl Mul for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != rhs.negative,
+            mul_magnitude(&self.digits, &rhs.digits),
+        )
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.digits)
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitude(&self.digits, &other.digits),
+            (true, true) => compare_magnitude(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Nine decimal digits at a time, least significant first
+        let mut chunks = Vec::new();
+        let mut magnitude = self.digits.clone();
+        while !magnitude.is_empty() {
+            let (q, r) = div_rem_small(&magnitude, 1_000_000_000);
+            chunks.push(r);
+            magnitude = BigInt::new(false, q).digits;
+        }
+
+        let mut text = String::from(if self.negative { "-" } else { "" });
+        match chunks.split_last() {
+            None => text.push('0'),
+            Some((top, rest)) => {
+                text.push_str(&top.to_string());
+                rest.iter()
+                    .rev()
+                    .for_each(|chunk| text.push_str(&format!("{chunk:09}")));
+            }
+        }
+        f.pad(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        let negative = text.starts_with('-');
+        let value = text
+            .trim_start_matches('-')
+            .bytes()
+            .fold(BigInt::zero(), |acc, b| {
+                acc * BigInt::from(10) + BigInt::from((b - b'0') as i128)
+            });
+        if negative { -value } else { value }
+    }
+
+    #[test]
+    fn prints_and_reads_decimals() {
+        for text in [
+            "0",
+            "7",
+            "-42",
+            "4294967296",
+            "-1000000000000000000000000000001",
+        ] {
+            assert_eq!(big(text).to_string(), text);
+        }
+        assert_eq!(BigInt::from(-5).to_i128(), Some(-5));
+        assert_eq!(
+            big("340282366920938463463374607431768211456").to_i128(),
+            None
+        );
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = big("123456789012345678901234567890");
+        let b = big("-987654321098765432109876543210");
+        assert_eq!(
+            (a.clone() + b.clone()).to_string(),
+            "-864197532086419753208641975320"
+        );
+        assert_eq!(
+            (a.clone() - b.clone()).to_string(),
+            "1111111110111111111011111111100"
+        );
+        assert_eq!(
+            (a.clone() * b.clone()).to_string(),
+            "-121932631137021795226185032733622923332237463801111263526900"
+        );
+        assert_eq!(
+            BigInt::from(2).pow(100).to_string(),
+            "1267650600228229401496703205376"
+        );
+        assert!(b < a);
+    }
+
+    #[test]
+    fn divides_with_truncation() {
+        let a = big("121932631137021795226185032733622923332237463801111263526907");
+        let b = big("987654321098765432109876543210");
+        let (q, r) = a.div_rem(&b);
+        assert_eq!(q.to_string(), "123456789012345678901234567890");
+        assert_eq!(r.to_string(), "7");
+
+        let (q, r) = BigInt::from(-7).div_rem(&BigInt::from(2));
+        assert_eq!((q, r), (BigInt::from(-3), BigInt::from(-1)));
+        assert_eq!(
+            BigInt::from(-7).rem_euclid(&BigInt::from(2)),
+            BigInt::from(1)
+        );
+
+        // Every quotient digit estimate corrected, with divisors just over a power of 2^32
+        let n = BigInt::from(2).pow(200) - BigInt::one();
+        let d = BigInt::from(2).pow(64) + BigInt::one();
+        let (q, r) = n.div_rem(&d);
+        assert_eq!(q * d + r, n);
+    }
+
+    #[test]
+    fn modular_powers_and_gcd() {
+        let m = BigInt::from(1_000_000_007);
+        assert_eq!(
+            BigInt::from(2).mod_pow(&BigInt::from(1_000_000_006), &m),
+            BigInt::one()
+        );
+        assert_eq!(
+            BigInt::from(3).mod_pow(&BigInt::from(200), &BigInt::from(50)),
+            BigInt::from(1)
+        );
+        assert_eq!(BigInt::from(-12).gcd(&BigInt::from(18)), BigInt::from(6));
+
+        let (a, b) = (BigInt::from(240), BigInt::from(-46));
+        let (g, x, y) = extended_gcd(&a, &b);
+        assert_eq!(g, BigInt::from(2));
+        assert_eq!(a * x + b * y, g);
+        assert_eq!(
+            BigInt::from(3).mod_inverse(&BigInt::from(11)),
+            Some(BigInt::from(4))
+        );
+        assert_eq!(
+            BigInt::from(-3).mod_inverse(&BigInt::from(11)),
+            Some(BigInt::from(7))
+        );
+        assert_eq!(BigInt::from(6).mod_inverse(&BigInt::from(9)), None);
+
+        let class = |r: i128, m: i128| (BigInt::from(r), BigInt::from(m));
+        let (a, b) = (class(2, 3), class(3, 5));
+        let both = chinese_remainder((&a.0, &a.1), (&b.0, &b.1));
+        assert_eq!(both, Some(class(8, 15)));
+        let (a, b) = (class(3, 4), class(5, 6));
+        assert_eq!(
+            chinese_remainder((&a.0, &a.1), (&b.0, &b.1)),
+            Some(class(11, 12))
+        );
+        let (a, b) = (class(2, 4), class(1, 6));
+        assert_eq!(chinese_remainder((&a.0, &a.1), (&b.0, &b.1)), None);
+    }
+}
@@ -0,0 +1,326 @@
+use std::fmt;
+
+// A closed interval [lo, hi]. Every operation rounds its bounds outwards, so the result
+// always encloses the exact result for any choice of points in the operands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub lo: f32,
+    pub hi: f32,
+}
+
+impl Interval {
+    pub fn new(lo: f32, hi: f32) -> Result<Self, String> {
+        if lo.is_nan() || hi.is_nan() || lo > hi {
+            return Err(format!("invalid interval [{lo}, {hi}]"));
+        }
+        Ok(Self { lo, hi })
+    }
+
+    // The interval for bounds written as decimals. A decimal such as 0.1 that no f32 holds
+    // exactly was rounded to the nearest f32 on reading, so its bound steps outwards by one
+    pub fn from_decimals(lo: f32, hi: f32) -> Result<Self, String> {
+        let inexact = |x: f32| x.is_finite() && x.to_string().parse::<f64>() != Ok(x as f64);
+        let lo = if inexact(lo) { lo.next_down() } else { lo };
+        let hi = if inexact(hi) { hi.next_up() } else { hi };
+        Self::new(lo, hi)
+    }
+
+    pub fn point(x: f32) -> Self {
+        Self { lo: x, hi: x }
+    }
+
+    pub fn entire() -> Self {
+        Self {
+            lo: f32::NEG_INFINITY,
+            hi: f32::INFINITY,
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.hi - self.lo
+    }
+
+    pub fn midpoint(&self) -> f32 {
+        self.lo + (self.hi - self.lo) / 2.0
+    }
+
+    pub fn contains(&self, x: f32) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        self.lo.is_finite() && self.hi.is_finite()
+    }
+
+    pub fn bisect(&self) -> (Self, Self) {
+        let mid = self.midpoint();
+        (
+            Self {
+                lo: self.lo,
+                hi: mid,
+            },
+            Self {
+                lo: mid,
+                hi: self.hi,
+            },
+        )
+    }
+
+    pub fn abs(self) -> Self {
+        if self.lo >= 0.0 {
+            self
+        } else if self.hi <= 0.0 {
+            -self
+        } else {
+            Self {
+                lo: 0.0,
+                hi: self.hi.max(-self.lo),
+            }
+        }
+    }
+
+    pub fn pow(self, exponent: f32) -> Result<Self, String> {
+        if exponent.fract() == 0.0 && exponent.abs() <= i32::MAX as f32 {
+            let n = exponent as i32;
+            let power = self.powi(n.unsigned_abs());
+            return Ok(if n < 0 {
+                Self::point(1.0) / power
+            } else {
+                power
+            });
+        }
+
+        if self.lo < 0.0 {
+            return Err(format!(
+                "cannot raise {self}, which contains negative numbers, to the power {exponent}"
+            ));
+        }
+
+        // powf is not correctly rounded, so widen by a couple of ulps instead
+        let (a, b) = (self.lo.powf(exponent), self.hi.powf(exponent));
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        Ok(Self {
+            lo: lo.next_down().next_down().max(0.0),
+            hi: hi.next_up().next_up(),
+        })
+    }
+
+    fn product(self, rhs: Self) -> Self {
+        let pairs = [
+            (self.lo, rhs.lo),
+            (self.lo, rhs.hi),
+            (self.hi, rhs.lo),
+            (self.hi, rhs.hi),
+        ];
+        Self {
+            lo: pairs
+                .iter()
+                .map(|&(a, b)| mult_down(a, b))
+                .fold(f32::INFINITY, f32::min),
+            hi: pairs
+                .iter()
+                .map(|&(a, b)| mult_up(a, b))
+                .fold(f32::NEG_INFINITY, f32::max),
+        }
+    }
+
+    fn powi(self, n: u32) -> Self {
+        if n.is_multiple_of(2) {
+            let base = self.abs();
+            return Self {
+                lo: pow_down(base.lo, n),
+                hi: pow_up(base.hi, n),
+            };
+        }
+
+        // Odd powers are increasing, and (-x)^n = -(x^n)
+        Self {
+            lo: if self.lo < 0.0 {
+                -pow_up(-self.lo, n)
+            } else {
+                pow_down(self.lo, n)
+            },
+            hi: if self.hi < 0.0 {
+                -pow_down(-self.hi, n)
+            } else {
+                pow_up(self.hi, n)
+            },
+        }
+    }
+}
+
+impl std::ops::Add for Interval {
+    type Output = Interval;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            lo: add_down(self.lo, rhs.lo),
+            hi: add_up(self.hi, rhs.hi),
+        }
+    }
+}
+
+impl std::ops::Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+
+impl std::ops::Mul for Interval {
+    type Output = Interval;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.product(rhs)
+    }
+}
+
+impl std::ops::Div for Interval {
+    type Output = Interval;
+
+    // Dividing by an interval that contains zero can give anything
+    fn div(self, rhs: Self) -> Self {
+        if rhs.contains(0.0) {
+            return Self::entire();
+        }
+        let reciprocal = Self {
+            lo: div_down(1.0, rhs.hi),
+            hi: div_up(1.0, rhs.lo),
+        };
+        self.product(reciprocal)
+    }
+}
+
+impl std::ops::Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Self {
+        Self {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+// Directed rounding from the exact rounding error of each operation:
+// a rounded result above the true value is nudged down for a lower bound, and the other way around.
+
+fn add_down(a: f32, b: f32) -> f32 {
+    let s = a + b;
+    if two_sum_error(a, b, s) < 0.0 {
+        s.next_down()
+    } else {
+        below_overflow(s, a.is_finite() && b.is_finite())
+    }
+}
+
+fn add_up(a: f32, b: f32) -> f32 {
+    let s = a + b;
+    if two_sum_error(a, b, s) > 0.0 {
+        s.next_up()
+    } else {
+        above_overflow(s, a.is_finite() && b.is_finite())
+    }
+}
+
+// A finite result that overflowed to infinity is only known to lie beyond the largest float
+fn below_overflow(x: f32, finite_operands: bool) -> f32 {
+    if finite_operands && x == f32::INFINITY {
+        f32::MAX
+    } else {
+        x
+    }
+}
+
+fn above_overflow(x: f32, finite_operands: bool) -> f32 {
+    if finite_operands && x == f32::NEG_INFINITY {
+        f32::MIN
+    } else {
+        x
+    }
+}
+
+// The exact a + b is s + error
+fn two_sum_error(a: f32, b: f32, s: f32) -> f32 {
+    if !s.is_finite() {
+        return 0.0;
+    }
+    let bb = s - a;
+    (a - (s - bb)) + (b - bb)
+}
+
+fn mult_down(a: f32, b: f32) -> f32 {
+    let p = a * b;
+    if p.is_finite() && a.mul_add(b, -p) < 0.0 {
+        p.next_down()
+    } else if p.is_nan() {
+        // 0 * inf, the bound of a product with an unbounded interval
+        f32::NEG_INFINITY
+    } else {
+        below_overflow(p, a.is_finite() && b.is_finite())
+    }
+}
+
+fn mult_up(a: f32, b: f32) -> f32 {
+    let p = a * b;
+    if p.is_finite() && a.mul_add(b, -p) > 0.0 {
+        p.next_up()
+    } else if p.is_nan() {
+        f32::INFINITY
+    } else {
+        above_overflow(p, a.is_finite() && b.is_finite())
+    }
+}
+
+// x^n for x >= 0
+fn pow_down(x: f32, n: u32) -> f32 {
+    pow_rounded(x, n, mult_down)
+}
+
+fn pow_up(x: f32, n: u32) -> f32 {
+    pow_rounded(x, n, mult_up)
+}
+
+// Square-and-multiply for x >= 0, where rounding every product the same way keeps the bound,
+// so even exponents near i32::MAX take 31 squarings
+fn pow_rounded(x: f32, n: u32, mult: fn(f32, f32) -> f32) -> f32 {
+    let (mut result, mut base, mut bits) = (1.0, x, n);
+    while bits > 0 {
+        if bits & 1 == 1 {
+            result = mult(result, base);
+        }
+        bits >>= 1;
+        if bits > 0 {
+            base = mult(base, base);
+        }
+    }
+    result
+}
+
+fn div_down(a: f32, b: f32) -> f32 {
+    let q = a / b;
+    // The exact a / b is q + r / b
+    let r = (-q).mul_add(b, a);
+    if q.is_finite() && r / b < 0.0 {
+        q.next_down()
+    } else {
+        q
+    }
+}
+
+fn div_up(a: f32, b: f32) -> f32 {
+    let q = a / b;
+    let r = (-q).mul_add(b, a);
+    if q.is_finite() && r / b > 0.0 {
+        q.next_up()
+    } else {
+        q
+    }
+}
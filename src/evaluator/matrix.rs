@@ -0,0 +1,285 @@
+// Pivots smaller than this, relative to the norm of the matrix, are treated as zero by the
+// elimination routines
+const EPSILON: f32 = 1e-6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    pub rows: usize,
+    pub cols: usize,
+    data: Vec<f32>,
+}
+
+impl Matrix {
+    pub fn new(rows: usize, cols: usize, data: Vec<f32>) -> Self {
+        assert_eq!(
+            rows * cols,
+            data.len(),
+            "matrix data does not match its shape"
+        );
+        Self { rows, cols, data }
+    }
+
+    pub fn identity(n: usize) -> Self {
+        let mut data = vec![0.0; n * n];
+        for i in 0..n {
+            data[i * n + i] = 1.0;
+        }
+        Self::new(n, n, data)
+    }
+
+    pub fn from_rows(rows: Vec<Vec<f32>>) -> Self {
+        let cols = rows.first().map_or(0, |row| row.len());
+        Self::new(rows.len(), cols, rows.into_iter().flatten().collect())
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f32 {
+        self.data[row * self.cols + col]
+    }
+
+    pub fn row(&self, row: usize) -> &[f32] {
+        &self.data[row * self.cols..(row + 1) * self.cols]
+    }
+
+    // Every element, row by row
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    pub fn shape(&self) -> String {
+        format!("{}x{}", self.rows, self.cols)
+    }
+
+    pub fn map(&self, f: impl Fn(f32) -> f32) -> Self {
+        Self::new(
+            self.rows,
+            self.cols,
+            self.data.iter().map(|&x| f(x)).collect(),
+        )
+    }
+
+    pub fn zip_with(&self, other: &Matrix, f: impl Fn(f32, f32) -> f32) -> Result<Self, String> {
+        if self.rows != other.rows || self.cols != other.cols {
+            return Err(format!(
+                "dimension mismatch: {} and {}",
+                self.shape(),
+                other.shape()
+            ));
+        }
+
+        let data = self.data.iter().zip(&other.data).map(|(&a, &b)| f(a, b));
+        Ok(Self::new(self.rows, self.cols, data.collect()))
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut data = Vec::with_capacity(self.data.len());
+        for col in 0..self.cols {
+            for row in 0..self.rows {
+                data.push(self.get(row, col));
+            }
+        }
+        Self::new(self.cols, self.rows, data)
+    }
+
+    pub fn mul(&self, other: &Matrix) -> Result<Self, String> {
+        if self.cols != other.rows {
+            return Err(format!(
+                "dimension mismatch: cannot multiply {} by {}",
+                self.shape(),
+                other.shape()
+            ));
+        }
+
+        let mut data = vec![0.0; self.rows * other.cols];
+        for i in 0..self.rows {
+            for j in 0..other.cols {
+                data[i * other.cols + j] = (0..self.cols)
+                    .map(|k| self.get(i, k) * other.get(k, j))
+                    .sum();
+            }
+        }
+        Ok(Self::new(self.rows, other.cols, data))
+    }
+
+    pub fn pow(&self, exponent: i32) -> Result<Self, String> {
+        if !self.is_square() {
+            return Err(format!("cannot raise a {} matrix to a power", self.shape()));
+        }
+
+        let mut base = if exponent < 0 {
+            self.inverse()?
+        } else {
+            self.clone()
+        };
+        // Square-and-multiply, one squaring per bit of the exponent
+        let mut result = Self::identity(self.rows);
+        let mut bits = exponent.unsigned_abs();
+        while bits > 0 {
+            if bits & 1 == 1 {
+                result = result.mul(&base)?;
+            }
+            bits >>= 1;
+            if bits > 0 {
+                base = base.mul(&base)?;
+            }
+        }
+        Ok(result)
+    }
+
+    // The largest absolute row sum
+    fn norm(&self) -> f32 {
+        (0..self.rows)
+            .map(|row| self.row(row).iter().map(|x| x.abs()).sum::<f32>())
+            .fold(0.0, f32::max)
+    }
+
+    pub fn det(&self) -> Result<f32, String> {
+        if !self.is_square() {
+            return Err(format!(
+                "determinant of a non-square {} matrix",
+                self.shape()
+            ));
+        }
+
+        let mut m = self.clone();
+        let mut det = 1.0;
+        let tolerance = EPSILON * self.norm();
+
+        for col in 0..m.cols {
+            let Some(pivot) = m.pivot_row(col, col, tolerance) else {
+                return Ok(0.0);
+            };
+            if pivot != col {
+                m.swap_rows(pivot, col);
+                det = -det;
+            }
+
+            det *= m.get(col, col);
+            m.eliminate_below(col, col);
+        }
+
+        Ok(det)
+    }
+
+    pub fn rank(&self) -> usize {
+        let mut m = self.clone();
+        let mut rank = 0;
+        let tolerance = EPSILON * self.norm();
+
+        for col in 0..m.cols {
+            if rank == m.rows {
+                break;
+            }
+            let Some(pivot) = m.pivot_row(rank, col, tolerance) else {
+                continue;
+            };
+            m.swap_rows(pivot, rank);
+            m.eliminate_below(rank, col);
+            rank += 1;
+        }
+
+        rank
+    }
+
+    pub fn inverse(&self) -> Result<Self, String> {
+        if !self.is_square() {
+            return Err(format!("inverse of a non-square {} matrix", self.shape()));
+        }
+        self.solve(&Self::identity(self.rows))
+    }
+
+    // Solves self * X = rhs with Gauss-Jordan elimination and partial pivoting
+    pub fn solve(&self, rhs: &Matrix) -> Result<Self, String> {
+        if !self.is_square() {
+            return Err(format!(
+                "cannot solve a system with a {} matrix",
+                self.shape()
+            ));
+        }
+        if rhs.rows != self.rows {
+            return Err(format!(
+                "dimension mismatch: {} system with a right-hand side of {} rows",
+                self.shape(),
+                rhs.rows
+            ));
+        }
+
+        let n = self.rows;
+        let width = n + rhs.cols;
+        let mut aug = Vec::with_capacity(n * width);
+        for row in 0..n {
+            aug.extend_from_slice(self.row(row));
+            aug.extend_from_slice(rhs.row(row));
+        }
+        let mut m = Self::new(n, width, aug);
+        let tolerance = EPSILON * self.norm();
+
+        for col in 0..n {
+            let pivot = m
+                .pivot_row(col, col, tolerance)
+                .ok_or("matrix is singular")?;
+            m.swap_rows(pivot, col);
+
+            let p = m.get(col, col);
+            for j in 0..width {
+                m.data[col * width + j] /= p;
+            }
+
+            for row in 0..n {
+                if row != col {
+                    m.subtract_row(col, row, m.get(row, col));
+                }
+            }
+        }
+
+        let mut data = Vec::with_capacity(n * rhs.cols);
+        for row in 0..n {
+            data.extend_from_slice(&m.row(row)[n..]);
+        }
+        Ok(Self::new(n, rhs.cols, data))
+    }
+
+    fn pivot_row(&self, from: usize, col: usize, tolerance: f32) -> Option<usize> {
+        (from..self.rows)
+            .max_by(|&a, &b| self.get(a, col).abs().total_cmp(&self.get(b, col).abs()))
+            .filter(|&row| self.get(row, col).abs() > tolerance)
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        for j in 0..self.cols {
+            self.data.swap(a * self.cols + j, b * self.cols + j);
+        }
+    }
+
+    // row -= factor * source
+    fn subtract_row(&mut self, source: usize, row: usize, factor: f32) {
+        for j in 0..self.cols {
+            let value = self.get(source, j);
+            self.data[row * self.cols + j] -= factor * value;
+        }
+    }
+
+    fn eliminate_below(&mut self, pivot: usize, col: usize) {
+        for row in pivot + 1..self.rows {
+            let factor = self.get(row, col) / self.get(pivot, col);
+            self.subtract_row(pivot, row, factor);
+        }
+    }
+}
+
+impl std::fmt::Display for Matrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for row in 0..self.rows {
+            if row > 0 {
+                write!(f, "; ")?;
+            }
+            let cells: Vec<String> = self.row(row).iter().map(|x| x.to_string()).collect();
+            write!(f, "{}", cells.join(", "))?;
+        }
+        write!(f, "]")
+    }
+}
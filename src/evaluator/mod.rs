@@ -1,26 +1,248 @@
+pub mod derivative;
+pub mod domain;
+pub mod error;
+pub mod function;
+pub mod integer;
+pub mod interval;
+pub mod matrix;
 pub mod operation;
+pub mod partial;
+pub mod prime;
+pub mod value;
 use crate::parser::expr::Expression;
+use crate::parser::{Operator, UnaryOperator};
+use crate::units::{Quantity, parse_unit};
 use operation::Operation;
 use operation::derive_operation;
+use std::collections::{HashMap, HashSet};
 
-pub fn eval_expr(expr: Expression) -> f32 {
-    evaluate(derive_operation(expr))
+pub use domain::Domain;
+pub use error::EvalError;
+pub use integer::BigInt;
+pub use interval::Interval;
+pub use matrix::Matrix;
+pub use value::Value;
+
+#[derive(Debug, Default, Clone)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    // What assume statements said about names, bound or not
+    domains: HashMap<String, Domain>,
+    // The expression of each let that used other names, theirs filled in, so that minimize
+    // can vary those names
+    formulas: HashMap<String, Expression>,
+    // Lets that minimize or argmin vary the names of, which stay formulas while those have
+    // no values
+    objectives: HashSet<String>,
+    // Lets 1/0 and (-1)^0.5 evaluate to inf and NaN instead of failing
+    ieee: bool,
+}
+
+impl Environment {
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    // Binding a value replaces whatever formula the name had
+    pub fn set(&mut self, name: String, value: Value) {
+        self.formulas.remove(&name);
+        self.values.insert(name, value);
+    }
+
+    pub fn formula(&self, name: &str) -> Option<&Expression> {
+        self.formulas.get(name)
+    }
+
+    // Keeps expr as the formula of name, with the formulas of the names it uses filled in.
+    // Expressions of constants alone, or of the name itself, are not formulas
+    pub fn define(&mut self, name: String, expr: &Expression) {
+        let bindings: Vec<(String, Expression)> = (self.formulas.iter())
+            .map(|(n, formula)| (n.clone(), formula.clone()))
+            .collect();
+        let formula = partial::substitute(expr.clone(), &bindings);
+
+        let names = formula.identifiers();
+        match names.is_empty() || names.contains(&name) {
+            true => self.formulas.remove(&name),
+            false => self.formulas.insert(name, formula),
+        };
+    }
+
+    pub fn domain(&self, name: &str) -> Option<&Domain> {
+        self.domains.get(name)
+    }
+
+    pub fn assume(&mut self, name: String, domain: Domain) {
+        self.domains.insert(name, domain);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        self.values.remove(name)
+    }
+
+    pub fn is_objective(&self, name: &str) -> bool {
+        self.objectives.contains(name)
+    }
+
+    pub fn set_objectives(&mut self, names: HashSet<String>) {
+        self.objectives = names;
+    }
+
+    pub fn ieee(&self) -> bool {
+        self.ieee
+    }
+
+    pub fn set_ieee(&mut self, ieee: bool) {
+        self.ieee = ieee;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.values.iter()
+    }
+}
+
+pub fn eval_expr(expr: Expression) -> Result<Value, EvalError> {
+    eval_in(expr, &Environment::default())
 }
 
-pub fn evaluate(operation: Operation) -> f32 {
+pub fn eval_in(expr: Expression, env: &Environment) -> Result<Value, EvalError> {
+    evaluate(derive_operation(expr), env)
+}
+
+pub fn evaluate(operation: Operation, env: &Environment) -> Result<Value, EvalError> {
     match operation {
-        Operation::Number(n) => n,
+        Operation::Number(n) => Ok(Value::Number(n)),
+        Operation::Integer(n) => Ok(Value::integer(n)),
+        Operation::Quantity(n, unit) => {
+            let unit = parse_unit(&unit)?;
+            Ok(Value::quantity(n * unit.factor, unit.dim))
+        }
+        Operation::Variable(name) => env
+            .get(&name)
+            .cloned()
+            .ok_or(EvalError::UndefinedVariable(name)),
+
+        Operation::Add(lhs, rhs) => binary(lhs, Operator::Add, rhs, env, Value::try_add),
+        Operation::Sub(lhs, rhs) => binary(lhs, Operator::Sub, rhs, env, Value::try_sub),
+        Operation::Mult(lhs, rhs) => binary(lhs, Operator::Mult, rhs, env, Value::try_mult),
+        Operation::ElemMult(lhs, rhs) => {
+            binary(lhs, Operator::ElemMult, rhs, env, Value::try_elem_mult)
+        }
+        Operation::Div(lhs, rhs) => binary(lhs, Operator::Div, rhs, env, Value::try_div),
+        Operation::Rem(lhs, rhs) => binary(lhs, Operator::Rem, rhs, env, Value::try_rem),
+        Operation::Pow(lhs, rhs) => binary(lhs, Operator::Pow, rhs, env, Value::try_pow),
+
+        Operation::Negative(expr) => Ok(eval_in(*expr, env)?.negative()),
+
+        Operation::Abs(expr) => Ok(eval_in(*expr, env)?.abs()?),
+
+        Operation::Positive(expr) => eval_in(*expr, env),
+
+        Operation::Not(expr) => {
+            let n = eval_in(*expr, env)?.as_number()?;
+            Ok(Value::Number(if n == 0.0 { 1.0 } else { 0.0 }))
+        }
+
+        Operation::Factorial(expr) => {
+            let value = function::factorial(&eval_in((*expr).clone(), env)?)?;
+            check(value, env, || Expression::Unary {
+                op: UnaryOperator::Factorial,
+                rhs: expr,
+            })
+        }
+
+        Operation::Percent(expr) => Ok(eval_in(*expr, env)?.try_mult(Value::Number(0.01))?),
+
+        Operation::Degree(expr) => {
+            let radians = Value::Number(std::f32::consts::PI / 180.0);
+            Ok(eval_in(*expr, env)?.try_mult(radians)?)
+        }
+
+        Operation::Vector(elements) => {
+            let values = elements
+                .into_iter()
+                .map(|e| Ok(eval_in(e, env)?.as_number()?))
+                .collect::<Result<Vec<f32>, EvalError>>()?;
+            Ok(Value::Vector(values))
+        }
 
-        Operation::Add(lhs, rhs) => eval_expr(*lhs) + eval_expr(*rhs),
-        Operation::Sub(lhs, rhs) => eval_expr(*lhs) - eval_expr(*rhs),
-        Operation::Mult(lhs, rhs) => eval_expr(*lhs) * eval_expr(*rhs),
-        Operation::Div(lhs, rhs) => eval_expr(*lhs) / eval_expr(*rhs),
-        Operation::Rem(lhs, rhs) => eval_expr(*lhs) % eval_expr(*rhs),
-        Operation::Pow(lhs, rhs) => eval_expr(*lhs).powf(eval_expr(*rhs)),
+        Operation::Matrix(rows) => {
+            let rows = rows
+                .into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|e| Ok(eval_in(e, env)?.as_number()?))
+                        .collect::<Result<Vec<f32>, EvalError>>()
+                })
+                .collect::<Result<Vec<Vec<f32>>, EvalError>>()?;
+            Ok(Value::Matrix(Matrix::from_rows(rows)))
+        }
 
-        Operation::Negative(expr) => -eval_expr(*expr),
+        Operation::Call(name, args) => {
+            let values = args
+                .iter()
+                .map(|e| eval_in(e.clone(), env))
+                .collect::<Result<Vec<Value>, EvalError>>()?;
+            let value = function::call_function(&name, values)?;
+            check(value, env, || Expression::Call { name, args })
+        }
 
-        Operation::Abs(expr) => eval_expr(*expr).abs(),
+        Operation::Convert(expr, name) => {
+            let unit = parse_unit(&name)?;
+            let value = eval_in(*expr, env)?;
+
+            if value.dimension() != unit.dim {
+                return Err(format!("cannot convert {} to {name}", value.kind()).into());
+            }
+            Ok(Value::Quantity(Quantity {
+                value: value.scalar()?,
+                dim: unit.dim,
+                display: Some(name),
+            }))
+        }
+
+        Operation::Equation(lhs, rhs) => {
+            Err(format!("{lhs} = {rhs} is an equation, not a value").into())
+        }
+        Operation::Range(range) => Err(format!("{range} is a range, not a value").into()),
+    }
+}
+
+fn binary(
+    lhs: Box<Expression>,
+    op: Operator,
+    rhs: Box<Expression>,
+    env: &Environment,
+    apply: fn(Value, Value) -> Result<Value, String>,
+) -> Result<Value, EvalError> {
+    let l = eval_in((*lhs).clone(), env)?;
+    let r = eval_in((*rhs).clone(), env)?;
+    let expr = || Expression::Binary { lhs, op, rhs };
+
+    if env.ieee {
+        return check(apply(l, r)?, env, expr);
+    }
+    // 0/0 has no value at all, while 0^-1 is 1/0 written another way
+    match op {
+        Operator::Div if l.is_zero() && r.is_zero() => Err(EvalError::Domain(expr())),
+        Operator::Div | Operator::Rem if r.is_zero() => Err(EvalError::DivisionByZero(expr())),
+        Operator::Pow if l.is_zero() && r.as_number().is_ok_and(|e| e < 0.0) => {
+            Err(EvalError::DivisionByZero(expr()))
+        }
+        _ => check(apply(l, r)?, env, expr),
+    }
+}
+
+// Outside IEEE mode a NaN or infinity is an error about the expression that produced it
+fn check(
+    value: Value,
+    env: &Environment,
+    expr: impl FnOnce() -> Expression,
+) -> Result<Value, EvalError> {
+    match value.non_finite() {
+        Some(n) if !env.ieee && n.is_nan() => Err(EvalError::Domain(expr())),
+        Some(_) if !env.ieee => Err(EvalError::Overflow(expr())),
+        _ => Ok(value),
     }
 }
 
@@ -80,7 +302,7 @@ mod tests {
             lhs: num(10.0),
             rhs: num(3.0),
         };
-        assert_eq!(eval_expr(expr), 1.0);
+        assert_eq!(eval_expr(expr).unwrap(), Value::Number(1.0));
     }
 
     #[test]
@@ -90,38 +312,108 @@ mod tests {
             lhs: num(2.0),
             rhs: num(3.0),
         };
-        assert_eq!(eval_expr(expr), 8.0);
+        assert_eq!(eval_expr(expr).unwrap(), Value::Number(8.0));
     }
 
     #[test]
     fn test_negative_number() {
         let expr = Expression::Unary {
-            op: "-".into(),
+            op: UnaryOperator::Neg,
             rhs: num(5.0),
         };
-        assert_eq!(eval_expr(expr), -5.0);
+        assert_eq!(eval_expr(expr).unwrap(), Value::Number(-5.0));
     }
 
     #[test]
     fn test_negative_expression() {
         let expr = Expression::Unary {
-            op: "-".into(),
+            op: UnaryOperator::Neg,
             rhs: Box::new(Expression::Binary {
                 op: Operator::Add,
                 lhs: num(2.0),
                 rhs: num(3.0),
             }),
         };
-        assert_eq!(eval_expr(expr), -5.0);
+        assert_eq!(eval_expr(expr).unwrap(), Value::Number(-5.0));
     }
 
     #[test]
     fn test_absolute_value() {
         let expr = Expression::Unary {
-            op: "modulus".into(),
+            op: UnaryOperator::Abs,
             rhs: num(-7.0),
         };
-        assert_eq!(eval_expr(expr), 7.0);
+        assert_eq!(eval_expr(expr).unwrap(), Value::Number(7.0));
+    }
+
+    #[test]
+    fn test_unary_operators() {
+        let unary = |op, n| Expression::Unary { op, rhs: num(n) };
+
+        assert_eq!(
+            eval_expr(unary(UnaryOperator::Plus, -2.0)).unwrap(),
+            Value::Number(-2.0)
+        );
+        assert_eq!(
+            eval_expr(unary(UnaryOperator::Not, 0.0)).unwrap(),
+            Value::Number(1.0)
+        );
+        assert_eq!(
+            eval_expr(unary(UnaryOperator::Not, 3.0)).unwrap(),
+            Value::Number(0.0)
+        );
+        assert_eq!(
+            eval_expr(unary(UnaryOperator::Factorial, 5.0)).unwrap(),
+            Value::Number(120.0)
+        );
+        assert!(matches!(
+            eval_expr(unary(UnaryOperator::Factorial, -1.0)),
+            Err(EvalError::Domain(_))
+        ));
+        assert!(matches!(
+            eval_expr(unary(UnaryOperator::Factorial, 20000.0)),
+            Err(EvalError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn test_exact_integers() {
+        let eval = |input: &str| {
+            let tokens = crate::lexer::tokenize(input.to_string()).unwrap();
+            let expr = crate::parser::parse_expression(&mut tokens.into_iter().peekable());
+            eval_expr(expr.unwrap()).unwrap().to_string()
+        };
+
+        assert_eq!(eval("2^100"), "1267650600228229401496703205376");
+        assert_eq!(
+            eval("40!"),
+            "815915283247897734345611269596115894272000000000"
+        );
+        assert_eq!(eval("(2^64 + 1) - 2^64"), "1");
+        assert_eq!(eval("100!/98!"), "9900");
+        assert_eq!(eval("-(2^70) % 1000"), "-424");
+        assert_eq!(eval("12345678901234567891 * 10"), "123456789012345678910");
+        assert_eq!(eval("2^70 / 2^80"), "0.0009765625");
+        assert_eq!(eval("mod_pow(3, 10^18, 1000000007)"), "246336683");
+        assert_eq!(eval("binomial(100, 50)"), "100891344545564193334812497256");
+        assert_eq!(eval("binomial(5, 7)"), "0");
+        assert_eq!(eval("isprime(2^89 - 1)"), "1");
+        assert_eq!(eval("isprime(2^89 + 1)"), "0");
+    }
+
+    #[test]
+    fn test_postfix_operators() {
+        let unary = |op, n| eval_expr(Expression::Unary { op, rhs: num(n) }).unwrap();
+
+        let Value::Number(half) = unary(UnaryOperator::Factorial, 0.5) else {
+            panic!("expected a number");
+        };
+        assert!((half - std::f32::consts::PI.sqrt() / 2.0).abs() < 1e-5);
+        assert_eq!(unary(UnaryOperator::Percent, 50.0), Value::Number(0.5));
+        assert_eq!(
+            unary(UnaryOperator::Degree, 180.0),
+            Value::Number(std::f32::consts::PI)
+        );
     }
 
     #[test]
@@ -136,6 +428,189 @@ mod tests {
             }),
         };
 
-        assert_eq!(eval_expr(expr), -5.0);
+        assert_eq!(eval_expr(expr).unwrap(), Value::Number(-5.0));
+    }
+
+    fn eval_str(input: &str, env: &Environment) -> Result<Value, EvalError> {
+        let tokens = crate::lexer::tokenize(input.to_string()).unwrap();
+        let expr = crate::parser::parse_expression(&mut tokens.into_iter().peekable()).unwrap();
+        eval_in(expr, env)
+    }
+
+    #[test]
+    fn test_matrix_vector_product() {
+        let value = eval_str("[1, 2; 3, 4] * [1, 1]", &Environment::default());
+        assert_eq!(value.unwrap(), Value::Vector(vec![3.0, 7.0]));
+    }
+
+    #[test]
+    fn test_elementwise_product() {
+        let value = eval_str("[1, 2; 3, 4] .* [2, 2; 2, 2]", &Environment::default());
+        assert_eq!(
+            value.unwrap(),
+            eval_str("[2, 4; 6, 8]", &Environment::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_linear_algebra_functions() {
+        let mut env = Environment::default();
+        env.set(
+            "A".into(),
+            eval_str("[2, 1; 1, 3]", &Environment::default()).unwrap(),
+        );
+
+        assert_eq!(eval_str("det(A)", &env).unwrap(), Value::Number(5.0));
+        assert_eq!(eval_str("rank(A)", &env).unwrap(), Value::Number(2.0));
+        assert_eq!(
+            eval_str("rank([1, 2; 2, 4])", &env).unwrap(),
+            Value::Number(1.0)
+        );
+        assert_eq!(
+            eval_str("transpose([1, 2; 3, 4])", &env).unwrap(),
+            eval_str("[1, 3; 2, 4]", &env).unwrap()
+        );
+        assert_eq!(
+            eval_str("A * inv(A)", &env).unwrap(),
+            eval_str("[1, 0; 0, 1]", &env).unwrap()
+        );
+        assert_eq!(
+            eval_str("solve(A, [3, 5])", &env).unwrap(),
+            Value::Vector(vec![0.8, 1.4])
+        );
+    }
+
+    #[test]
+    fn test_matrix_powers_and_small_entries() {
+        let env = Environment::default();
+        assert_eq!(
+            eval_str("[1, 1; 0, 1]^1073741824", &env).unwrap(),
+            eval_str("[1, 1073741824; 0, 1]", &env).unwrap()
+        );
+        assert_eq!(
+            eval_str("[2, 0; 0, 2]^-3", &env).unwrap(),
+            eval_str("[0.125, 0; 0, 0.125]", &env).unwrap()
+        );
+        assert!(eval_str("[1, 0; 0, 1]^1e10", &env).is_err());
+
+        // Small entries are not singular when the whole matrix is small
+        assert_eq!(
+            eval_str("rank([1e-7, 0; 0, 2e-7])", &env).unwrap(),
+            Value::Number(2.0)
+        );
+        assert_eq!(
+            eval_str("inv([1e-7, 0; 0, 1e-7])", &env).unwrap(),
+            eval_str("[1e7, 0; 0, 1e7]", &env).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_dimension_mismatch() {
+        let env = Environment::default();
+
+        assert!(eval_str("[1, 2] + [1, 2, 3]", &env).is_err());
+        assert!(eval_str("[1, 2; 3, 4] * [1, 2, 3]", &env).is_err());
+        assert!(eval_str("det([1, 2, 3; 4, 5, 6])", &env).is_err());
+        assert!(eval_str("inv([1, 2; 2, 4])", &env).is_err());
+    }
+
+    #[test]
+    fn test_unit_propagation() {
+        let env = Environment::default();
+
+        assert_eq!(
+            eval_str("2 kg * 9.81 m/s^2", &env).unwrap().to_string(),
+            "19.62 N"
+        );
+        assert_eq!(eval_str("(6 m) / (2 m)", &env).unwrap(), Value::Number(3.0));
+        assert_eq!(
+            eval_str("(3 m)^2 in cm^2", &env).unwrap().to_string(),
+            "90000 cm^2"
+        );
+        assert!(eval_str("3 m + 2 s", &env).is_err());
+        assert!(eval_str("3 m in s", &env).is_err());
+    }
+
+    #[test]
+    fn test_interval_enclosures() {
+        let mut env = Environment::default();
+        env.set(
+            "x".into(),
+            Value::Interval(Interval::new(-1.0, 2.0).unwrap()),
+        );
+
+        assert_eq!(
+            eval_str("x^2", &env).unwrap(),
+            Value::Interval(Interval::new(0.0, 4.0).unwrap())
+        );
+        assert_eq!(
+            eval_str("|x - 3|", &env).unwrap(),
+            Value::Interval(Interval::new(1.0, 4.0).unwrap())
+        );
+
+        // Neither bound of a third is exact, so the enclosure must be rounded outwards
+        let Value::Interval(third) = eval_str("interval(1, 1) / 3", &env).unwrap() else {
+            panic!("expected an interval");
+        };
+        assert!((third.lo as f64) < 1.0 / 3.0 && 1.0 / 3.0 < third.hi as f64);
+
+        // 0.1 and 0.2 read as the nearest f32, which lie above and below them
+        let Value::Interval(tenths) = eval_str("interval(0.1, 0.2)", &env).unwrap() else {
+            panic!("expected an interval");
+        };
+        assert!((tenths.lo as f64) < 0.1 && 0.2 < tenths.hi as f64);
+
+        // Powers take one squaring per bit of the exponent and still round outwards
+        assert_eq!(
+            eval_str("interval(1, 1)^2000000000", &env).unwrap(),
+            Value::Interval(Interval::new(1.0, 1.0).unwrap())
+        );
+        let Value::Interval(cube) = eval_str("interval(-1.1, 1.1)^3", &env).unwrap() else {
+            panic!("expected an interval");
+        };
+        assert!((cube.lo as f64) < -1.331 && 1.331 < cube.hi as f64);
+        assert_eq!(
+            eval_str("interval(0.5, 2)", &env).unwrap(),
+            Value::Interval(Interval::new(0.5, 2.0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_errors() {
+        let env = Environment::default();
+        let error = |input: &str| eval_str(input, &env).unwrap_err().to_string();
+
+        assert_eq!(error("1 + 1/(2 - 2)"), "division by zero in 1/(2 - 2)");
+        assert_eq!(error("[1, 2] % 0"), "division by zero in [1, 2]%0");
+        assert_eq!(error("0^-1"), "division by zero in 0^(-1)");
+        assert_eq!(error("0.0^-0.5"), "division by zero in 0^(-0.5)");
+        assert!(matches!(eval_str("0/0", &env), Err(EvalError::Domain(_))));
+        assert!(matches!(eval_str("0.0/0", &env), Err(EvalError::Domain(_))));
+        assert_eq!(eval_str("0^0", &env).unwrap().to_string(), "1");
+        assert!(matches!(
+            eval_str("2 * (-1)^0.5", &env),
+            Err(EvalError::Domain(Expression::Binary {
+                op: Operator::Pow,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            eval_str("1e30 * 1e30", &env),
+            Err(EvalError::Overflow(_))
+        ));
+        assert_eq!(
+            eval_str("x + 1", &env),
+            Err(EvalError::UndefinedVariable("x".into()))
+        );
+    }
+
+    #[test]
+    fn test_ieee_mode() {
+        let mut env = Environment::default();
+        env.set_ieee(true);
+
+        assert_eq!(eval_str("1/0", &env).unwrap(), Value::Number(f32::INFINITY));
+        assert!(eval_str("0/0", &env).unwrap().as_number().unwrap().is_nan());
+        assert!(eval_str("x", &env).is_err());
     }
 }
@@ -1,39 +1,70 @@
-use crate::parser::{Expression, Operator};
+use crate::evaluator::BigInt;
+use crate::parser::{Expression, Operator, UnaryOperator};
 
 #[derive(Debug, PartialEq)]
 pub enum Operation {
     Add(Box<Expression>, Box<Expression>),
     Sub(Box<Expression>, Box<Expression>),
     Mult(Box<Expression>, Box<Expression>),
+    ElemMult(Box<Expression>, Box<Expression>),
     Div(Box<Expression>, Box<Expression>),
     Rem(Box<Expression>, Box<Expression>),
     Pow(Box<Expression>, Box<Expression>),
 
     Abs(Box<Expression>),
     Negative(Box<Expression>),
+    Positive(Box<Expression>),
+    Factorial(Box<Expression>),
+    Not(Box<Expression>),
+    Percent(Box<Expression>),
+    Degree(Box<Expression>),
 
+    Vector(Vec<Expression>),
+    Matrix(Vec<Vec<Expression>>),
+    Call(String, Vec<Expression>),
+
+    Convert(Box<Expression>, String),
+    Equation(Box<Expression>, Box<Expression>),
+    Range(Expression),
+
+    Variable(String),
     Number(f32),
+    Integer(BigInt),
+    Quantity(f32, String),
 }
 
 pub fn derive_operation(expr: Expression) -> Operation {
     match expr {
         Expression::Number(n) => Operation::Number(n),
+        Expression::Integer(n) => Operation::Integer(n),
+        Expression::Ident(name) => Operation::Variable(name),
+        Expression::Quantity { value, unit } => Operation::Quantity(value, unit),
 
         Expression::Binary { op, lhs, rhs } => match op {
             Operator::Add => Operation::Add(lhs, rhs),
             Operator::Sub => Operation::Sub(lhs, rhs),
             Operator::Mult => Operation::Mult(lhs, rhs),
+            Operator::ElemMult => Operation::ElemMult(lhs, rhs),
             Operator::Div => Operation::Div(lhs, rhs),
             Operator::Rem => Operation::Rem(lhs, rhs),
             Operator::Pow => Operation::Pow(lhs, rhs),
         },
 
-        Expression::Unary { op, rhs } => match op.as_str() {
-            "-" => Operation::Negative(rhs),
-            "modulus" => Operation::Abs(rhs),
-            _ => unreachable!("unknown unary operator"),
+        Expression::Unary { op, rhs } => match op {
+            UnaryOperator::Neg => Operation::Negative(rhs),
+            UnaryOperator::Plus => Operation::Positive(rhs),
+            UnaryOperator::Not => Operation::Not(rhs),
+            UnaryOperator::Abs => Operation::Abs(rhs),
+            UnaryOperator::Factorial => Operation::Factorial(rhs),
+            UnaryOperator::Percent => Operation::Percent(rhs),
+            UnaryOperator::Degree => Operation::Degree(rhs),
         },
 
-        _ => unreachable!("unknown expression"),
+        Expression::Vector(elements) => Operation::Vector(elements),
+        Expression::Matrix(rows) => Operation::Matrix(rows),
+        Expression::Call { name, args } => Operation::Call(name, args),
+        Expression::Convert { expr, unit } => Operation::Convert(expr, unit),
+        Expression::Equation { lhs, rhs } => Operation::Equation(lhs, rhs),
+        range @ Expression::Range { .. } => Operation::Range(range),
     }
 }
@@ -0,0 +1,381 @@
+use crate::evaluator::function::FUNCTIONS;
+use crate::evaluator::{Environment, Value, eval_in};
+use crate::parser::{Expression, Operator, UnaryOperator};
+use crate::units::parse_unit;
+
+// Replaces every occurrence of the bound names, all at once, so subs(x, x = y, y = x) swaps them
+pub fn substitute(expr: Expression, bindings: &[(String, Expression)]) -> Expression {
+    match expr {
+        Expression::Ident(name) => match bindings.iter().find(|(n, _)| *n == name) {
+            Some((_, value)) => value.clone(),
+            None => Expression::Ident(name),
+        },
+        expr => expr
+            .map_children(|e| Ok(substitute(e, bindings)))
+            .expect("substitution cannot fail"),
+    }
+}
+
+// Evaluates every part of the expression whose names are all known in env and keeps the
+// rest symbolic, dropping identities such as x * 1 and x + 0 along the way. What is assumed
+// about a name settles signs, so sqrt(x^2) is x once x >= 0
+pub fn partial_eval(expr: Expression, env: &Environment) -> Result<Expression, String> {
+    let expr = expr.map_children(|e| partial_eval(e, env))?;
+
+    let known = expr
+        .identifiers()
+        .iter()
+        .all(|name| env.get(name).is_some())
+        && !calls_unknown_function(&expr);
+    if known
+        && !matches!(expr, Expression::Equation { .. } | Expression::Range { .. })
+        && let Some(folded) = value_expression(&eval_in(expr.clone(), env)?)
+    {
+        return Ok(folded);
+    }
+
+    Ok(match expr {
+        Expression::Binary { lhs, op, rhs } => collect_constants(identity(*lhs, op, *rhs)),
+        Expression::Unary {
+            op: UnaryOperator::Abs,
+            rhs,
+        } => abs_identity(*rhs, env),
+        Expression::Unary { op, rhs } => unary_identity(op, *rhs),
+        // sqrt(x^2) is |x|
+        Expression::Call { name, mut args } if name == "sqrt" && args.len() == 1 => {
+            match args.pop().expect("one argument") {
+                Expression::Binary {
+                    lhs,
+                    op: Operator::Pow,
+                    rhs,
+                } if matches!(*rhs, Expression::Number(2.0)) => abs_identity(*lhs, env),
+                arg => Expression::Call {
+                    name,
+                    args: vec![arg],
+                },
+            }
+        }
+        expr => expr,
+    })
+}
+
+// f(1) stays as written when f is no built-in function, as do the expressions around it
+fn calls_unknown_function(expr: &Expression) -> bool {
+    if let Expression::Call { name, .. } = expr
+        && !FUNCTIONS.contains(&name.as_str())
+    {
+        return true;
+    }
+    let mut found = false;
+    let _ = expr.clone().map_children(|e| {
+        found = found || calls_unknown_function(&e);
+        Ok(e)
+    });
+    found
+}
+
+// Sums and products are flattened, and the numbers spread through them combined into one,
+// so 2 + (z + 1) is z + 3 and 2(3z) is 6z. A lone number keeps its place
+fn collect_constants(expr: Expression) -> Expression {
+    let number = |e: &Expression| match e {
+        Expression::Number(n) => Some(*n),
+        _ => None,
+    };
+
+    match expr {
+        Expression::Binary {
+            op: Operator::Add | Operator::Sub,
+            ..
+        } => {
+            let mut terms = Vec::new();
+            sum_terms(expr, false, &mut terms);
+            let constants = terms.iter().filter(|(_, t)| number(t).is_some()).count();
+            if constants >= 2 {
+                let sum: f32 = (terms.iter())
+                    .filter_map(|(negated, t)| number(t).map(|n| if *negated { -n } else { n }))
+                    .sum();
+                terms.retain(|(_, t)| number(t).is_none());
+                if sum != 0.0 || terms.is_empty() {
+                    terms.push((sum < 0.0, Expression::Number(sum.abs())));
+                }
+            }
+
+            let mut terms = terms.into_iter();
+            let (negated, first) = terms.next().expect("a sum has terms");
+            let first = match negated {
+                true => unary_identity(UnaryOperator::Neg, first),
+                false => first,
+            };
+            terms.fold(first, |acc, (negated, term)| Expression::Binary {
+                lhs: Box::new(acc),
+                op: if negated {
+                    Operator::Sub
+                } else {
+                    Operator::Add
+                },
+                rhs: Box::new(term),
+            })
+        }
+        Expression::Binary {
+            op: Operator::Mult, ..
+        } => {
+            let mut factors = Vec::new();
+            product_factors(expr, &mut factors);
+            let constants = factors.iter().filter(|f| number(f).is_some()).count();
+            if constants >= 2 {
+                let product: f32 = factors.iter().filter_map(number).product();
+                factors.retain(|f| number(f).is_none());
+                factors.insert(0, Expression::Number(product));
+            }
+
+            (factors.into_iter())
+                .reduce(|lhs, rhs| identity(lhs, Operator::Mult, rhs))
+                .expect("a product has factors")
+        }
+        expr => expr,
+    }
+}
+
+fn sum_terms(expr: Expression, negated: bool, terms: &mut Vec<(bool, Expression)>) {
+    match expr {
+        Expression::Binary { lhs, op, rhs } if matches!(op, Operator::Add | Operator::Sub) => {
+            sum_terms(*lhs, negated, terms);
+            sum_terms(*rhs, negated != (op == Operator::Sub), terms);
+        }
+        Expression::Unary {
+            op: UnaryOperator::Neg,
+            rhs,
+        } => sum_terms(*rhs, !negated, terms),
+        term => terms.push((negated, term)),
+    }
+}
+
+fn product_factors(expr: Expression, factors: &mut Vec<Expression>) {
+    match expr {
+        Expression::Binary {
+            lhs,
+            op: Operator::Mult,
+            rhs,
+        } => {
+            product_factors(*lhs, factors);
+            product_factors(*rhs, factors);
+        }
+        factor => factors.push(factor),
+    }
+}
+
+// |x| is x or -x when the sign of x is known
+fn abs_identity(rhs: Expression, env: &Environment) -> Expression {
+    if nonnegative(&rhs, env) {
+        rhs
+    } else if nonpositive(&rhs, env) {
+        unary_identity(UnaryOperator::Neg, rhs)
+    } else {
+        Expression::Unary {
+            op: UnaryOperator::Abs,
+            rhs: Box::new(rhs),
+        }
+    }
+}
+
+fn nonnegative(expr: &Expression, env: &Environment) -> bool {
+    match expr {
+        Expression::Number(n) => *n >= 0.0,
+        Expression::Ident(name) => env.domain(name).is_some_and(|d| d.is_nonnegative()),
+        Expression::Unary { op, rhs } => match op {
+            UnaryOperator::Abs => true,
+            UnaryOperator::Neg => nonpositive(rhs, env),
+            UnaryOperator::Plus | UnaryOperator::Percent | UnaryOperator::Degree => {
+                nonnegative(rhs, env)
+            }
+            _ => false,
+        },
+        Expression::Binary { lhs, op, rhs } => match op {
+            Operator::Add => nonnegative(lhs, env) && nonnegative(rhs, env),
+            Operator::Mult | Operator::Div => {
+                (nonnegative(lhs, env) && nonnegative(rhs, env))
+                    || (nonpositive(lhs, env) && nonpositive(rhs, env))
+            }
+            Operator::Pow => {
+                matches!(**rhs, Expression::Number(n) if n.rem_euclid(2.0) == 0.0)
+                    || nonnegative(lhs, env)
+            }
+            _ => false,
+        },
+        Expression::Call { name, .. } => name == "sqrt",
+        _ => false,
+    }
+}
+
+fn nonpositive(expr: &Expression, env: &Environment) -> bool {
+    match expr {
+        Expression::Number(n) => *n <= 0.0,
+        Expression::Ident(name) => env.domain(name).is_some_and(|d| d.is_nonpositive()),
+        Expression::Unary { op, rhs } => match op {
+            UnaryOperator::Neg => nonnegative(rhs, env),
+            UnaryOperator::Plus | UnaryOperator::Percent | UnaryOperator::Degree => {
+                nonpositive(rhs, env)
+            }
+            _ => false,
+        },
+        Expression::Binary { lhs, op, rhs } => match op {
+            Operator::Add => nonpositive(lhs, env) && nonpositive(rhs, env),
+            Operator::Mult | Operator::Div => {
+                (nonnegative(lhs, env) && nonpositive(rhs, env))
+                    || (nonpositive(lhs, env) && nonnegative(rhs, env))
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// +x is x and - -x is x, the other unary operators stay as written
+fn unary_identity(op: UnaryOperator, rhs: Expression) -> Expression {
+    match (op, rhs) {
+        (UnaryOperator::Plus, rhs) => rhs,
+        (
+            UnaryOperator::Neg,
+            Expression::Unary {
+                op: UnaryOperator::Neg,
+                rhs,
+            },
+        ) => *rhs,
+        (op, rhs) => Expression::Unary {
+            op,
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
+fn identity(lhs: Expression, op: Operator, rhs: Expression) -> Expression {
+    let is = |e: &Expression, n: f32| matches!(e, Expression::Number(x) if *x == n);
+
+    match op {
+        Operator::Add if is(&lhs, 0.0) => rhs,
+        Operator::Add | Operator::Sub if is(&rhs, 0.0) => lhs,
+        Operator::Sub if is(&lhs, 0.0) => Expression::Unary {
+            op: UnaryOperator::Neg,
+            rhs: Box::new(rhs),
+        },
+        Operator::Mult if is(&lhs, 0.0) || is(&rhs, 0.0) => Expression::Number(0.0),
+        Operator::Mult if is(&lhs, 1.0) => rhs,
+        Operator::Mult | Operator::Div if is(&rhs, 1.0) => lhs,
+        Operator::Pow if is(&rhs, 0.0) => Expression::Number(1.0),
+        Operator::Pow if is(&rhs, 1.0) => lhs,
+        op => Expression::Binary {
+            lhs: Box::new(lhs),
+            op,
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
+// The literal that evaluates back to value, intervals and residues have none
+pub fn value_expression(value: &Value) -> Option<Expression> {
+    match value {
+        Value::Number(n) => Some(Expression::Number(*n)),
+        Value::Quantity(q) => {
+            let name = q.display.clone().unwrap_or(q.dim.to_string());
+            let unit = parse_unit(&name).ok()?;
+            Some(Expression::Quantity {
+                value: q.value / unit.factor,
+                unit: name,
+            })
+        }
+        Value::Vector(elements) => Some(Expression::Vector(
+            elements.iter().map(|&n| Expression::Number(n)).collect(),
+        )),
+        Value::Matrix(m) => Some(Expression::Matrix(
+            (0..m.rows)
+                .map(|i| m.row(i).iter().map(|&n| Expression::Number(n)).collect())
+                .collect(),
+        )),
+        Value::Integer(n) => Some(Expression::Integer(n.clone())),
+        Value::Interval(_) | Value::Residue { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::Domain;
+    use crate::lexer;
+    use crate::parser::parse_expression;
+
+    fn parse(input: &str) -> Expression {
+        let tokens = lexer::tokenize(input.to_string()).unwrap();
+        parse_expression(&mut tokens.into_iter().peekable()).unwrap()
+    }
+
+    fn subs(input: &str, bindings: &[(&str, &str)]) -> String {
+        let bindings: Vec<(String, Expression)> = bindings
+            .iter()
+            .map(|(name, value)| (name.to_string(), parse(value)))
+            .collect();
+        let expr = substitute(parse(input), &bindings);
+        partial_eval(expr, &Environment::default())
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn folds_what_becomes_constant() {
+        assert_eq!(subs("x * y + x", &[("x", "2")]), "2y + 2");
+        assert_eq!(subs("x^2 + y", &[("x", "3"), ("y", "z + 1")]), "z + 10");
+        assert_eq!(subs("x * y", &[("x", "2"), ("y", "4")]), "8");
+        assert_eq!(subs("x + y", &[("x", "2"), ("y", "z + 1")]), "z + 3");
+        assert_eq!(subs("x * (3 * z) - (1 - y)", &[("x", "2")]), "6z - 1 + y");
+        assert_eq!(subs("x - (y - 1)", &[("x", "1")]), "-y + 2");
+    }
+
+    #[test]
+    fn substitutes_inside_calls() {
+        assert_eq!(subs("f(x)", &[("x", "1")]), "f(1)");
+        assert_eq!(
+            subs("2 * g(x + y) + sqrt(x)", &[("x", "4")]),
+            "2g(4 + y) + 2"
+        );
+    }
+
+    #[test]
+    fn substitutes_simultaneously() {
+        assert_eq!(subs("x - y", &[("x", "y"), ("y", "x")]), "y - x");
+    }
+
+    #[test]
+    fn drops_identities() {
+        assert_eq!(subs("(x - a) * y^b", &[("a", "0"), ("b", "1")]), "x*y");
+        assert_eq!(subs("0 - x * c", &[("c", "1")]), "-x");
+        assert_eq!(subs("+x - -(-y)", &[]), "x - y");
+    }
+
+    #[test]
+    fn uses_known_bindings() {
+        let mut env = Environment::default();
+        env.set("g".into(), Value::Number(10.0));
+
+        let expr = partial_eval(parse("m * g * h"), &env).unwrap();
+        assert_eq!(expr.to_string(), "m*10*h");
+    }
+
+    #[test]
+    fn keeps_units() {
+        assert_eq!(subs("x + 1 m", &[("x", "2 m")]), "3 m");
+        assert!(partial_eval(parse("1 m + 1 s"), &Environment::default()).is_err());
+    }
+
+    #[test]
+    fn settles_signs_from_assumptions() {
+        let mut env = Environment::default();
+        env.assume("x".into(), Domain::above(0.0, false));
+        env.assume("y".into(), Domain::below(0.0, true));
+        let simplify = |input: &str| partial_eval(parse(input), &env).unwrap().to_string();
+
+        assert_eq!(simplify("sqrt(x^2)"), "x");
+        assert_eq!(simplify("sqrt(y^2)"), "-y");
+        assert_eq!(simplify("sqrt(z^2)"), "|z|");
+        assert_eq!(simplify("|x*y| + |x^2 + 1|"), "-(x*y) + x^2 + 1");
+        assert_eq!(simplify("sqrt(x)"), "sqrt(x)");
+    }
+}
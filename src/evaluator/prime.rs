@@ -0,0 +1,170 @@
+use crate::evaluator::integer::BigInt;
+
+// The witnesses 2 to 41 make Miller-Rabin exact below 3.3e24, past that a composite
+// slips through with a chance under 4^-13
+const WITNESSES: [i128; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+pub fn is_prime(n: &BigInt) -> bool {
+    if *n < BigInt::from(2) {
+        return false;
+    }
+    for p in WITNESSES {
+        let p = BigInt::from(p);
+        if *n == p {
+            return true;
+        }
+        if n.div_rem(&p).1.is_zero() {
+            return false;
+        }
+    }
+
+    // n - 1 = d * 2^s with d odd
+    let one = BigInt::one();
+    let minus_one = n.clone() - one.clone();
+    let mut d = minus_one.clone();
+    let mut s = 0;
+    while d.is_even() {
+        d = d.div_rem(&BigInt::from(2)).0;
+        s += 1;
+    }
+
+    WITNESSES.iter().all(|&a| {
+        let mut x = BigInt::from(a).mod_pow(&d, n);
+        if x == one || x == minus_one {
+            return true;
+        }
+        for _ in 1..s {
+            x = (x.clone() * x).rem_euclid(n);
+            if x == minus_one {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+// The prime factors of |n| with their multiplicities, smallest first. Trial division takes
+// out the small primes and Pollard's rho splits what is left
+pub fn factor(n: &BigInt) -> Vec<(BigInt, u32)> {
+    let mut rest = n.clone().abs();
+    let mut primes = Vec::new();
+
+    for p in (2..1000).filter(|&p| (2..p).take_while(|d| d * d <= p).all(|d| p % d != 0)) {
+        let p = BigInt::from(p);
+        loop {
+            let (q, r) = rest.div_rem(&p);
+            if !r.is_zero() {
+                break;
+            }
+            primes.push(p.clone());
+            rest = q;
+        }
+    }
+
+    let mut composites = vec![rest];
+    while let Some(m) = composites.pop() {
+        if m <= BigInt::one() {
+            continue;
+        }
+        if is_prime(&m) {
+            primes.push(m);
+            continue;
+        }
+        let d = rho(&m);
+        composites.push(m.div_rem(&d).0);
+        composites.push(d);
+    }
+
+    primes.sort();
+    let mut factors: Vec<(BigInt, u32)> = Vec::new();
+    for p in primes {
+        match factors.last_mut() {
+            Some((last, count)) if *last == p => *count += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    factors
+}
+
+// A proper factor of the composite n, from the cycle of x^2 + c mod n. Differences are
+// multiplied together a batch at a time so that only every 64th step pays for a gcd
+fn rho(n: &BigInt) -> BigInt {
+    let one = BigInt::one();
+
+    for c in 1.. {
+        let step = |x: &BigInt| (x.clone() * x.clone() + BigInt::from(c)).rem_euclid(n);
+        let (mut x, mut y) = (BigInt::from(2), BigInt::from(2));
+
+        loop {
+            let (start_x, start_y) = (x.clone(), y.clone());
+            let mut product = one.clone();
+            for _ in 0..64 {
+                x = step(&x);
+                y = step(&step(&y));
+                product = (product * (x.clone() - y.clone())).rem_euclid(n);
+            }
+
+            let d = product.gcd(n);
+            if d == one {
+                continue;
+            }
+            if d != *n {
+                return d;
+            }
+
+            // The batch overshot, so retrace it one step at a time
+            (x, y) = (start_x, start_y);
+            let d = loop {
+                x = step(&x);
+                y = step(&step(&y));
+                let d = (x.clone() - y.clone()).gcd(n);
+                if d != one {
+                    break d;
+                }
+            };
+            if d != *n {
+                return d;
+            }
+            break;
+        }
+    }
+    unreachable!("some x^2 + c splits every composite")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn factors(n: i128) -> Vec<(i128, u32)> {
+        factor(&BigInt::from(n))
+            .into_iter()
+            .map(|(p, k)| (p.to_i128().unwrap(), k))
+            .collect()
+    }
+
+    #[test]
+    fn tests_primality() {
+        let primes: Vec<i128> = (0..50).filter(|&n| is_prime(&BigInt::from(n))).collect();
+        assert_eq!(
+            primes,
+            [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47]
+        );
+
+        assert!(is_prime(&BigInt::from(1_000_000_007)));
+        // A strong pseudoprime to the bases 2 to 37
+        assert!(!is_prime(&BigInt::from(318_665_857_834_031_151_167_461)));
+        assert!(is_prime(&(BigInt::from(2).pow(127) - BigInt::one())));
+    }
+
+    #[test]
+    fn factors_integers() {
+        assert_eq!(factors(360), [(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(factors(-97), [(97, 1)]);
+        assert_eq!(factors(1), []);
+        assert_eq!(
+            factors(1_000_000_007 * 998_244_353),
+            [(998_244_353, 1), (1_000_000_007, 1)]
+        );
+        assert_eq!(factors(1_009 * 1_009 * 1_013), [(1_009, 2), (1_013, 1)]);
+    }
+}
@@ -0,0 +1,544 @@
+use crate::evaluator::integer::BigInt;
+use crate::evaluator::interval::Interval;
+use crate::evaluator::matrix::Matrix;
+use crate::units::{Dimension, Quantity};
+
+// A magnitude in SI base units with its dimension
+type Scalar = (f32, Dimension);
+
+// 2^24, up to which an f32 holds every whole number exactly
+const EXACT_INTEGERS: f32 = 16_777_216.0;
+
+// The largest result, in bits, that integer powers compute exactly before falling back to floats
+const MAX_POWER_BITS: u64 = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f32),
+    // Whole numbers past what an f32 holds exactly
+    Integer(BigInt),
+    // The class of value mod modulus, with value in 0..modulus
+    Residue { value: BigInt, modulus: BigInt },
+    Quantity(Quantity),
+    Interval(Interval),
+    Vector(Vec<f32>),
+    Matrix(Matrix),
+}
+
+impl Value {
+    // Quantities whose units cancel out become plain numbers
+    pub fn quantity(value: f32, dim: Dimension) -> Value {
+        if dim.is_dimensionless() {
+            Value::Number(value)
+        } else {
+            Value::Quantity(Quantity {
+                value,
+                dim,
+                display: None,
+            })
+        }
+    }
+
+    // Integers small enough for an f32 stay plain numbers
+    pub fn integer(n: BigInt) -> Value {
+        match n.to_i128() {
+            Some(small) if small.unsigned_abs() <= EXACT_INTEGERS as u128 => {
+                Value::Number(small as f32)
+            }
+            _ => Value::Integer(n),
+        }
+    }
+
+    pub fn residue(value: BigInt, modulus: BigInt) -> Value {
+        Value::Residue {
+            value: value.rem_euclid(&modulus),
+            modulus,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<BigInt> {
+        match self {
+            Value::Number(n) if n.abs() <= EXACT_INTEGERS => BigInt::from_f32(*n),
+            Value::Integer(n) => Some(n.clone()),
+            _ => None,
+        }
+    }
+
+    // Integers rounded to the nearest float, for arithmetic that cannot stay exact
+    pub fn float(self) -> Value {
+        match self {
+            Value::Integer(n) => Value::Number(n.to_f32()),
+            value => value,
+        }
+    }
+
+    fn integers(&self, rhs: &Value) -> Option<(BigInt, BigInt)> {
+        Some((self.as_integer()?, rhs.as_integer()?))
+    }
+
+    // The representatives and modulus when either side is a residue class. A whole number
+    // joins the class it falls in, anything else cannot be mixed with one
+    fn residues(&self, rhs: &Value) -> Option<Result<(BigInt, BigInt, BigInt), String>> {
+        let operands = match (self, rhs) {
+            (
+                Value::Residue {
+                    value: a,
+                    modulus: m,
+                },
+                Value::Residue {
+                    value: b,
+                    modulus: n,
+                },
+            ) => match m == n {
+                true => Ok((a.clone(), b.clone(), m.clone())),
+                false => Err(format!("cannot mix residues mod {m} and mod {n}")),
+            },
+            (Value::Residue { value: a, modulus }, other) => match other.as_integer() {
+                Some(b) => Ok((a.clone(), b, modulus.clone())),
+                None => Err(format!(
+                    "cannot mix a residue mod {modulus} with {}",
+                    other.kind()
+                )),
+            },
+            (other, Value::Residue { value: b, modulus }) => match other.as_integer() {
+                Some(a) => Ok((a, b.clone(), modulus.clone())),
+                None => Err(format!(
+                    "cannot mix {} with a residue mod {modulus}",
+                    other.kind()
+                )),
+            },
+            _ => return None,
+        };
+        Some(operands)
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        match self {
+            Value::Quantity(q) => q.dim,
+            _ => Dimension::default(),
+        }
+    }
+
+    // The magnitude of a number or quantity, in SI base units
+    pub fn scalar(&self) -> Result<f32, String> {
+        self.scalar_parts()
+            .map(|(value, _)| value)
+            .ok_or(format!("expected a number, got {}", self.kind()))
+    }
+
+    fn scalar_parts(&self) -> Option<Scalar> {
+        match self {
+            Value::Number(n) => Some((*n, Dimension::default())),
+            Value::Integer(n) => Some((n.to_f32(), Dimension::default())),
+            Value::Quantity(q) => Some((q.value, q.dim)),
+            _ => None,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        matches!(self.scalar_parts(), Some((n, _)) if n == 0.0)
+    }
+
+    // The first inf or NaN in a number, quantity, vector or matrix. Intervals are left out
+    // as their bounds are allowed to be infinite
+    pub fn non_finite(&self) -> Option<f32> {
+        let numbers = match self {
+            Value::Number(n) => std::slice::from_ref(n),
+            Value::Integer(_) | Value::Residue { .. } => &[],
+            Value::Quantity(q) => std::slice::from_ref(&q.value),
+            Value::Vector(v) => v.as_slice(),
+            Value::Matrix(m) => m.data(),
+            Value::Interval(_) => &[],
+        };
+        numbers.iter().copied().find(|n| !n.is_finite())
+    }
+
+    fn is_quantity(&self) -> bool {
+        matches!(self, Value::Quantity(_))
+    }
+
+    // Numbers take part in interval arithmetic as single points
+    pub fn as_interval(&self) -> Result<Interval, String> {
+        match self {
+            Value::Number(n) => Ok(Interval::point(*n)),
+            Value::Integer(n) => Ok(Interval::point(n.to_f32())),
+            Value::Interval(i) => Ok(*i),
+            other => Err(format!(
+                "cannot mix an interval with {}, only plain numbers are supported",
+                other.kind()
+            )),
+        }
+    }
+
+    fn is_interval(&self) -> bool {
+        matches!(self, Value::Interval(_))
+    }
+
+    pub fn as_number(&self) -> Result<f32, String> {
+        match self {
+            Value::Number(n) => Ok(*n),
+            Value::Integer(n) => Ok(n.to_f32()),
+            other => Err(format!("expected a number, got {}", other.kind())),
+        }
+    }
+
+    // Vectors take part in matrix operations as columns
+    pub fn as_matrix(&self) -> Result<Matrix, String> {
+        match self {
+            Value::Matrix(m) => Ok(m.clone()),
+            Value::Vector(v) => Ok(Matrix::new(v.len(), 1, v.clone())),
+            other => Err(format!("expected a matrix, got {}", other.kind())),
+        }
+    }
+
+    pub fn kind(&self) -> String {
+        match self {
+            Value::Number(_) => String::from("a number"),
+            Value::Integer(_) => String::from("an integer"),
+            Value::Residue { modulus, .. } => format!("a residue mod {modulus}"),
+            Value::Quantity(q) => format!("a quantity in {}", q.dim),
+            Value::Interval(_) => String::from("an interval"),
+            Value::Vector(v) => format!("a vector of length {}", v.len()),
+            Value::Matrix(m) => format!("a {} matrix", m.shape()),
+        }
+    }
+
+    pub fn try_add(self, rhs: Value) -> Result<Value, String> {
+        if let Some(operands) = self.residues(&rhs) {
+            let (a, b, m) = operands?;
+            return Ok(Value::residue(a + b, m));
+        }
+        match self.integers(&rhs) {
+            Some((a, b)) => Ok(Value::integer(a + b)),
+            None => self.float().add_floats(rhs.float()),
+        }
+    }
+
+    fn add_floats(self, rhs: Value) -> Result<Value, String> {
+        if self.is_interval() || rhs.is_interval() {
+            return Ok(Value::Interval(self.as_interval()? + rhs.as_interval()?));
+        }
+        if self.is_quantity() || rhs.is_quantity() {
+            return self.same_dimension(rhs, "add", |a, b| a + b);
+        }
+        self.elementwise(rhs, "add", |a, b| a + b)
+    }
+
+    pub fn try_sub(self, rhs: Value) -> Result<Value, String> {
+        if let Some(operands) = self.residues(&rhs) {
+            let (a, b, m) = operands?;
+            return Ok(Value::residue(a - b, m));
+        }
+        match self.integers(&rhs) {
+            Some((a, b)) => Ok(Value::integer(a - b)),
+            None => self.float().sub_floats(rhs.float()),
+        }
+    }
+
+    fn sub_floats(self, rhs: Value) -> Result<Value, String> {
+        if self.is_interval() || rhs.is_interval() {
+            return Ok(Value::Interval(self.as_interval()? - rhs.as_interval()?));
+        }
+        if self.is_quantity() || rhs.is_quantity() {
+            return self.same_dimension(rhs, "subtract", |a, b| a - b);
+        }
+        self.elementwise(rhs, "subtract", |a, b| a - b)
+    }
+
+    pub fn try_elem_mult(self, rhs: Value) -> Result<Value, String> {
+        if let Some(operands) = self.residues(&rhs) {
+            let (a, b, m) = operands?;
+            return Ok(Value::residue(a * b, m));
+        }
+        match self.integers(&rhs) {
+            Some((a, b)) => Ok(Value::integer(a * b)),
+            None => self.float().elem_mult_floats(rhs.float()),
+        }
+    }
+
+    fn elem_mult_floats(self, rhs: Value) -> Result<Value, String> {
+        if self.is_quantity() || self.is_interval() || rhs.is_interval() || rhs.is_quantity() {
+            return self.try_mult(rhs);
+        }
+        match (&self, &rhs) {
+            (Value::Number(n), _) => Ok(rhs.scale(*n)),
+            (_, Value::Number(n)) => Ok(self.scale(*n)),
+            _ => self.elementwise(rhs, "multiply", |a, b| a * b),
+        }
+    }
+
+    pub fn try_mult(self, rhs: Value) -> Result<Value, String> {
+        if let Some(operands) = self.residues(&rhs) {
+            let (a, b, m) = operands?;
+            return Ok(Value::residue(a * b, m));
+        }
+        match self.integers(&rhs) {
+            Some((a, b)) => Ok(Value::integer(a * b)),
+            None => self.float().mult_floats(rhs.float()),
+        }
+    }
+
+    fn mult_floats(self, rhs: Value) -> Result<Value, String> {
+        if self.is_interval() || rhs.is_interval() {
+            return Ok(Value::Interval(self.as_interval()? * rhs.as_interval()?));
+        }
+        if self.is_quantity() || rhs.is_quantity() {
+            let ((a, da), (b, db)) = self.quantity_operands(rhs, "multiply")?;
+            return Ok(Value::quantity(a * b, da.checked_mul(db)?));
+        }
+
+        match (self, rhs) {
+            (Value::Number(n), value) | (value, Value::Number(n)) => Ok(value.scale(n)),
+
+            (Value::Vector(a), Value::Vector(b)) => {
+                if a.len() != b.len() {
+                    return Err(format!(
+                        "dimension mismatch: dot product of vectors of length {} and {}",
+                        a.len(),
+                        b.len()
+                    ));
+                }
+                Ok(Value::Number(a.iter().zip(&b).map(|(x, y)| x * y).sum()))
+            }
+
+            // Row vector times matrix
+            (Value::Vector(v), Value::Matrix(m)) => {
+                let row = Matrix::new(1, v.len(), v);
+                Ok(Value::Vector(row.mul(&m)?.row(0).to_vec()))
+            }
+
+            (Value::Matrix(m), Value::Vector(v)) => {
+                let col = Matrix::new(v.len(), 1, v);
+                Ok(Value::Vector(m.mul(&col)?.transpose().row(0).to_vec()))
+            }
+
+            (Value::Matrix(a), Value::Matrix(b)) => Ok(Value::Matrix(a.mul(&b)?)),
+
+            _ => unreachable!("quantities are handled above"),
+        }
+    }
+
+    // Exact when the integers divide evenly, so 100!/98! is 9900 rather than inf/inf
+    pub fn try_div(self, rhs: Value) -> Result<Value, String> {
+        if let Some(operands) = self.residues(&rhs) {
+            let (a, b, m) = operands?;
+            let inverse = b
+                .mod_inverse(&m)
+                .ok_or_else(|| format!("{b} has no inverse mod {m}"))?;
+            return Ok(Value::residue(a * inverse, m));
+        }
+        if let Some((a, b)) = self.integers(&rhs)
+            && !b.is_zero()
+            && let (q, r) = a.div_rem(&b)
+            && r.is_zero()
+        {
+            return Ok(Value::integer(q));
+        }
+        self.float().div_floats(rhs.float())
+    }
+
+    fn div_floats(self, rhs: Value) -> Result<Value, String> {
+        if self.is_interval() || rhs.is_interval() {
+            return Ok(Value::Interval(self.as_interval()? / rhs.as_interval()?));
+        }
+        if self.is_quantity() || rhs.is_quantity() {
+            let ((a, da), (b, db)) = self.quantity_operands(rhs, "divide")?;
+            return Ok(Value::quantity(a / b, da.checked_div(db)?));
+        }
+
+        match rhs {
+            Value::Number(n) => Ok(self.map(|x| x / n)),
+            _ => Err(String::from(
+                "division by a vector or matrix is not defined, use inv or solve",
+            )),
+        }
+    }
+
+    pub fn try_rem(self, rhs: Value) -> Result<Value, String> {
+        if let Some(operands) = self.residues(&rhs) {
+            operands?;
+            return Err(String::from("the remainder of residues is not defined"));
+        }
+        match self.integers(&rhs) {
+            Some((a, b)) if !b.is_zero() => Ok(Value::integer(a.div_rem(&b).1)),
+            _ => self.float().rem_floats(rhs.float()),
+        }
+    }
+
+    fn rem_floats(self, rhs: Value) -> Result<Value, String> {
+        if self.is_interval() || rhs.is_interval() {
+            return Err(String::from("the remainder of intervals is not supported"));
+        }
+        if self.is_quantity() || rhs.is_quantity() {
+            return self.same_dimension(rhs, "take the remainder of", |a, b| a % b);
+        }
+        Ok(Value::Number(self.as_number()? % rhs.as_number()?))
+    }
+
+    pub fn try_pow(self, rhs: Value) -> Result<Value, String> {
+        if let Value::Residue { value, modulus } = &self {
+            let exponent = rhs
+                .as_integer()
+                .ok_or("a residue can only be raised to a whole number")?;
+            let base = match exponent.is_negative() {
+                true => value
+                    .mod_inverse(modulus)
+                    .ok_or_else(|| format!("{value} has no inverse mod {modulus}"))?,
+                false => value.clone(),
+            };
+            return Ok(Value::residue(
+                base.mod_pow(&exponent.abs(), modulus),
+                modulus.clone(),
+            ));
+        }
+        if let Value::Residue { modulus, .. } = &rhs {
+            return Err(format!("exponents cannot be residues mod {modulus}"));
+        }
+        if let Some((base, exponent)) = self.integers(&rhs)
+            && let Some(exponent) = exponent.to_i128().and_then(|e| u64::try_from(e).ok())
+            && base.bits().saturating_mul(exponent) <= MAX_POWER_BITS
+        {
+            return Ok(Value::integer(base.pow(exponent)));
+        }
+        self.float().pow_floats(rhs.float())
+    }
+
+    fn pow_floats(self, rhs: Value) -> Result<Value, String> {
+        let exponent = match rhs {
+            Value::Interval(i) if i.lo == i.hi => i.lo,
+            Value::Interval(_) => return Err(String::from("exponents cannot be intervals")),
+            rhs => rhs.as_number()?,
+        };
+        match self {
+            Value::Interval(i) => Ok(Value::Interval(i.pow(exponent)?)),
+            Value::Number(n) => Ok(Value::Number(n.powf(exponent))),
+            Value::Quantity(q) => Ok(Value::quantity(
+                q.value.powf(exponent),
+                q.dim.pow(exponent)?,
+            )),
+            Value::Matrix(m) if exponent.fract() == 0.0 => {
+                match exponent.abs() <= i32::MAX as f32 {
+                    true => Ok(Value::Matrix(m.pow(exponent as i32)?)),
+                    false => Err(format!("matrix exponent {exponent} is out of range")),
+                }
+            }
+            other => Err(format!(
+                "cannot raise {} to the power {exponent}",
+                other.kind()
+            )),
+        }
+    }
+
+    pub fn negative(self) -> Value {
+        match self {
+            Value::Integer(n) => Value::Integer(-n),
+            Value::Residue { value, modulus } => Value::residue(-value, modulus),
+            Value::Interval(i) => Value::Interval(-i),
+            value => value.map(|x| -x),
+        }
+    }
+
+    // |x| is the absolute value of a number, the length of a vector and the determinant of a matrix
+    pub fn abs(self) -> Result<Value, String> {
+        match self {
+            Value::Number(n) => Ok(Value::Number(n.abs())),
+            Value::Integer(n) => Ok(Value::Integer(n.abs())),
+            Value::Residue { modulus, .. } => {
+                Err(format!("|x| of a residue mod {modulus} is not defined"))
+            }
+            Value::Quantity(q) => Ok(Value::quantity(q.value.abs(), q.dim)),
+            Value::Interval(i) => Ok(Value::Interval(i.abs())),
+            Value::Vector(v) => Ok(Value::Number(v.iter().map(|x| x * x).sum::<f32>().sqrt())),
+            Value::Matrix(m) => Ok(Value::Number(m.det()?)),
+        }
+    }
+
+    fn scale(self, factor: f32) -> Value {
+        self.map(|x| x * factor)
+    }
+
+    fn map(self, f: impl Fn(f32) -> f32) -> Value {
+        match self {
+            Value::Number(n) => Value::Number(f(n)),
+            Value::Integer(n) => Value::Number(f(n.to_f32())),
+            Value::Quantity(q) => Value::Quantity(Quantity {
+                value: f(q.value),
+                ..q
+            }),
+            Value::Residue { .. } => unreachable!("residues are handled by their own operations"),
+            // Mapping the bounds would lose the outward rounding
+            Value::Interval(_) => unreachable!("intervals are handled by their own operations"),
+            Value::Vector(v) => Value::Vector(v.into_iter().map(f).collect()),
+            Value::Matrix(m) => Value::Matrix(m.map(f)),
+        }
+    }
+
+    fn quantity_operands(self, rhs: Value, verb: &str) -> Result<(Scalar, Scalar), String> {
+        match (self.scalar_parts(), rhs.scalar_parts()) {
+            (Some(a), Some(b)) => Ok((a, b)),
+            _ => Err(format!(
+                "cannot {verb} {} and {}, vectors and matrices of quantities are not supported",
+                self.kind(),
+                rhs.kind()
+            )),
+        }
+    }
+
+    fn same_dimension(
+        self,
+        rhs: Value,
+        verb: &str,
+        f: impl Fn(f32, f32) -> f32,
+    ) -> Result<Value, String> {
+        let lhs_kind = self.kind();
+        let rhs_kind = rhs.kind();
+        let ((a, da), (b, db)) = self.quantity_operands(rhs, verb)?;
+
+        if da != db {
+            return Err(format!(
+                "dimension mismatch: cannot {verb} {lhs_kind} and {rhs_kind}"
+            ));
+        }
+        Ok(Value::quantity(f(a, b), da))
+    }
+
+    fn elementwise(
+        self,
+        rhs: Value,
+        verb: &str,
+        f: impl Fn(f32, f32) -> f32,
+    ) -> Result<Value, String> {
+        match (self, rhs) {
+            (Value::Number(a), Value::Number(b)) => Ok(Value::Number(f(a, b))),
+
+            (Value::Vector(a), Value::Vector(b)) if a.len() == b.len() => Ok(Value::Vector(
+                a.into_iter().zip(b).map(|(x, y)| f(x, y)).collect(),
+            )),
+
+            (Value::Matrix(a), Value::Matrix(b)) => Ok(Value::Matrix(a.zip_with(&b, f)?)),
+
+            (lhs, rhs) => Err(format!(
+                "dimension mismatch: cannot {verb} {} and {}",
+                lhs.kind(),
+                rhs.kind()
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{n}"),
+            Value::Integer(n) => write!(f, "{n}"),
+            Value::Residue { value, modulus } => write!(f, "{value} (mod {modulus})"),
+            Value::Quantity(q) => write!(f, "{q}"),
+            Value::Interval(i) => write!(f, "{i}"),
+            Value::Vector(v) => {
+                let cells: Vec<String> = v.iter().map(|x| x.to_string()).collect();
+                write!(f, "[{}]", cells.join(", "))
+            }
+            Value::Matrix(m) => write!(f, "{m}"),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::lexer::{self, Span};
+use crate::parser::Parser;
+
+// Rewrites a program in the canonical style: one statement per line ending in ';', spaced
+// and bracketed the way the printer prints, with each '#' comment kept on the line of the
+// statement it sat on. Returns the lexer and parser errors instead if the program is broken
+pub fn format(content: &str) -> Result<String, Vec<String>> {
+    let mut parser = Parser::with_spans(lexer::tokenize_spanned(content));
+    let statements = parser.run_parser();
+    if !parser.errors().is_empty() {
+        return Err(parser.errors().iter().map(|e| e.to_string()).collect());
+    }
+
+    let lines = parser.statement_lines();
+    let mut trailing: Vec<Vec<String>> = vec![Vec::new(); statements.len()];
+    let mut items: Vec<(usize, usize, String)> = Vec::new();
+
+    // A comment on a line with code belongs to the last statement on that line
+    for (Span { line, .. }, comment) in lexer::comments(content) {
+        match lines
+            .iter()
+            .rposition(|&(first, last)| first <= line && line <= last)
+        {
+            Some(i) => trailing[i].push(comment),
+            None => items.push((line, line, comment)),
+        }
+    }
+
+    for ((statement, &(first, last)), comments) in statements.iter().zip(lines).zip(trailing) {
+        let mut text = format!("{statement};");
+        for comment in comments {
+            text.push_str("  ");
+            text.push_str(&comment);
+        }
+        items.push((first, last, text));
+    }
+    items.sort_by_key(|&(first, last, _)| (first, last));
+
+    let mut output = String::new();
+    let mut previous = None;
+    for (first, last, text) in items {
+        // A run of blank lines between two items becomes one
+        if previous.is_some_and(|previous| first > previous + 1) {
+            output.push('\n');
+        }
+        output.push_str(&text);
+        output.push('\n');
+        previous = Some(last);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_canonically() {
+        let source = "let f->5*x+7*y=10\n\n\n\nlet g -> 8*x + 11*y = 25;f+g=20;\n";
+
+        assert_eq!(
+            format(source).unwrap(),
+            "let f -> 5x + 7y = 10;\n\nlet g -> 8x + 11y = 25;\nf + g = 20;\n"
+        );
+    }
+
+    #[test]
+    fn normalises_number_literals() {
+        assert_eq!(
+            format("1_000 + 0x1F + .5 + 6.02e23").unwrap(),
+            "1000 + 31 + 0.5 + 6.02e23;\n"
+        );
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let source = "# gravity\nlet g -> 9.81 # m/s^2\nh = (g *\n  2) # drop\n# end";
+
+        assert_eq!(
+            format(source).unwrap(),
+            "# gravity\nlet g -> 9.81;  # m/s^2\nh = g*2;  # drop\n# end\n"
+        );
+    }
+
+    #[test]
+    fn is_idempotent() {
+        let source = "# a\nlet x in [0,2] # b\n\nx^2 = 2\nA = [1,2;3,4]";
+        let formatted = format(source).unwrap();
+
+        assert_eq!(format(&formatted).unwrap(), formatted);
+        assert!(format("x = (1").is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use crate::parser::Statement;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+
+// Definitions that depend on each other in a loop, as in a -> b -> a
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle(pub Vec<String>);
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.0.join(" -> "), self.0[0])
+    }
+}
+
+// Which statements each statement needs to have run first. A name used before a let further
+// down the file refers to that let, and a name bound twice refers to the latest binding above
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    // The name each statement binds, if it is a let
+    names: Vec<Option<String>>,
+    dependencies: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    pub fn new(statements: &[Statement]) -> Self {
+        let names: Vec<Option<String>> = statements.iter().map(defines).collect();
+        let mut dependencies: Vec<Vec<usize>> = vec![Vec::new(); statements.len()];
+
+        for (i, statement) in statements.iter().enumerate() {
+            for name in uses(statement) {
+                let lets: Vec<usize> = (0..names.len())
+                    .filter(|&j| names[j].as_deref() == Some(name.as_str()))
+                    .collect();
+
+                // A let that mentions its own name means the binding before it, or itself
+                // if there is none
+                let before = lets.iter().rev().find(|&&j| j < i).copied();
+                let binding = match before {
+                    Some(j) => j,
+                    None if names[i].as_ref() == Some(&name) => i,
+                    None => match lets.iter().find(|&&j| j > i) {
+                        Some(&j) => j,
+                        None => continue,
+                    },
+                };
+                dependencies[i].push(binding);
+
+                // The next binding of the name has to wait until this use has run
+                if let Some(&next) = lets.iter().find(|&&j| j > binding && j != i) {
+                    dependencies[next].push(i);
+                }
+            }
+        }
+
+        // Rebinding a name keeps the bindings in file order
+        for (i, name) in names.iter().enumerate() {
+            if let Some(previous) = (0..i).rev().find(|&j| name.is_some() && names[j] == *name) {
+                dependencies[i].push(previous);
+            }
+        }
+
+        for deps in dependencies.iter_mut() {
+            deps.sort_unstable();
+            deps.dedup();
+        }
+
+        Self {
+            names,
+            dependencies,
+        }
+    }
+
+    // The statements in an order that runs every dependency first, keeping file order
+    // wherever it can, and the cycles whose statements were left out. Statements that only
+    // depend on a cycle still run, so they report what they could not find
+    pub fn order(&self) -> (Vec<usize>, Vec<Cycle>) {
+        let count = self.names.len();
+        let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); count];
+        let mut waiting: Vec<usize> = vec![0; count];
+        for (i, deps) in self.dependencies.iter().enumerate() {
+            for &dep in deps {
+                dependents[dep].push(i);
+                waiting[i] += 1;
+            }
+        }
+
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..count)
+            .filter(|&i| waiting[i] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(count);
+        while let Some(Reverse(i)) = ready.pop() {
+            order.push(i);
+            for &dependent in &dependents[i] {
+                waiting[dependent] -= 1;
+                if waiting[dependent] == 0 {
+                    ready.push(Reverse(dependent));
+                }
+            }
+        }
+
+        let (cycles, in_cycle) = self.cycles(&waiting);
+        order.extend((0..count).filter(|&i| waiting[i] > 0 && !in_cycle[i]));
+        (order, cycles)
+    }
+
+    // Every statement still waiting has a dependency that is waiting too, so following
+    // those dependencies from any of them ends up going round a cycle
+    fn cycles(&self, waiting: &[usize]) -> (Vec<Cycle>, Vec<bool>) {
+        let mut cycles = Vec::new();
+        let mut in_cycle = vec![false; waiting.len()];
+        let mut visited = vec![false; waiting.len()];
+
+        for start in (0..waiting.len()).filter(|&i| waiting[i] > 0) {
+            let mut path = Vec::new();
+            let mut current = start;
+
+            while !visited[current] {
+                visited[current] = true;
+                path.push(current);
+                current = match self.dependencies[current]
+                    .iter()
+                    .find(|&&dep| waiting[dep] > 0)
+                {
+                    Some(&dep) => dep,
+                    None => break,
+                };
+            }
+
+            if let Some(at) = path.iter().position(|&i| i == current) {
+                let members = &path[at..];
+                members.iter().for_each(|&i| in_cycle[i] = true);
+                cycles.push(Cycle(
+                    members
+                        .iter()
+                        .map(|&i| self.names[i].clone().unwrap_or_default())
+                        .collect(),
+                ));
+            }
+        }
+
+        (cycles, in_cycle)
+    }
+
+    // The lets as a Graphviz digraph, with an edge from each name to the names it uses
+    pub fn dot(&self) -> String {
+        let mut lines = vec![String::from("digraph methal {")];
+
+        for name in self.names.iter().flatten() {
+            let line = format!("    \"{name}\";");
+            if !lines.contains(&line) {
+                lines.push(line);
+            }
+        }
+        for (i, name) in self.names.iter().enumerate() {
+            let Some(name) = name else { continue };
+            for dep in &self.dependencies[i] {
+                if let Some(dep_name) = &self.names[*dep]
+                    && dep_name != name
+                {
+                    let line = format!("    \"{name}\" -> \"{dep_name}\";");
+                    if !lines.contains(&line) {
+                        lines.push(line);
+                    }
+                }
+            }
+        }
+
+        lines.push(String::from("}"));
+        lines.join("\n")
+    }
+}
+
+pub(crate) fn defines(statement: &Statement) -> Option<String> {
+    match statement {
+        Statement::Let {
+            name, value: None, ..
+        }
+        | Statement::Interval { name, .. }
+        | Statement::Assume { name, .. } => Some(name.clone()),
+        _ => None,
+    }
+}
+
+pub(crate) fn uses(statement: &Statement) -> Vec<String> {
+    let mut names = match statement {
+        Statement::Let { expr, value, .. } => {
+            let mut names = expr.identifiers();
+            if let Some(value) = value {
+                names.extend(value.identifiers());
+            }
+            names
+        }
+        Statement::Assign { expr, value } => {
+            let mut names = expr.identifiers();
+            names.extend(value.identifiers());
+            names
+        }
+        Statement::Congruence { lhs, rhs, modulus } => {
+            let mut names = lhs.identifiers();
+            names.extend(rhs.identifiers());
+            names.extend(modulus.identifiers());
+            names
+        }
+        Statement::Expr(expr) => expr.identifiers(),
+        Statement::Interval { lo, hi, .. } => {
+            let mut names = lo.identifiers();
+            names.extend(hi.identifiers());
+            names
+        }
+        Statement::Assume { assumptions, .. } => (assumptions.iter())
+            .filter_map(|a| a.bound())
+            .flat_map(|bound| bound.identifiers())
+            .collect(),
+        Statement::Optimize {
+            objective,
+            constraints,
+            ..
+        } => {
+            let mut names = objective.identifiers();
+            for constraint in constraints {
+                names.extend(constraint.lhs.identifiers());
+                names.extend(constraint.rhs.identifiers());
+            }
+            names
+        }
+    };
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+    use crate::parser::Parser;
+
+    fn graph(input: &str) -> Graph {
+        let tokens = lexer::tokenize_spanned(input);
+        Graph::new(&Parser::with_spans(tokens).run_parser())
+    }
+
+    #[test]
+    fn orders_by_dependency() {
+        let (order, cycles) = graph("c = a + b; let a -> b * 2; let b -> 3").order();
+
+        assert_eq!(order, vec![2, 1, 0]);
+        assert!(cycles.is_empty());
+    }
+
+    #[test]
+    fn keeps_rebinding_in_file_order() {
+        let (order, _) = graph("let x -> 1; y = x; let x -> x + 1; x").order();
+
+        assert_eq!(order, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let (order, cycles) = graph("let a -> b + 1; let b -> a; let c -> 2; d = a").order();
+
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].to_string(), "a -> b -> a");
+        assert_eq!(order, vec![2, 3]);
+        assert_eq!(graph("let x -> x").order().1[0].to_string(), "x -> x");
+    }
+
+    #[test]
+    fn prints_dot() {
+        assert_eq!(
+            graph("let a -> b + c; let b -> c; let c -> 1").dot(),
+            "digraph methal {\n    \"a\";\n    \"b\";\n    \"c\";\n    \"a\" -> \"b\";\n    \"a\" -> \"c\";\n    \"b\" -> \"c\";\n}"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+// Spelled-out names of the Greek letters, so alpha and α are the same identifier.
+// Omicron is left out, it is indistinguishable from o.
+const LETTERS: [(&str, char); 35] = [
+    ("alpha", 'α'),
+    ("beta", 'β'),
+    ("gamma", 'γ'),
+    ("delta", 'δ'),
+    ("epsilon", 'ε'),
+    ("zeta", 'ζ'),
+    ("eta", 'η'),
+    ("theta", 'θ'),
+    ("iota", 'ι'),
+    ("kappa", 'κ'),
+    ("lambda", 'λ'),
+    ("mu", 'μ'),
+    ("nu", 'ν'),
+    ("xi", 'ξ'),
+    ("pi", 'π'),
+    ("rho", 'ρ'),
+    ("sigma", 'σ'),
+    ("tau", 'τ'),
+    ("upsilon", 'υ'),
+    ("phi", 'φ'),
+    ("chi", 'χ'),
+    ("psi", 'ψ'),
+    ("omega", 'ω'),
+    ("Gamma", 'Γ'),
+    ("Delta", 'Δ'),
+    ("Theta", 'Θ'),
+    ("Lambda", 'Λ'),
+    ("Xi", 'Ξ'),
+    ("Pi", 'Π'),
+    ("Sigma", 'Σ'),
+    ("Upsilon", 'Υ'),
+    ("Phi", 'Φ'),
+    ("Psi", 'Ψ'),
+    ("Omega", 'Ω'),
+    ("varphi", 'ϕ'),
+];
+
+pub fn letter(name: &str) -> Option<char> {
+    LETTERS.iter().find(|(n, _)| *n == name).map(|(_, c)| *c)
+}
+
+pub fn name(letter: char) -> Option<&'static str> {
+    LETTERS.iter().find(|(_, c)| *c == letter).map(|(n, _)| *n)
+}
@@ -1,17 +1,55 @@
+pub mod error;
+pub mod greek;
 pub mod token;
 
+pub use error::{LexError, Span};
 pub use token::{Token, TokenKind};
 
-pub fn tokenize(content: String) -> Vec<Token> {
+// The tokens alone, or the first literal that could not be read
+pub fn tokenize(content: String) -> Result<Vec<Token>, LexError> {
+    tokenize_spanned(&content)
+        .into_iter()
+        .map(|(token, span)| match token {
+            Token::Malformed { message, .. } => Err(LexError { message, span }),
+            token => Ok(token),
+        })
+        .collect()
+}
+
+// Every token with its span. A literal that cannot be read becomes a Token::Malformed and the
+// rest of the line is still read
+pub fn tokenize_spanned(content: &str) -> Vec<(Token, Span)> {
     let mut token_vec = Vec::new();
 
-    for line in content.lines() {
+    for (number, line) in content.lines().enumerate() {
+        let width = line.chars().count();
         let mut chars = line.chars().peekable();
+        let span = |start: usize, chars: &std::iter::Peekable<std::str::Chars>| Span {
+            line: number + 1,
+            start,
+            end: width - chars.clone().count(),
+        };
 
         while let Some(&c) = chars.peek() {
+            let start = width - chars.clone().count();
+            let starts_number = c.is_ascii_digit()
+                || (c == '.' && chars.clone().nth(1).is_some_and(|c| c.is_ascii_digit()));
+
             let token = match c {
-                '0'..='9' => token::read_number(&mut chars),
-                'a'..='z' | 'A'..='Z' => token::read_string(&mut chars),
+                _ if starts_number => match token::read_number(&mut chars) {
+                    Ok(token) => token,
+                    Err(message) => {
+                        // The rest of the literal goes with it, so 1.2.3 is one error
+                        while chars
+                            .next_if(|&c| c.is_alphanumeric() || c == '.' || c == '_')
+                            .is_some()
+                        {}
+                        let end = width - chars.clone().count();
+                        let text = line.chars().skip(start).take(end - start).collect();
+                        Token::Malformed { text, message }
+                    }
+                },
+                c if token::is_ident_start(c) => token::read_string(&mut chars),
 
                 '+' => {
                     chars.next();
@@ -51,26 +89,96 @@ pub fn tokenize(content: String) -> Vec<Token> {
                     chars.next();
                     Token::Pipe
                 }
+                '!' => {
+                    chars.next();
+                    Token::Bang
+                }
+                '°' => {
+                    chars.next();
+                    Token::Degree
+                }
+                '.' => {
+                    chars.next();
+
+                    if let Some(&c) = chars.peek()
+                        && c == '*'
+                    {
+                        chars.next();
+                        Token::DotMult
+                    } else {
+                        Token::Illegal('.')
+                    }
+                }
 
                 '(' => {
                     chars.next();
-                    Token::LParen
+
+                    // "(mod m)" closes a congruence rather than opening a product
+                    let mut word = chars.clone();
+                    if word.by_ref().take(3).eq("mod".chars())
+                        && word.peek().is_none_or(|c| c.is_whitespace())
+                    {
+                        chars = word;
+                        Token::Modulo
+                    } else {
+                        Token::LParen
+                    }
                 }
                 ')' => {
                     chars.next();
                     Token::RParen
                 }
+                '[' => {
+                    chars.next();
+                    Token::LBracket
+                }
+                ']' => {
+                    chars.next();
+                    Token::RBracket
+                }
+                ',' => {
+                    chars.next();
+                    Token::Comma
+                }
 
                 '=' => {
                     chars.next();
                     Token::Assign
                 }
+                '≡' => {
+                    chars.next();
+                    Token::Congruent
+                }
+                '>' | '<' => {
+                    chars.next();
+                    match (c, chars.next_if_eq(&'=').is_some()) {
+                        ('>', false) => Token::Greater,
+                        ('>', true) => Token::GreaterEq,
+                        (_, false) => Token::Less,
+                        (_, true) => Token::LessEq,
+                    }
+                }
+                '∈' => {
+                    chars.next();
+                    Token::In
+                }
+                '≥' => {
+                    chars.next();
+                    Token::GreaterEq
+                }
+                '≤' => {
+                    chars.next();
+                    Token::LessEq
+                }
 
                 ';' => {
                     chars.next();
                     Token::Semicolon
                 }
 
+                // A comment runs to the end of the line
+                '#' => break,
+
                 // Skip whitespace
                 ' ' | '\t' | '\n' => {
                     chars.next();
@@ -83,21 +191,159 @@ pub fn tokenize(content: String) -> Vec<Token> {
                 }
             };
 
-            token_vec.push(token);
+            // A unit can follow a number, or the 'in' of a conversion. After a number it has to
+            // be set apart by a space, so 5 kN is a force while 3s and 2h stay products that
+            // equations can solve for. The spelling decides, not what is bound: with g = 9.81,
+            // 2 g is still two grams and 2g is 19.62
+            let reads_unit = matches!(token, Token::Number(_) | Token::Integer(_) | Token::In);
+            let spaced = matches!(chars.peek(), Some(' ' | '\t'));
+            token_vec.push((token, span(start, &chars)));
+
+            if reads_unit {
+                let start = width - chars.clone().count();
+                if matches!(
+                    token_vec.last(),
+                    Some((Token::Number(_) | Token::Integer(_), _))
+                ) && token::read_degree(&mut chars)
+                {
+                    let mut degree_span = span(start, &chars);
+                    degree_span.start = degree_span.end - 3;
+                    token_vec.push((Token::Degree, degree_span));
+                } else if (spaced || matches!(token_vec.last(), Some((Token::In, _))))
+                    && let Some(unit) = token::read_unit(&mut chars)
+                {
+                    // The span starts at the unit itself, not the spaces before it
+                    let mut unit_span = span(start, &chars);
+                    unit_span.start = unit_span.end - unit.chars().count();
+                    token_vec.push((Token::Unit(unit), unit_span));
+                }
+            }
+        }
+
+        let end_of_line = Span {
+            line: number + 1,
+            start: width,
+            end: width + 1,
+        };
+        token_vec.push((Token::Newline, end_of_line));
+    }
+
+    line_breaks(percent_signs(token_vec))
+}
+
+// A '%' with no operand after it is a percent sign, as in 50% or x% + 1, rather than a remainder.
+// A sign written against what follows it starts an operand, so 7 % -2 is a remainder while
+// 50% - 2 takes a percentage. A '|' starts one when it opens an absolute value, that is when an
+// even number of '|' come before it in the statement
+fn percent_signs(mut tokens: Vec<(Token, Span)>) -> Vec<(Token, Span)> {
+    let mut bars = 0;
+    for i in 0..tokens.len() {
+        match tokens[i].0 {
+            Token::Pipe => bars += 1,
+            Token::Newline | Token::Semicolon => bars = 0,
+            _ => {}
+        }
+        if tokens[i].0 == Token::Mod && !starts_operand(&tokens[i + 1..], bars) {
+            tokens[i].0 = Token::Percent;
+        }
+    }
+    tokens
+}
+
+fn starts_operand(rest: &[(Token, Span)], bars: usize) -> bool {
+    match rest {
+        [
+            (Token::Minus | Token::Plus | Token::Bang, sign),
+            (_, next),
+            ..,
+        ] if sign.line == next.line && sign.end == next.start => starts_operand(&rest[1..], bars),
+        [(Token::Pipe, _), ..] => bars.is_multiple_of(2),
+        [(next, _), ..] => matches!(
+            next,
+            Token::Number(_)
+                | Token::Integer(_)
+                | Token::Ident(_)
+                | Token::LParen
+                | Token::LBracket
+        ),
+        [] => false,
+    }
+}
+
+// The '#' comments the tokens skip, with their text from the '#' on
+pub fn comments(content: &str) -> Vec<(Span, String)> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(number, line)| {
+            let (before, comment) = line.split_at(line.find('#')?);
+            let start = before.chars().count();
+            Some((
+                Span {
+                    line: number + 1,
+                    start,
+                    end: start + comment.chars().count(),
+                },
+                comment.trim_end().to_string(),
+            ))
+        })
+        .collect()
+}
+
+// A line break ends a statement only when the line leaves nothing open: no bracket, and no
+// operator, comma or keyword still waiting for the rest of the expression. Blank lines and
+// breaks straight after a ';' are dropped. A ';', 'let' or 'assume' starts afresh, so one
+// unclosed bracket cannot swallow the rest of the file
+fn line_breaks(tokens: Vec<(Token, Span)>) -> Vec<(Token, Span)> {
+    let mut kept: Vec<(Token, Span)> = Vec::with_capacity(tokens.len());
+    let mut depth = 0;
+
+    for (token, span) in tokens {
+        match token {
+            Token::LParen | Token::LBracket | Token::Modulo => depth += 1,
+            Token::RParen | Token::RBracket => depth -= 1,
+            Token::Semicolon | Token::Let | Token::Assume => depth = 0,
+            Token::Newline => {
+                let complete = kept.last().is_some_and(|(last, _)| {
+                    matches!(
+                        last,
+                        Token::Number(_)
+                            | Token::Integer(_)
+                            | Token::Ident(_)
+                            | Token::Unit(_)
+                            | Token::Malformed { .. }
+                            | Token::RParen
+                            | Token::RBracket
+                            | Token::Pipe
+                            | Token::Bang
+                            | Token::Percent
+                            | Token::Degree
+                    )
+                });
+                if depth > 0 || !complete {
+                    continue;
+                }
+            }
+            _ => {}
         }
+        kept.push((token, span));
     }
 
-    token_vec
+    if matches!(kept.last(), Some((Token::Newline, _))) {
+        kept.pop();
+    }
+    kept
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::evaluator::BigInt;
 
     #[test]
     fn unit_test() {
         let input = String::from("let f -> 5x + 7y = 1");
-        let tokens = tokenize(input);
+        let tokens = tokenize(input).unwrap();
 
         let rhs = vec![
             Token::Let,
@@ -114,4 +360,268 @@ mod tests {
 
         assert_eq!(tokens, rhs);
     }
+
+    #[test]
+    fn matrix_tokens() {
+        let tokens = tokenize(String::from("[1, 2; 3, 4] .* A")).unwrap();
+
+        let rhs = vec![
+            Token::LBracket,
+            Token::Number(1.0),
+            Token::Comma,
+            Token::Number(2.0),
+            Token::Semicolon,
+            Token::Number(3.0),
+            Token::Comma,
+            Token::Number(4.0),
+            Token::RBracket,
+            Token::DotMult,
+            Token::Ident("A".to_string()),
+        ];
+
+        assert_eq!(tokens, rhs);
+    }
+
+    #[test]
+    fn unit_suffixes() {
+        let tokens = tokenize(String::from("9.81 m/s^2 * 5 kN in ft + 2 m / x")).unwrap();
+
+        let rhs = vec![
+            Token::Number(9.81),
+            Token::Unit("m/s^2".to_string()),
+            Token::Mult,
+            Token::Number(5.0),
+            Token::Unit("kN".to_string()),
+            Token::In,
+            Token::Unit("ft".to_string()),
+            Token::Plus,
+            Token::Number(2.0),
+            Token::Unit("m".to_string()),
+            Token::Div,
+            Token::Ident("x".to_string()),
+        ];
+
+        assert_eq!(tokens, rhs);
+        assert_eq!(
+            tokenize(String::from("3s + 2h")).unwrap(),
+            vec![
+                Token::Number(3.0),
+                Token::Ident("s".into()),
+                Token::Plus,
+                Token::Number(2.0),
+                Token::Ident("h".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn numeric_literals() {
+        let tokens = tokenize(String::from(
+            "1e-3 6.02e23 0x1F 0b1010 0o17 1_000_000 .5 2.5E+2",
+        ))
+        .unwrap();
+
+        let rhs: Vec<Token> = [1e-3, 6.02e23, 31.0, 10.0, 15.0, 1e6, 0.5, 250.0]
+            .into_iter()
+            .map(Token::Number)
+            .collect();
+
+        assert_eq!(tokens, rhs);
+    }
+
+    #[test]
+    fn exponent_needs_digits() {
+        let tokens = tokenize(String::from("2e + 2.*x")).unwrap();
+
+        let rhs = vec![
+            Token::Number(2.0),
+            Token::Ident("e".to_string()),
+            Token::Plus,
+            Token::Number(2.0),
+            Token::DotMult,
+            Token::Ident("x".to_string()),
+        ];
+
+        assert_eq!(tokens, rhs);
+    }
+
+    #[test]
+    fn malformed_numbers_report_their_span() {
+        let error = tokenize(String::from("x = 1 +\ny + 1.2.3")).unwrap_err();
+
+        assert_eq!(error.message, "malformed number '1.2.3'");
+        assert_eq!(
+            error.span,
+            Span {
+                line: 2,
+                start: 4,
+                end: 9,
+            }
+        );
+
+        for input in ["1__0", "1_", "0x", "0b102", "1e39"] {
+            assert!(tokenize(input.to_string()).is_err(), "{input}");
+        }
+
+        // The lexer records the literal and reads on
+        let tokens: Vec<Token> = (tokenize_spanned("x = 1.2.3 + y\nz"))
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+        assert_eq!(
+            tokens[2],
+            Token::Malformed {
+                text: String::from("1.2.3"),
+                message: String::from("malformed number '1.2.3'"),
+            }
+        );
+        assert_eq!(
+            tokens[3..],
+            [
+                Token::Plus,
+                Token::Ident("y".into()),
+                Token::Newline,
+                Token::Ident("z".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn whole_numbers_past_f32_stay_exact() {
+        let tokens = tokenize(String::from(
+            "16777216 16777217 0x1_0000_0000_0000_0000 1e30",
+        ))
+        .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Number(16777216.0),
+                Token::Integer(BigInt::from(16_777_217)),
+                Token::Integer(BigInt::from(1 << 64)),
+                Token::Number(1e30),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_carry_spans() {
+        let tokens = tokenize_spanned("let x -> 9.81 m/s^2");
+        let spans: Vec<(usize, usize)> = tokens.iter().map(|(_, s)| (s.start, s.end)).collect();
+
+        assert_eq!(spans, vec![(0, 3), (4, 5), (6, 8), (9, 13), (14, 19)]);
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let idents = |input: &str| -> Vec<Token> { tokenize(input.to_string()).unwrap() };
+
+        assert_eq!(
+            idents("x1 v_0"),
+            vec![Token::Ident("x1".into()), Token::Ident("v_0".into())]
+        );
+        assert_eq!(idents("x₁"), vec![Token::Ident("x_1".into())]);
+        assert_eq!(idents("theta_max"), vec![Token::Ident("θ_max".into())]);
+        assert_eq!(idents("alpha"), idents("α"));
+        assert_eq!(idents("Δt"), vec![Token::Ident("Δt".into())]);
+        assert_eq!(
+            idents("2 m2"),
+            vec![Token::Number(2.0), Token::Ident("m2".into())]
+        );
+    }
+
+    #[test]
+    fn line_breaks_end_complete_lines() {
+        let tokens = tokenize(String::from("x + 1\n\ny = (2 +\n3)\nz;\n")).unwrap();
+        let breaks = tokens.iter().filter(|t| **t == Token::Newline).count();
+
+        assert_eq!(breaks, 2);
+        assert_eq!(tokens[3], Token::Newline);
+        assert_eq!(tokens.last(), Some(&Token::Semicolon));
+
+        let unclosed = tokenize(String::from("x = [1, 2\nlet a -> 1\na + 1\na + 2")).unwrap();
+        assert_eq!(unclosed.iter().filter(|t| **t == Token::Newline).count(), 2);
+        let unclosed = tokenize(String::from("let b -> (3 * 2;\nlet a -> 1\na + 1")).unwrap();
+        assert_eq!(unclosed.iter().filter(|t| **t == Token::Newline).count(), 1);
+    }
+
+    #[test]
+    fn postfix_signs() {
+        let tokens = tokenize(String::from("x % 3 + 50% - 30° * 2deg + 4!\ny")).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Ident("x".to_string()),
+                Token::Mod,
+                Token::Number(3.0),
+                Token::Plus,
+                Token::Number(50.0),
+                Token::Percent,
+                Token::Minus,
+                Token::Number(30.0),
+                Token::Degree,
+                Token::Mult,
+                Token::Number(2.0),
+                Token::Degree,
+                Token::Plus,
+                Token::Number(4.0),
+                Token::Bang,
+                Token::Newline,
+                Token::Ident("y".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn percent_or_remainder() {
+        let signs = |input: &str| {
+            let tokens = tokenize(input.to_string()).unwrap();
+            (tokens.into_iter())
+                .filter(|t| matches!(t, Token::Mod | Token::Percent))
+                .collect::<Vec<Token>>()
+        };
+
+        assert_eq!(signs("7 % -2"), vec![Token::Mod]);
+        assert_eq!(signs("7 % |-2|"), vec![Token::Mod]);
+        assert_eq!(signs("7 % !x"), vec![Token::Mod]);
+        assert_eq!(signs("50% + 1"), vec![Token::Percent]);
+        assert_eq!(signs("50% - 2"), vec![Token::Percent]);
+        assert_eq!(signs("|50%| * 2"), vec![Token::Percent]);
+    }
+
+    #[test]
+    fn congruences() {
+        let tokens = tokenize(String::from("3x ≡ 2 (mod\n 7)\n(modulus)")).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Number(3.0),
+                Token::Ident("x".to_string()),
+                Token::Congruent,
+                Token::Number(2.0),
+                Token::Modulo,
+                Token::Number(7.0),
+                Token::RParen,
+                Token::Newline,
+                Token::LParen,
+                Token::Ident("modulus".to_string()),
+                Token::RParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn optimization_keywords() {
+        let tokens = tokenize(String::from("maximize x subject to subject >= 0")).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Maximize,
+                Token::Ident("x".to_string()),
+                Token::SubjectTo,
+                Token::Ident("subject".to_string()),
+                Token::GreaterEq,
+                Token::Number(0.0),
+            ]
+        );
+    }
 }
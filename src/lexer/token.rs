@@ -1,3 +1,6 @@
+use crate::evaluator::BigInt;
+use std::fmt;
+
 #[derive(Debug, PartialEq)]
 pub enum Token {
     Plus,
@@ -7,19 +10,49 @@ pub enum Token {
     Mod,
     Pow,
     Pipe,
+    Bang,
+    Percent,
+    Degree,
+    DotMult,
 
     LParen,
     RParen,
+    // The "(mod" of "x ≡ 3 (mod 7)", closed by an RParen
+    Modulo,
+    LBracket,
+    RBracket,
+    Comma,
 
     Number(f32),
+    // Whole literals past 2^24, which an f32 would round
+    Integer(BigInt),
     Ident(String),
+    Unit(String),
 
     Let,
+    Assume,
+    Maximize,
+    Minimize,
+    // The two words of "subject to", which on its own is a name
+    SubjectTo,
+    In,
+    From,
+    To,
+    Step,
     Assign,
+    Congruent,
+    Greater,
+    Less,
+    GreaterEq,
+    LessEq,
     Arrow,
     Semicolon,
+    Newline,
 
     Illegal(char),
+    // A literal that could not be read, such as 1.2.3, with the reason. The lexer carries on
+    // after it and leaves the parser to report it
+    Malformed { text: String, message: String },
 }
 
 #[derive(Debug, PartialEq)]
@@ -32,6 +65,66 @@ pub enum TokenKind {
     Syntax,
 }
 
+// The token as it is written in the source
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Mult => "*",
+            Token::Div => "/",
+            Token::Mod | Token::Percent => "%",
+            Token::Pow => "^",
+            Token::Pipe => "|",
+            Token::Bang => "!",
+            Token::Degree => "°",
+            Token::DotMult => ".*",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::Modulo => "(mod",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Comma => ",",
+            Token::Number(n) => return write!(f, "{n}"),
+            Token::Integer(n) => return write!(f, "{n}"),
+            Token::Ident(s) | Token::Unit(s) => s,
+            Token::Let => "let",
+            Token::Assume => "assume",
+            Token::Maximize => "maximize",
+            Token::Minimize => "minimize",
+            Token::SubjectTo => "subject to",
+            Token::In => "in",
+            Token::From => "from",
+            Token::To => "to",
+            Token::Step => "step",
+            Token::Assign => "=",
+            Token::Congruent => "≡",
+            Token::Greater => ">",
+            Token::Less => "<",
+            Token::GreaterEq => ">=",
+            Token::LessEq => "<=",
+            Token::Arrow => "->",
+            Token::Semicolon => ";",
+            Token::Newline => "\n",
+            Token::Illegal(c) => return write!(f, "{c}"),
+            Token::Malformed { text, .. } => text,
+        };
+        write!(f, "{text}")
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Ident => write!(f, "a name"),
+            TokenKind::Arrow => write!(f, "'->'"),
+            TokenKind::Assign => write!(f, "'='"),
+            TokenKind::Semicolon => write!(f, "';'"),
+            TokenKind::Syntax => write!(f, "a symbol"),
+        }
+    }
+}
+
 impl Token {
     pub fn kind(&self) -> TokenKind {
         match self {
@@ -51,35 +144,267 @@ impl Token {
     }
 }
 
-pub fn read_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Token {
-    let mut num = String::new();
+// Decimals with an optional fraction and exponent, such as 1_000, .5 and 6.02e23, and
+// integers in hex, binary or octal, such as 0x1F, 0b1010 and 0o17
+pub fn read_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Token, String> {
+    let mut prefix = chars.clone();
+    if prefix.next() == Some('0') {
+        let radix = match prefix.peek() {
+            Some('x' | 'X') => Some(16),
+            Some('b' | 'B') => Some(2),
+            Some('o' | 'O') => Some(8),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            prefix.next();
+            *chars = prefix;
+            return read_integer(chars, radix);
+        }
+    }
+
+    let mut text = match chars.peek() {
+        Some('.') => String::from("0"),
+        _ => read_digits(chars)?,
+    };
 
-    while let Some(&c) = chars.peek() {
-        if c.is_ascii_digit() || c == '.' {
-            num.push(c);
-            chars.next();
-        } else {
-            break;
+    if chars.peek() == Some(&'.') && digit_after(chars, 1) {
+        chars.next();
+        text.push('.');
+        text.push_str(&read_digits(chars)?);
+    }
+
+    // Only with digits after it is the e an exponent, 2e on its own is 2 * e
+    if matches!(chars.peek(), Some('e' | 'E')) {
+        let mut exponent = chars.clone();
+        exponent.next();
+        let sign = exponent.next_if(|&c| c == '+' || c == '-');
+        if exponent.peek().is_some_and(|c| c.is_ascii_digit()) {
+            *chars = exponent;
+            text.push('e');
+            text.extend(sign);
+            text.push_str(&read_digits(chars)?);
         }
     }
 
-    Token::Number(num.parse::<f32>().unwrap())
+    // 1.2.3
+    if chars.peek() == Some(&'.') && digit_after(chars, 1) {
+        while let Some(c) = chars.next_if(|&c| c.is_ascii_digit() || c == '.' || c == '_') {
+            text.push(c);
+        }
+        return Err(format!("malformed number '{text}'"));
+    }
+
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(whole_number(&text, 10));
+    }
+
+    match text.parse::<f32>() {
+        Ok(n) if n.is_finite() => Ok(Token::Number(n)),
+        Ok(_) => Err(format!("{text} is too large")),
+        Err(_) => Err(format!("malformed number '{text}'")),
+    }
+}
+
+fn digit_after(chars: &std::iter::Peekable<std::str::Chars>, n: usize) -> bool {
+    chars.clone().nth(n).is_some_and(|c| c.is_ascii_digit())
+}
+
+// Digits with '_' separators between them, returned without the separators
+fn read_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+    let mut text = String::new();
+    while let Some(c) = chars.next_if(|&c| c.is_ascii_digit() || c == '_') {
+        text.push(c);
+    }
+    check_separators(&text)?;
+    Ok(text.replace('_', ""))
+}
+
+fn check_separators(text: &str) -> Result<(), String> {
+    if text.starts_with('_') || text.ends_with('_') || text.contains("__") {
+        return Err(format!("misplaced digit separator in '{text}'"));
+    }
+    Ok(())
+}
+
+fn read_integer(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    radix: u32,
+) -> Result<Token, String> {
+    let (prefix, name) = match radix {
+        16 => ("0x", "hex"),
+        2 => ("0b", "binary"),
+        _ => ("0o", "octal"),
+    };
+
+    let mut text = String::new();
+    while let Some(c) = chars.next_if(|&c| {
+        c == '_' || (radix == 16 && c.is_ascii_hexdigit()) || (radix < 16 && c.is_ascii_digit())
+    }) {
+        text.push(c);
+    }
+
+    if text.is_empty() {
+        return Err(format!("{prefix} needs {name} digits after it"));
+    }
+    check_separators(&text)?;
+    if let Some(c) = text.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+        return Err(format!(
+            "invalid digit '{c}' in {name} literal '{prefix}{text}'"
+        ));
+    }
+
+    Ok(whole_number(&text.replace('_', ""), radix))
+}
+
+// Digits already checked against the radix
+fn whole_number(digits: &str, radix: u32) -> Token {
+    let n = BigInt::parse_radix(digits, radix).expect("digits checked by the caller");
+    match n.to_i128() {
+        Some(small) if small <= 1 << 24 => Token::Number(small as f32),
+        _ => Token::Integer(n),
+    }
+}
+
+// Identifiers start with a letter or '_' and go on with letters, digits and '_'. Letters are
+// anything Unicode counts as alphabetic, which is close to XID without needing its tables.
+pub fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+pub fn is_ident_continue(c: char) -> bool {
+    is_ident_start(c) || c.is_ascii_digit() || subscript_digit(c).is_some()
 }
 
+// ₀ to ₉
+fn subscript_digit(c: char) -> Option<char> {
+    let offset = (c as u32).checked_sub('₀' as u32)?;
+    char::from_digit(offset, 10)
+}
+
+// x₁ is read as x_1 and alpha as α, so each name has a single spelling
 pub fn read_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Token {
     let mut ident = String::new();
+    let mut in_subscript = false;
 
-    while let Some(&c) = chars.peek() {
-        if c.is_ascii_alphabetic() {
-            ident.push(c);
-            chars.next();
-        } else {
-            break;
+    while let Some(c) = chars.next_if(|&c| is_ident_continue(c)) {
+        match subscript_digit(c) {
+            Some(digit) => {
+                if !in_subscript {
+                    ident.push('_');
+                }
+                ident.push(digit);
+                in_subscript = true;
+            }
+            None => {
+                ident.push(c);
+                in_subscript = false;
+            }
         }
     }
 
     match ident.as_str() {
         "let" => Token::Let,
-        _ => Token::Ident(ident),
+        "assume" => Token::Assume,
+        "maximize" => Token::Maximize,
+        "minimize" => Token::Minimize,
+        "subject" if read_word(chars, "to") => Token::SubjectTo,
+        "in" => Token::In,
+        "from" => Token::From,
+        "to" => Token::To,
+        "step" => Token::Step,
+        _ => Token::Ident(canonical_name(ident)),
+    }
+}
+
+fn canonical_name(ident: String) -> String {
+    let (base, subscript) = match ident.split_once('_') {
+        Some((base, subscript)) => (base, Some(subscript)),
+        None => (ident.as_str(), None),
+    };
+
+    match (crate::lexer::greek::letter(base), subscript) {
+        (Some(letter), Some(subscript)) => format!("{letter}_{subscript}"),
+        (Some(letter), None) => letter.to_string(),
+        (None, _) => ident,
+    }
+}
+
+// Skips spaces and the word if it comes next, leaving the input untouched if not
+fn read_word(chars: &mut std::iter::Peekable<std::str::Chars>, word: &str) -> bool {
+    let mut lookahead = chars.clone();
+    while lookahead.next_if(|&c| c == ' ' || c == '\t').is_some() {}
+
+    let next: String = lookahead
+        .clone()
+        .take_while(|&c| is_ident_continue(c))
+        .collect();
+    if next != word {
+        return false;
     }
+    lookahead.nth(word.chars().count() - 1);
+    *chars = lookahead;
+    true
+}
+
+// 30deg or 30 deg, the degree sign spelled out after a number
+pub fn read_degree(chars: &mut std::iter::Peekable<std::str::Chars>) -> bool {
+    read_word(chars, "deg")
+}
+
+// Reads a unit suffix such as "kN" or "m/s^2", leaving the input untouched if none follows
+pub fn read_unit(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    let mut lookahead = chars.clone();
+    while lookahead.next_if(|&c| c == ' ' || c == '\t').is_some() {}
+
+    let mut unit = read_unit_term(&mut lookahead)?;
+    *chars = lookahead.clone();
+
+    // Further terms must be attached without spaces, so "2 m / x" keeps its division
+    while let Some(&op) = lookahead.peek()
+        && (op == '*' || op == '/')
+    {
+        lookahead.next();
+        let Some(term) = read_unit_term(&mut lookahead) else {
+            break;
+        };
+
+        unit.push(op);
+        unit.push_str(&term);
+        *chars = lookahead.clone();
+    }
+
+    Some(unit)
+}
+
+fn read_unit_term(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+        name.push(c);
+    }
+
+    // m2 or kg_1 is a name rather than a unit
+    if !crate::units::is_unit(&name) || chars.peek().is_some_and(|&c| is_ident_continue(c)) {
+        return None;
+    }
+
+    if chars.peek() == Some(&'^') {
+        let mut exponent = chars.clone();
+        exponent.next();
+
+        let mut digits = String::new();
+        if let Some(c) = exponent.next_if_eq(&'-') {
+            digits.push(c);
+        }
+        while let Some(c) = exponent.next_if(|c| c.is_ascii_digit()) {
+            digits.push(c);
+        }
+
+        if digits.parse::<i8>().is_ok() {
+            name.push('^');
+            name.push_str(&digits);
+            *chars = exponent;
+        }
+    }
+
+    Some(name)
 }
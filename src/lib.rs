@@ -1,26 +1,721 @@
+pub mod engine;
 pub mod evaluator;
+pub mod format;
+pub mod graph;
 pub mod lexer;
+pub mod lsp;
 pub mod parser;
+pub mod plot;
+pub mod polynomial;
+pub mod solver;
+pub mod table;
+pub mod units;
 
-use crate::evaluator::eval_expr;
-use crate::parser::Statement;
+use crate::engine::Engine;
+use crate::evaluator::{Domain, Environment, EvalError, Interval, Value, eval_in, partial, prime};
+use crate::graph::Graph;
+use crate::parser::printer::pretty_name;
+use crate::parser::{Assumption, Expression, Goal, NumberSet, Statement};
+use crate::polynomial::Polynomial;
+use crate::solver::interval::Root;
+use crate::solver::linear::{self, Outcome};
+use crate::solver::minimize::{self, Minimum};
+use crate::solver::modular::{self, Classes};
+use crate::table::{Format, Sweep, Table};
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    // Runs the whole program once per value and prints one table of the results
+    pub sweep: Option<Sweep>,
+    pub format: Format,
+    // plot(...) writes an SVG here instead of drawing in the terminal
+    pub plot: Option<String>,
+    // Prints the dependency graph of the lets as DOT instead of running them
+    pub graph: bool,
+    // Division by zero and the like give inf and NaN instead of an error
+    pub ieee: bool,
+}
+
+// One line of output from a statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    Binding(String, Value),
+    // A let of names that have no values yet, kept as its formula
+    Formula(String, Expression),
+    Solution(String, Value),
+    Root(String, Root),
+    // Everything assumed about a name so far
+    Assumption(String, Domain),
+    // A bare expression with its value, or a note about one
+    Value(String, String),
+}
+
+impl Output {
+    // The column this output goes in when sweeping
+    pub fn label(&self) -> String {
+        match self {
+            Output::Binding(name, _)
+            | Output::Formula(name, _)
+            | Output::Solution(name, _)
+            | Output::Root(name, _)
+            | Output::Assumption(name, _)
+            | Output::Value(name, _) => name.clone(),
+        }
+    }
+
+    pub fn value(&self) -> String {
+        match self {
+            Output::Binding(_, value) | Output::Solution(_, value) => value.to_string(),
+            Output::Formula(_, expr) => expr.to_string(),
+            Output::Root(_, root) if root.verified => root.enclosure.to_string(),
+            Output::Root(_, root) => format!("{} (possible root)", root.enclosure),
+            Output::Assumption(name, domain) => domain.describe(name),
+            Output::Value(_, text) => text.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = pretty_name(&self.label());
+        match self {
+            Output::Binding(_, value @ Value::Interval(_)) => write!(f, "let {name} in {value}"),
+            Output::Binding(_, value) => write!(f, "let {name} = {value}"),
+            Output::Formula(_, expr) => write!(f, "let {name} -> {expr}"),
+            Output::Solution(_, Value::Residue { .. }) => write!(f, "{name} ≡ {}", self.value()),
+            Output::Solution(..) => write!(f, "{name} = {}", self.value()),
+            Output::Root(..) => write!(f, "{name} in {}", self.value()),
+            Output::Assumption(_, domain) => write!(f, "assume {}", domain.describe(&name)),
+            Output::Value(_, text) => write!(f, "{text}"),
+        }
+    }
+}
 
 pub fn run(content: String) {
-    let tokens = lexer::tokenize(content);
+    run_with(content, &Options::default())
+}
+
+pub fn run_with(content: String, options: &Options) {
+    let tokens = lexer::tokenize_spanned(&content);
     // println!("{:?}", &tokens);
-    let mut parser = parser::Parser::new(tokens.into_iter());
+    let mut parser = parser::Parser::with_spans(tokens);
     let ast = parser.run_parser();
 
-    for statement in ast.iter() {
-        if let Statement::Let {
+    // The statements that did parse still run
+    for error in parser.errors() {
+        eprintln!("{}", error);
+    }
+
+    let graph = Graph::new(&ast);
+    if options.graph {
+        println!("{}", graph.dot());
+        return;
+    }
+
+    // Lets run before the statements that use them, wherever they are in the file
+    if let Some(sweep) = &options.sweep {
+        let (order, cycles) = graph.order();
+        cycles
+            .iter()
+            .for_each(|cycle| eprintln!("cyclic definition: {}", cycle));
+
+        let ast: Vec<&Statement> = order.iter().map(|&i| &ast[i]).collect();
+        let table = sweep_table(&ast, sweep, options);
+        println!("{}", table.render(options.format));
+        return;
+    }
+
+    let mut engine = Engine::new(options.clone());
+    engine.update(ast);
+    for cycle in engine.cycles() {
+        eprintln!("cyclic definition: {}", cycle);
+    }
+
+    for (_, result) in engine.results() {
+        match result {
+            Ok(outputs) => outputs.iter().for_each(|output| println!("{}", output)),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // println!("{:?}", ast);
+}
+
+pub fn execute(
+    statement: &Statement,
+    env: &mut Environment,
+    options: &Options,
+) -> Result<Vec<Output>, String> {
+    match statement {
+        Statement::Let { name, expr, value } => {
+            // Without a right-hand side the let binds its expression to the name
+            let result = match value {
+                Some(value) => eval_in(value.clone(), env),
+                None => eval_in(expr.clone(), env),
+            };
+
+            // The objective of a minimize(f, x) keeps its formula while x has no value. Any
+            // other let of an unknown name is an error, as it is likely a typo
+            if let (Err(EvalError::UndefinedVariable(_)), None) = (&result, value)
+                && env.is_objective(name)
+            {
+                env.remove(name);
+                env.define(name.clone(), expr);
+                if env.formula(name).is_some() {
+                    return Ok(vec![Output::Formula(name.clone(), expr.clone())]);
+                }
+            }
+            let result = result.map_err(|e| format!("let {}: {}", name, e))?;
+
+            if value.is_none() {
+                env.set(name.clone(), result.clone());
+                env.define(name.clone(), expr);
+            }
+            Ok(vec![Output::Binding(name.clone(), result)])
+        }
+
+        Statement::Assign { expr, value } => solve(expr, value, env),
+
+        Statement::Congruence { lhs, rhs, modulus } => {
+            let (unknown, classes) = congruence(lhs, rhs, modulus, env)?;
+            match classes.residues.as_slice() {
+                [residue] => {
+                    let value = Value::residue(residue.clone(), classes.modulus);
+                    env.set(unknown.clone(), value.clone());
+                    Ok(vec![Output::Solution(unknown, value)])
+                }
+                _ => Ok(vec![Output::Value(
+                    unknown.clone(),
+                    congruence_text(&unknown, &classes),
+                )]),
+            }
+        }
+
+        Statement::Expr(expr) => {
+            let result = symbolic(expr, env, options).unwrap_or_else(|| {
+                match eval_in(expr.clone(), env) {
+                    Ok(value) => Ok(value.to_string()),
+                    // Names with no value but something assumed about them are unknowns, and
+                    // the expression is simplified by what is assumed instead
+                    Err(EvalError::UndefinedVariable(_)) if assumed_unknowns(expr, env) => {
+                        Ok(partial::partial_eval(expr.clone(), env)?.to_string())
+                    }
+                    Err(e) => Err(e.into()),
+                }
+            })?;
+            Ok(vec![Output::Value(expr.to_string(), result)])
+        }
+
+        Statement::Interval { name, lo, hi } => {
+            let bounds = eval_in(lo.clone(), env)
+                .map_err(String::from)
+                .and_then(|lo| lo.as_number())
+                .and_then(|lo| Ok((lo, eval_in(hi.clone(), env)?.as_number()?)));
+
+            let interval = bounds
+                .and_then(|(lo, hi)| Interval::from_decimals(lo, hi))
+                .map_err(|e| format!("let {}: {}", name, e))?;
+
+            let value = Value::Interval(interval);
+            env.set(name.clone(), value.clone());
+            Ok(vec![Output::Binding(name.clone(), value)])
+        }
+
+        // Each assumption narrows what is already assumed about the name
+        Statement::Assume { name, assumptions } => {
+            let bound = |bound: &Expression| -> Result<f32, String> {
+                eval_in(bound.clone(), env)?.scalar()
+            };
+            let domain = |assumption: &Assumption| match assumption {
+                Assumption::Greater(b) => bound(b).map(|b| Domain::above(b, true)),
+                Assumption::AtLeast(b) => bound(b).map(|b| Domain::above(b, false)),
+                Assumption::Less(b) => bound(b).map(|b| Domain::below(b, true)),
+                Assumption::AtMost(b) => bound(b).map(|b| Domain::below(b, false)),
+                Assumption::In(NumberSet::Integers) => Ok(Domain::integers()),
+                Assumption::In(NumberSet::Naturals) => {
+                    Ok(Domain::integers().intersect(Domain::above(0.0, false)))
+                }
+                Assumption::In(NumberSet::Reals) => Ok(Domain::default()),
+            };
+
+            let mut assumed = env.domain(name).copied().unwrap_or_default();
+            for assumption in assumptions {
+                let narrowed = domain(assumption).map_err(|e| format!("assume {}: {}", name, e))?;
+                assumed = assumed.intersect(narrowed);
+            }
+            env.assume(name.clone(), assumed);
+            Ok(vec![Output::Assumption(name.clone(), assumed)])
+        }
+
+        Statement::Optimize {
+            goal,
+            objective,
+            constraints,
+        } => {
+            let (names, outcome) = linear::optimize(*goal, objective, constraints, env)?;
+            let text = optimum_text(*goal, objective, &names, outcome);
+            Ok(vec![Output::Value(objective.to_string(), text)])
+        }
+    }
+}
+
+// Whether every name of expr without a value has an assumption about it
+fn assumed_unknowns(expr: &Expression, env: &Environment) -> bool {
+    (expr.identifiers().iter())
+        .filter(|name| env.get(name).is_none())
+        .all(|name| env.domain(name).is_some())
+}
+
+// Runs the whole program once per value of the parameter, each run filling a row. A let of the
+// parameter itself is skipped so a script can give it a default for normal runs.
+fn sweep_table(ast: &[&Statement], sweep: &Sweep, options: &Options) -> Table {
+    let mut table = Table::new(vec![sweep.name.clone()]);
+
+    for x in sweep.values() {
+        let mut env = Environment::default();
+        env.set_ieee(options.ieee);
+        env.set_objectives(objectives(ast.iter().copied()));
+        env.set(sweep.name.clone(), Value::Number(x));
+        let mut row = vec![x.to_string()];
+
+        for statement in ast {
+            if let Statement::Let {
+                name, value: None, ..
+            } = statement
+                && *name == sweep.name
+            {
+                continue;
+            }
+
+            match execute(statement, &mut env, options) {
+                Ok(outputs) => {
+                    for output in outputs {
+                        let column = table.column(&output.label());
+                        if row.len() <= column {
+                            row.resize(column + 1, String::new());
+                        }
+                        row[column] = output.value();
+                    }
+                }
+                Err(e) => eprintln!("{} = {}: {}", sweep.name, x, e),
+            }
+        }
+
+        table.push_row(row);
+    }
+
+    table
+}
+
+// The names minimize and argmin calls take as their objective, as f in minimize(f, x)
+pub(crate) fn objectives<'a>(
+    statements: impl IntoIterator<Item = &'a Statement>,
+) -> HashSet<String> {
+    statements
+        .into_iter()
+        .filter_map(|statement| match statement {
+            Statement::Expr(Expression::Call { name, args })
+                if name == "minimize" || name == "argmin" =>
+            {
+                match args.first() {
+                    Some(Expression::Ident(objective)) => Some(objective.clone()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+pub const SYMBOLIC_FUNCTIONS: [&str; 12] = [
+    "expand",
+    "factor",
+    "subs",
+    "simplify",
+    "table",
+    "plot",
+    "latex",
+    "factorint",
+    "solve_mod",
+    "diophantine",
+    "minimize",
+    "argmin",
+];
+
+// expand, factor, subs, table and plot work on the expression itself rather than its value,
+// factorint prints a product of primes that no value can hold exactly, solve_mod and
+// diophantine solve equations over the integers, and minimize and argmin vary the names they
+// are given
+fn symbolic(
+    expr: &Expression,
+    env: &Environment,
+    options: &Options,
+) -> Option<Result<String, String>> {
+    let Expression::Call { name, args } = expr else {
+        return None;
+    };
+
+    match (name.as_str(), args.as_slice()) {
+        ("expand", [arg]) => Some(Polynomial::from_expression(arg).map(|p| p.to_string())),
+        ("factor", [arg]) => {
+            Some(Polynomial::from_expression(arg).map(|p| polynomial::factor(&p).to_string()))
+        }
+        ("subs", [arg, bindings @ ..]) => Some(subs(arg, bindings, env)),
+        ("simplify", [arg]) => {
+            Some(partial::partial_eval(arg.clone(), env).map(|expr| expr.to_string()))
+        }
+        ("table", args) => Some(table(args, env, options.format)),
+        ("plot", args) => Some(plot(args, env, options.plot.as_deref())),
+        ("latex", [arg]) => Some(Ok(arg.to_latex())),
+        ("factorint", [arg]) => Some(factorint(arg, env)),
+        ("solve_mod", [equation, modulus]) => {
+            let (lhs, rhs) = equation_sides(equation);
+            Some(
+                congruence(lhs, rhs, modulus, env)
+                    .map(|(unknown, classes)| congruence_text(&unknown, &classes)),
+            )
+        }
+        ("diophantine", [equation]) => {
+            let (lhs, rhs) = equation_sides(equation);
+            Some(diophantine(lhs, rhs, env))
+        }
+        ("minimize" | "argmin", [objective, names @ ..]) => Some(
+            minimize::minimum(objective, names, env).map(|(names, minimum)| {
+                let value = Value::Number(minimum.value as f32);
+                let point = point_text(&names, &minimum);
+                match (name.as_str(), minimum.unbounded) {
+                    (_, true) => format!("unbounded, {objective} has no minimum"),
+                    ("minimize", _) => format!("{value} at {point} ({})", diagnostics(&minimum)),
+                    _ => format!("{point} ({}, minimum {value})", diagnostics(&minimum)),
+                }
+            }),
+        ),
+        ("expand" | "factor" | "simplify" | "latex" | "factorint" | "diophantine", _) => Some(Err(
+            format!("{} takes 1 argument, got {}", name, args.len()),
+        )),
+        ("minimize" | "argmin", []) => Some(Err(format!(
+            "{name} needs an expression and the names to vary, as in {name}(f, x, y)"
+        ))),
+        ("solve_mod", _) => Some(Err(format!(
+            "{} takes 2 arguments, got {}",
             name,
-            expr: _,
-            value,
-        } = statement
+            args.len()
+        ))),
+        _ => None,
+    }
+}
+
+fn subs(expr: &Expression, bindings: &[Expression], env: &Environment) -> Result<String, String> {
+    let bindings = bindings
+        .iter()
+        .map(|binding| match binding {
+            Expression::Equation { lhs, rhs } => match lhs.as_ref() {
+                Expression::Ident(name) => Ok((name.clone(), *rhs.clone())),
+                _ => Err(format!("cannot substitute for {lhs}, only for a name")),
+            },
+            other => Err(format!("expected a substitution like x = 2, got {other}")),
+        })
+        .collect::<Result<Vec<(String, Expression)>, String>>()?;
+
+    let expr = partial::substitute(expr.clone(), &bindings);
+    Ok(partial::partial_eval(expr, env)?.to_string())
+}
+
+// factorint(360) is 2^3*3^2*5, written as a product so that it reads back as one
+fn factorint(expr: &Expression, env: &Environment) -> Result<String, String> {
+    let value = eval_in(expr.clone(), env)?;
+    let n = value.as_integer().ok_or(format!(
+        "factorint expects a whole number, got {}",
+        value.kind()
+    ))?;
+    if n.is_zero() {
+        return Err(String::from("0 has no prime factorisation"));
+    }
+
+    let mut factors: Vec<String> = prime::factor(&n)
+        .into_iter()
+        .map(|(p, k)| match k {
+            1 => p.to_string(),
+            k => format!("{p}^{k}"),
+        })
+        .collect();
+    if n.is_negative() {
+        factors.insert(0, String::from("-1"));
+    }
+    if factors.is_empty() {
+        factors.push(String::from("1"));
+    }
+    Ok(factors.join("*"))
+}
+
+// An equation as its two sides, anything else as an expression equal to 0
+fn equation_sides(expr: &Expression) -> (&Expression, &Expression) {
+    const ZERO: &Expression = &Expression::Number(0.0);
+    match expr {
+        Expression::Equation { lhs, rhs } => (lhs, rhs),
+        expr => (expr, ZERO),
+    }
+}
+
+// The classes of the one unknown with lhs ≡ rhs (mod modulus). A name already bound to a class
+// counts as unknown, and its new classes are joined with the old one so that a run of
+// congruences in x solves them as a system
+fn congruence(
+    lhs: &Expression,
+    rhs: &Expression,
+    modulus: &Expression,
+    env: &Environment,
+) -> Result<(String, Classes), String> {
+    let mut unknowns: Vec<String> = Vec::new();
+    for name in lhs.identifiers().into_iter().chain(rhs.identifiers()) {
+        let open = matches!(env.get(&name), None | Some(Value::Residue { .. }));
+        if open && !unknowns.contains(&name) {
+            unknowns.push(name);
+        }
+    }
+    let unknown = match unknowns.as_slice() {
+        [unknown] => unknown.clone(),
+        [] => {
+            return Err(String::from(
+                "nothing to solve for, every name in the congruence is known",
+            ));
+        }
+        _ => {
+            return Err(format!(
+                "cannot solve for several unknowns: {}",
+                unknowns.join(", ")
+            ));
+        }
+    };
+
+    let m = eval_in(modulus.clone(), env)?;
+    let m = m.as_integer().ok_or(format!(
+        "the modulus must be a whole number, got {}",
+        m.kind()
+    ))?;
+
+    let mut scope = env.clone();
+    let earlier = scope.remove(&unknown);
+    let classes = modular::solve_congruence(lhs, rhs, &m, &unknown, &scope)
+        .map_err(|e| format!("{unknown}: {e}"))?;
+    match earlier {
+        Some(Value::Residue { value, modulus }) => Ok((
+            unknown,
+            classes.intersect(&Classes {
+                residues: vec![value],
+                modulus,
+            }),
+        )),
+        _ => Ok((unknown, classes)),
+    }
+}
+
+fn congruence_text(unknown: &str, classes: &Classes) -> String {
+    let name = pretty_name(unknown);
+    match classes.residues.is_empty() {
+        true => format!("no solutions for {name}"),
+        false => format!("{name} ≡ {classes}"),
+    }
+}
+
+// diophantine(3x + 5y = 1) is x = 5t + 2, y = -3t - 1 for every integer t
+fn diophantine(lhs: &Expression, rhs: &Expression, env: &Environment) -> Result<String, String> {
+    let Some(solutions) = modular::solve_diophantine(lhs, rhs, env)? else {
+        return Ok(String::from("no integer solutions"));
+    };
+    let solutions: Vec<String> = solutions
+        .iter()
+        .map(|(name, p)| format!("{} = {p}", pretty_name(name)))
+        .collect();
+    Ok(solutions.join(", "))
+}
+
+// "optimal value 12 at x = 4, y = 0", the names left unbound so the program can be optimised again
+fn optimum_text(goal: Goal, objective: &Expression, names: &[String], outcome: Outcome) -> String {
+    match outcome {
+        Outcome::Optimal { value, point } => {
+            // Adding 0 turns a -0 left by the pivoting into 0
+            let coordinates: Vec<String> = (names.iter().zip(point))
+                .map(|(name, x)| {
+                    format!("{} = {}", pretty_name(name), Value::Number(x as f32 + 0.0))
+                })
+                .collect();
+            format!(
+                "optimal value {} at {}",
+                Value::Number(value as f32 + 0.0),
+                coordinates.join(", ")
+            )
+        }
+        Outcome::Infeasible => String::from("infeasible, no point meets every constraint"),
+        Outcome::Unbounded => {
+            let extreme = match goal {
+                Goal::Maximize => "maximum",
+                Goal::Minimize => "minimum",
+            };
+            format!("unbounded, {objective} has no {extreme}")
+        }
+    }
+}
+
+fn point_text(names: &[String], minimum: &Minimum) -> String {
+    let coordinates: Vec<String> = (names.iter().zip(&minimum.point))
+        .map(|(name, &x)| format!("{} = {}", pretty_name(name), Value::Number(x as f32)))
+        .collect();
+    coordinates.join(", ")
+}
+
+// How the minimum was found, as in "BFGS, 12 iterations, 30 evaluations"
+fn diagnostics(minimum: &Minimum) -> String {
+    let count = |n: usize, noun: &str| match n {
+        1 => format!("1 {noun}"),
+        n => format!("{n} {noun}s"),
+    };
+    let text = format!(
+        "{}, {}, {}",
+        minimum.method,
+        count(minimum.iterations, "iteration"),
+        count(minimum.evaluations, "evaluation")
+    );
+    match minimum.converged {
+        true => text,
+        false => format!("{text}, stopped before converging"),
+    }
+}
+
+// table(expr, ..., x from 0 to 10 step 0.5) evaluates each expression, or solves each
+// equation, for every value of x with the rest of the environment unchanged. A cell that
+// fails shows its error, and the other cells still fill in
+fn table(args: &[Expression], env: &Environment, format: Format) -> Result<String, String> {
+    let [
+        columns @ ..,
+        Expression::Range {
+            name,
+            from,
+            to,
+            step,
+        },
+    ] = args
+    else {
+        return Err(String::from(
+            "table needs a range such as x from 0 to 10 as its last argument",
+        ));
+    };
+
+    let number = |e: &Expression| eval_in(e.clone(), env)?.as_number();
+    let step = match step {
+        Some(step) => number(step)?,
+        None => 1.0,
+    };
+    let sweep = Sweep::new(name.clone(), number(from)?, number(to)?, step)?;
+
+    let headers = std::iter::once(name.clone()).chain(columns.iter().map(|c| c.to_string()));
+    let mut table = Table::new(headers.collect());
+
+    for x in sweep.values() {
+        let mut env = env.clone();
+        env.set(name.clone(), Value::Number(x));
+
+        let mut row = vec![x.to_string()];
+        for column in columns {
+            let cell = match column {
+                Expression::Equation { lhs, rhs } => solve(lhs, rhs, &mut env)
+                    .map(|outputs| outputs.iter().map(|o| o.value()).collect::<Vec<_>>())
+                    .map(|values| values.join(", ")),
+                expr => eval_in(expr.clone(), &env)
+                    .map(|value| value.to_string())
+                    .map_err(String::from),
+            };
+            row.push(cell.unwrap_or_else(|e| e));
+        }
+        table.push_row(row);
+    }
+
+    Ok(table.render(format))
+}
+
+// plot(expr, ..., x, lo, hi) draws every expression against x
+fn plot(args: &[Expression], env: &Environment, path: Option<&str>) -> Result<String, String> {
+    let [exprs @ .., Expression::Ident(name), lo, hi] = args else {
+        return Err(String::from(
+            "plot takes expressions followed by a name and its range, as in plot(x^2, x, -10, 10)",
+        ));
+    };
+    if exprs.is_empty() {
+        return Err(String::from("nothing to plot"));
+    }
+
+    let lo = eval_in(lo.clone(), env)?.as_number()?;
+    let hi = eval_in(hi.clone(), env)?.as_number()?;
+    let plot = plot::Plot::new(exprs, name, lo, hi, env)?;
+
+    match path {
+        Some(path) => {
+            std::fs::write(path, plot.svg()).map_err(|e| format!("{path}: {e}"))?;
+            Ok(format!("wrote {path}"))
+        }
+        None => Ok(plot.ascii()),
+    }
+}
+
+fn solve(lhs: &Expression, rhs: &Expression, env: &mut Environment) -> Result<Vec<Output>, String> {
+    let mut unknowns = solver::unknowns(lhs, env);
+    let mut ranges = Vec::new();
+    for name in lhs.identifiers().into_iter().chain(rhs.identifiers()) {
+        if let Some(Value::Interval(domain)) = env.get(&name)
+            && !ranges.iter().any(|(n, _)| *n == name)
         {
-            println!("let {} = {}", name, eval_expr(value.clone().unwrap()))
+            ranges.push((name, *domain));
+        }
+    }
+    for name in solver::unknowns(rhs, env) {
+        if !unknowns.contains(&name) {
+            unknowns.push(name);
         }
     }
 
-    // println!("{:?}", ast);
+    // An equation in a variable bound to an interval is solved for all roots in that interval
+    if let ([], [(name, domain)]) = (unknowns.as_slice(), ranges.as_slice()) {
+        let mut roots = solver::interval::find_roots(lhs, rhs, name, *domain, env)
+            .map_err(|e| format!("{}: {}", name, e))?;
+        if let Some(assumed) = env.domain(name) {
+            roots.retain(|root| assumed.meets(&root.enclosure));
+        }
+
+        if roots.is_empty() {
+            let note = format!("no roots for {} in {}", name, domain);
+            return Ok(vec![Output::Value(name.clone(), note)]);
+        }
+        return Ok(roots
+            .into_iter()
+            .map(|root| Output::Root(name.clone(), root))
+            .collect());
+    }
+
+    match unknowns.as_slice() {
+        [unknown] => {
+            let result = solver::solve_equation(lhs, rhs, unknown, env)
+                .map_err(|e| format!("{}: {}", unknown, e))?;
+            env.set(unknown.clone(), result.clone());
+            Ok(vec![Output::Solution(unknown.clone(), result)])
+        }
+        [] => Err(String::from(
+            "nothing to solve for, every name in the equation is known",
+        )),
+        // Integers make a single linear equation in two unknowns solvable
+        _ if unknowns
+            .iter()
+            .all(|name| env.domain(name).is_some_and(|d| d.integer)) =>
+        {
+            Ok(vec![Output::Value(
+                unknowns.join(", "),
+                diophantine(lhs, rhs, env)?,
+            )])
+        }
+        _ => Err(format!(
+            "cannot solve for several unknowns: {}",
+            unknowns.join(", ")
+        )),
+    }
 }
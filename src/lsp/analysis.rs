@@ -0,0 +1,162 @@
+use crate::engine::Engine;
+use crate::evaluator::function::FUNCTIONS;
+use crate::lexer::{self, Span, Token};
+use crate::parser::Parser;
+use crate::{Output, SYMBOLIC_FUNCTIONS};
+
+const KEYWORDS: [&str; 9] = [
+    "let", "assume", "maximize", "minimize", "subject", "in", "from", "to", "step",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompletionKind {
+    Function,
+    Variable,
+    Keyword,
+}
+
+// Lexer and parser errors, a literal the lexer could not read being reported by the parser
+pub fn diagnostics(text: &str) -> Vec<(Span, String)> {
+    let mut parser = Parser::with_spans(lexer::tokenize_spanned(text));
+    parser.run_parser();
+    parser
+        .errors()
+        .iter()
+        .map(|e| (e.span, e.message.clone()))
+        .collect()
+}
+
+// What a name evaluated to when the document last ran, along with the name's own span.
+// line and column are 0-based, the column counted in characters
+pub fn hover(text: &str, engine: &Engine, line: usize, column: usize) -> Option<(String, Span)> {
+    let tokens = lexer::tokenize_spanned(text);
+    let (name, span) = ident_at(&tokens, line, column)?;
+
+    let outputs: Vec<&Output> = engine.outputs().collect();
+    let output = outputs
+        .iter()
+        .rev()
+        .find(|output| !matches!(output, Output::Value(..)) && output.label() == name)?;
+    Some((output.to_string(), span))
+}
+
+// Brings the engine up to date with the document, re-evaluating only what the edit affected
+pub fn evaluate(text: &str, engine: &mut Engine) {
+    let tokens = lexer::tokenize_spanned(text);
+    engine.update(Parser::with_spans(tokens).run_parser());
+}
+
+// The let that binds the name under the cursor, the latest one above it if it is rebound
+pub fn definition(text: &str, line: usize, column: usize) -> Option<Span> {
+    let tokens = lexer::tokenize_spanned(text);
+    let (name, at) = ident_at(&tokens, line, column)?;
+
+    let spans: Vec<Span> = definitions(&tokens)
+        .into_iter()
+        .filter(|(n, _)| *n == name)
+        .map(|(_, span)| span)
+        .collect();
+    spans
+        .iter()
+        .rev()
+        .find(|span| (span.line, span.start) <= (at.line, at.start))
+        .or(spans.first())
+        .copied()
+}
+
+pub fn completions(text: &str) -> Vec<(String, CompletionKind)> {
+    let mut items: Vec<(String, CompletionKind)> = Vec::new();
+
+    for (name, _) in definitions(&lexer::tokenize_spanned(text)) {
+        if !items.iter().any(|(n, _)| *n == name) {
+            items.push((name, CompletionKind::Variable));
+        }
+    }
+    items.extend(
+        FUNCTIONS
+            .iter()
+            .chain(SYMBOLIC_FUNCTIONS.iter())
+            .map(|name| (name.to_string(), CompletionKind::Function)),
+    );
+    items.extend(
+        KEYWORDS
+            .iter()
+            .map(|name| (name.to_string(), CompletionKind::Keyword)),
+    );
+    items
+}
+
+// The formatted document, or None while it has errors
+pub fn format(text: &str) -> Option<String> {
+    crate::format::format(text).ok()
+}
+
+fn ident_at(tokens: &[(Token, Span)], line: usize, column: usize) -> Option<(String, Span)> {
+    tokens.iter().find_map(|(token, span)| match token {
+        Token::Ident(name)
+            if span.line == line + 1 && span.start <= column && column <= span.end =>
+        {
+            Some((name.clone(), *span))
+        }
+        _ => None,
+    })
+}
+
+fn definitions(tokens: &[(Token, Span)]) -> Vec<(String, Span)> {
+    tokens
+        .windows(2)
+        .filter_map(|pair| match pair {
+            [(Token::Let, _), (Token::Ident(name), span)] => Some((name.clone(), *span)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = "let g -> 10\nlet h -> 10\nlet t -> 2\nd = g*t^2/2 - h\n";
+
+    #[test]
+    fn reports_parse_errors() {
+        let errors = diagnostics("let a -> 2 +\nb = (1;\n");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0.line, 2);
+        assert!(diagnostics(DOCUMENT).is_empty());
+    }
+
+    #[test]
+    fn hovers_and_finds_definitions() {
+        let mut engine = Engine::default();
+        evaluate(DOCUMENT, &mut engine);
+
+        assert_eq!(hover(DOCUMENT, &engine, 3, 4).unwrap().0, "let g = 10");
+        assert_eq!(hover(DOCUMENT, &engine, 3, 0).unwrap().0, "d = 10");
+        assert_eq!(hover(DOCUMENT, &engine, 3, 14).unwrap().0, "let h = 10");
+        assert_eq!(hover(DOCUMENT, &engine, 3, 8), None);
+
+        let span = definition(DOCUMENT, 3, 6).unwrap();
+        assert_eq!((span.line, span.start), (3, 4));
+        assert_eq!(definition(DOCUMENT, 3, 0), None);
+    }
+
+    #[test]
+    fn completes_names_and_builtins() {
+        let items = completions(DOCUMENT);
+
+        assert!(items.contains(&("t".into(), CompletionKind::Variable)));
+        assert!(items.contains(&("factor".into(), CompletionKind::Function)));
+        assert!(!items.iter().any(|(name, _)| name == "d"));
+    }
+
+    #[test]
+    fn formats_statements() {
+        assert_eq!(
+            format("let f -> 5*x + 7*y = 10;  f+g=20").unwrap(),
+            "let f -> 5x + 7y = 10;\nf + g = 20;\n"
+        );
+        assert_eq!(format("x = (1"), None);
+    }
+}
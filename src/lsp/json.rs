@@ -0,0 +1,301 @@
+use std::fmt;
+
+// Just enough JSON for the protocol, objects keep their keys in order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    // Follows a path of keys, as in message.at(&["params", "textDocument", "uri"])
+    pub fn at(&self, path: &[&str]) -> Option<&Json> {
+        path.iter().try_fold(self, |json, key| json.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut chars = text.chars().peekable();
+        let json = parse_value(&mut chars)?;
+        skip_whitespace(&mut chars);
+
+        match chars.next() {
+            None => Ok(json),
+            Some(c) => Err(format!("unexpected '{c}' after the JSON value")),
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn skip_whitespace(chars: &mut Chars) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn parse_value(chars: &mut Chars) -> Result<Json, String> {
+    skip_whitespace(chars);
+
+    match chars.peek() {
+        Some('{') => parse_object(chars),
+        Some('[') => parse_array(chars),
+        Some('"') => parse_string(chars).map(Json::String),
+        Some('-' | '0'..='9') => parse_number(chars),
+        Some(_) => {
+            let word: String =
+                std::iter::from_fn(|| chars.next_if(|c| c.is_alphabetic())).collect();
+            match word.as_str() {
+                "null" => Ok(Json::Null),
+                "true" => Ok(Json::Bool(true)),
+                "false" => Ok(Json::Bool(false)),
+                _ => Err(format!("unexpected '{word}' in JSON")),
+            }
+        }
+        None => Err(String::from("unexpected end of JSON")),
+    }
+}
+
+fn parse_object(chars: &mut Chars) -> Result<Json, String> {
+    chars.next(); // consume '{'
+    let mut fields = Vec::new();
+
+    skip_whitespace(chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Ok(Json::Object(fields));
+    }
+
+    loop {
+        skip_whitespace(chars);
+        let key = parse_string(chars)?;
+        skip_whitespace(chars);
+        if chars.next() != Some(':') {
+            return Err(format!("expected ':' after \"{key}\""));
+        }
+        fields.push((key, parse_value(chars)?));
+
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some('}') => return Ok(Json::Object(fields)),
+            other => return Err(format!("expected ',' or '}}' in object, got {:?}", other)),
+        }
+    }
+}
+
+fn parse_array(chars: &mut Chars) -> Result<Json, String> {
+    chars.next(); // consume '['
+    let mut elements = Vec::new();
+
+    skip_whitespace(chars);
+    if chars.next_if_eq(&']').is_some() {
+        return Ok(Json::Array(elements));
+    }
+
+    loop {
+        elements.push(parse_value(chars)?);
+
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some(']') => return Ok(Json::Array(elements)),
+            other => return Err(format!("expected ',' or ']' in array, got {:?}", other)),
+        }
+    }
+}
+
+fn parse_number(chars: &mut Chars) -> Result<Json, String> {
+    let text: String = std::iter::from_fn(|| {
+        chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+    })
+    .collect();
+
+    text.parse()
+        .map(Json::Number)
+        .map_err(|_| format!("invalid number '{text}' in JSON"))
+}
+
+fn parse_string(chars: &mut Chars) -> Result<String, String> {
+    if chars.next() != Some('"') {
+        return Err(String::from("expected a string in JSON"));
+    }
+
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some('r') => s.push('\r'),
+                Some('b') => s.push('\u{8}'),
+                Some('f') => s.push('\u{c}'),
+                Some('u') => s.push(parse_escape(chars)?),
+                Some(c) => s.push(c),
+                None => break,
+            },
+            Some(c) => s.push(c),
+            None => break,
+        }
+    }
+
+    Err(String::from("unterminated string in JSON"))
+}
+
+// \uXXXX, with characters outside the basic plane written as a surrogate pair
+fn parse_escape(chars: &mut Chars) -> Result<char, String> {
+    let high = code_unit(chars)?;
+    let code = if (0xD800..0xDC00).contains(&high) {
+        if chars.next() != Some('\\') || chars.next() != Some('u') {
+            return Err(String::from("unpaired surrogate in JSON"));
+        }
+        let low = code_unit(chars)?;
+        0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+    } else {
+        high
+    };
+
+    char::from_u32(code).ok_or_else(|| format!("invalid character {code:#x} in JSON"))
+}
+
+fn code_unit(chars: &mut Chars) -> Result<u32, String> {
+    let hex: String = chars.by_ref().take(4).collect();
+    u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape '\\u{hex}' in JSON"))
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) => write!(f, "{n}"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_prints_back() {
+        let text = r#"{"id":1,"params":{"text":"let x -> 2\n","ok":true,"list":[null,-1.5]}}"#;
+        let json = Json::parse(text).unwrap();
+
+        assert_eq!(json.at(&["params", "ok"]), Some(&Json::Bool(true)));
+        assert_eq!(
+            json.at(&["params", "text"]).unwrap().as_str(),
+            Some("let x -> 2\n")
+        );
+        assert_eq!(json.to_string(), text);
+    }
+
+    #[test]
+    fn decodes_escapes() {
+        let json = Json::parse(r#""\u03b8 \ud83d\ude00 \"q\"""#).unwrap();
+
+        assert_eq!(json, Json::from("θ 😀 \"q\""));
+        assert!(Json::parse(r#"{"a" 1}"#).is_err());
+        assert!(Json::parse("[1, 2").is_err());
+    }
+}
@@ -0,0 +1,462 @@
+pub mod analysis;
+pub mod json;
+
+pub use json::Json;
+
+use crate::engine::Engine;
+use crate::lexer::Span;
+use analysis::CompletionKind;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+// One message body from a stream of 'Content-Length: n' framed messages, None at the end
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = length.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without a Content-Length",
+        )
+    })?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+// The open documents and where the session is in its lifecycle. Each document keeps an
+// engine so an edit only re-evaluates the bindings it affects
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, String>,
+    engines: HashMap<String, Engine>,
+    shutting_down: bool,
+    exit_code: Option<i32>,
+}
+
+impl Server {
+    // Set once the client sends 'exit', 0 if it asked to shut down first
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    pub fn receive(&mut self, body: &str) -> Vec<Json> {
+        match Json::parse(body) {
+            Ok(message) => self.handle(&message),
+            Err(e) => vec![error(Json::Null, -32700, e)],
+        }
+    }
+
+    // The responses and notifications to send back for one message
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+
+        let Some(id) = message.get("id").cloned() else {
+            return self.notify(method, params);
+        };
+
+        if self.shutting_down {
+            return vec![error(
+                id,
+                -32600,
+                String::from("the server is shutting down"),
+            )];
+        }
+
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => {
+                self.shutting_down = true;
+                Some(Json::Null)
+            }
+            "textDocument/hover" => Some(self.hover(params)),
+            "textDocument/definition" => Some(self.definition(params)),
+            "textDocument/completion" => Some(self.completion(params)),
+            "textDocument/formatting" => Some(self.formatting(params)),
+            _ => None,
+        };
+
+        match result {
+            Some(result) => vec![Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id),
+                ("result", result),
+            ])],
+            None => vec![error(id, -32601, format!("unknown method '{method}'"))],
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params
+            .at(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .to_string();
+
+        match method {
+            "exit" => {
+                self.exit_code = Some(if self.shutting_down { 0 } else { 1 });
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let text = params.at(&["textDocument", "text"]).and_then(Json::as_str);
+                self.documents
+                    .insert(uri.clone(), text.unwrap_or("").to_string());
+                vec![self.refresh(&uri)]
+            }
+            // Only full syncs are offered, so the last change holds the whole text
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").and_then(Json::as_array);
+                if let Some(text) = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str)
+                {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                vec![self.refresh(&uri)]
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.engines.remove(&uri);
+                vec![notification(
+                    "textDocument/publishDiagnostics",
+                    Json::object([
+                        ("uri", uri.into()),
+                        ("diagnostics", Json::Array(Vec::new())),
+                    ]),
+                )]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // Re-evaluates a document after it opened or changed and reports its errors
+    fn refresh(&mut self, uri: &str) -> Json {
+        let text = self.documents.get(uri).map_or("", |text| text.as_str());
+        analysis::evaluate(text, self.engines.entry(uri.to_string()).or_default());
+        self.publish_diagnostics(uri)
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Json {
+        let text = self.text(uri);
+        let diagnostics = analysis::diagnostics(text)
+            .into_iter()
+            .map(|(span, message)| {
+                Json::object([
+                    ("range", range(text, span)),
+                    ("severity", 1.into()),
+                    ("source", "methal".into()),
+                    ("message", message.into()),
+                ])
+            })
+            .collect();
+
+        notification(
+            "textDocument/publishDiagnostics",
+            Json::object([
+                ("uri", uri.into()),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        )
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let (uri, line, column) = self.position(params);
+        let text = self.text(uri);
+
+        let hover = self
+            .engines
+            .get(uri)
+            .and_then(|engine| analysis::hover(text, engine, line, column));
+        match hover {
+            Some((value, span)) => Json::object([
+                (
+                    "contents",
+                    Json::object([("kind", "plaintext".into()), ("value", value.into())]),
+                ),
+                ("range", range(text, span)),
+            ]),
+            None => Json::Null,
+        }
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let (uri, line, column) = self.position(params);
+        let text = self.text(uri);
+
+        match analysis::definition(text, line, column) {
+            Some(span) => Json::object([("uri", uri.into()), ("range", range(text, span))]),
+            None => Json::Null,
+        }
+    }
+
+    fn completion(&self, params: &Json) -> Json {
+        let (uri, ..) = self.position(params);
+        let items = analysis::completions(self.text(uri))
+            .into_iter()
+            .map(|(label, kind)| {
+                let kind: usize = match kind {
+                    CompletionKind::Function => 3,
+                    CompletionKind::Variable => 6,
+                    CompletionKind::Keyword => 14,
+                };
+                Json::object([("label", label.into()), ("kind", kind.into())])
+            })
+            .collect();
+        Json::Array(items)
+    }
+
+    // A single edit replacing the whole document, none if it has errors or is already tidy
+    fn formatting(&self, params: &Json) -> Json {
+        let (uri, ..) = self.position(params);
+        let text = self.text(uri);
+
+        let edits = match analysis::format(text) {
+            Some(formatted) if formatted != text => {
+                let end = Json::object([
+                    ("line", text.lines().count().into()),
+                    ("character", 0.into()),
+                ]);
+                let start = Json::object([("line", 0.into()), ("character", 0.into())]);
+                vec![Json::object([
+                    ("range", Json::object([("start", start), ("end", end)])),
+                    ("newText", formatted.into()),
+                ])]
+            }
+            _ => Vec::new(),
+        };
+        Json::Array(edits)
+    }
+
+    fn text(&self, uri: &str) -> &str {
+        self.documents.get(uri).map_or("", |text| text.as_str())
+    }
+
+    // The document and the 0-based line and character column a request points at
+    fn position<'a>(&self, params: &'a Json) -> (&'a str, usize, usize) {
+        let uri = params
+            .at(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .unwrap_or("");
+        let line = params.at(&["position", "line"]).and_then(Json::as_usize);
+        let character = params
+            .at(&["position", "character"])
+            .and_then(Json::as_usize);
+
+        let line = line.unwrap_or(0);
+        let column = char_column(self.text(uri), line, character.unwrap_or(0));
+        (uri, line, column)
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                ("textDocumentSync", 1.into()),
+                ("hoverProvider", true.into()),
+                ("definitionProvider", true.into()),
+                ("completionProvider", Json::object([])),
+                ("documentFormattingProvider", true.into()),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", "methal-lsp".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn error(id: Json, code: i32, message: String) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object([
+                ("code", Json::Number(code as f64)),
+                ("message", message.into()),
+            ]),
+        ),
+    ])
+}
+
+// Spans count characters but the protocol counts UTF-16 code units
+fn range(text: &str, span: Span) -> Json {
+    let line = span.line.saturating_sub(1);
+    let position = |column: usize| {
+        Json::object([
+            ("line", line.into()),
+            ("character", utf16_column(text, line, column).into()),
+        ])
+    };
+    Json::object([("start", position(span.start)), ("end", position(span.end))])
+}
+
+fn utf16_column(text: &str, line: usize, column: usize) -> usize {
+    let line = text.lines().nth(line).unwrap_or("");
+    let prefix = line.chars().take(column);
+    prefix.map(char::len_utf16).sum::<usize>() + column.saturating_sub(line.chars().count())
+}
+
+fn char_column(text: &str, line: usize, utf16: usize) -> usize {
+    let line = text.lines().nth(line).unwrap_or("");
+    let mut units = 0;
+    for (column, c) in line.chars().enumerate() {
+        if units >= utf16 {
+            return column;
+        }
+        units += c.len_utf16();
+    }
+    line.chars().count() + utf16.saturating_sub(units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: usize, method: &str, params: &str) -> String {
+        format!(r#"{{"jsonrpc":"2.0","id":{id},"method":"{method}","params":{params}}}"#)
+    }
+
+    fn notification(method: &str, params: &str) -> String {
+        format!(r#"{{"jsonrpc":"2.0","method":"{method}","params":{params}}}"#)
+    }
+
+    const URI: &str = r#""textDocument":{"uri":"file:///a.mth""#;
+
+    #[test]
+    fn scripted_session() {
+        let mut script = Vec::new();
+        for message in [
+            request(1, "initialize", "{}"),
+            notification(
+                "textDocument/didOpen",
+                &format!(r#"{{{URI},"text":"let θ -> 2\nx = θ*3 +\n"}}}}"#),
+            ),
+            notification(
+                "textDocument/didChange",
+                &format!(r#"{{{URI}}},"contentChanges":[{{"text":"let θ -> 2\nx = θ * 3\n"}}]}}"#),
+            ),
+            request(
+                2,
+                "textDocument/hover",
+                &format!(r#"{{{URI}}},"position":{{"line":1,"character":4}}}}"#),
+            ),
+            request(
+                3,
+                "textDocument/definition",
+                &format!(r#"{{{URI}}},"position":{{"line":1,"character":5}}}}"#),
+            ),
+            request(
+                4,
+                "textDocument/formatting",
+                &format!(r#"{{{URI}}},"options":{{}}}}"#),
+            ),
+            request(5, "shutdown", "null"),
+            notification("exit", "null"),
+        ] {
+            write!(script, "Content-Length: {}\r\n\r\n{message}", message.len()).unwrap();
+        }
+
+        let mut server = Server::default();
+        let mut replies = Vec::new();
+        let mut reader = script.as_slice();
+        while let Some(body) = read_message(&mut reader).unwrap() {
+            replies.extend(server.receive(&body));
+        }
+
+        let diagnostics = |i: usize| {
+            replies[i]
+                .at(&["params", "diagnostics"])
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len()
+        };
+        assert!(
+            replies[0]
+                .at(&["result", "capabilities", "hoverProvider"])
+                .is_some()
+        );
+        assert_eq!(diagnostics(1), 1);
+        assert_eq!(diagnostics(2), 0);
+        assert_eq!(
+            replies[3]
+                .at(&["result", "contents", "value"])
+                .unwrap()
+                .as_str(),
+            Some("let θ = 2")
+        );
+        assert_eq!(
+            replies[4].at(&["result", "range", "start", "character"]),
+            Some(&Json::Number(4.0))
+        );
+        assert_eq!(
+            replies[5].at(&["result"]).unwrap().as_array().unwrap()[0]
+                .get("newText")
+                .unwrap()
+                .as_str(),
+            Some("let θ -> 2;\nx = θ*3;\n")
+        );
+        assert_eq!(server.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn rejects_unknown_requests() {
+        let mut server = Server::default();
+        let reply = &server.receive(&request(7, "workspace/symbol", "{}"))[0];
+
+        assert_eq!(reply.at(&["error", "code"]), Some(&Json::Number(-32601.0)));
+        assert_eq!(
+            server.receive("{")[0].at(&["error", "code"]),
+            Some(&Json::Number(-32700.0))
+        );
+    }
+
+    #[test]
+    fn counts_utf16_columns() {
+        assert_eq!(utf16_column("a😀b", 0, 2), 3);
+        assert_eq!(char_column("a😀b", 0, 3), 2);
+    }
+}
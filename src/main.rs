@@ -2,33 +2,101 @@ use std::env;
 use std::error::Error;
 use std::fs;
 
+use methal::Options;
+use methal::table::{Format, Sweep};
+
 fn main() {
-    let content = match parse_args(env::args()) {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).is_some_and(|arg| arg == "fmt") {
+        std::process::exit(format_files(&args[2..]));
+    }
+
+    let (content, options) = match parse_args(env::args()) {
         Err(e) => {
             eprintln!("File content could not be recovered\n{e}");
             std::process::exit(1);
         }
 
-        Ok(content) => content,
+        Ok(parsed) => parsed,
     };
 
-    methal::run(content);
+    methal::run_with(content, &options);
 }
 
-fn parse_args(mut args: impl Iterator<Item = String>) -> Result<String, String> {
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<(String, Options), String> {
     args.next(); // skips the program name 
 
-    let mut content = args
-        .next()
-        .ok_or("Invalid arguments\nEnter text or -f path")?;
+    let mut content = None;
+    let mut options = Options::default();
 
-    if content == "-f" {
-        let path = args.next().ok_or("No file path provided")?;
-        println!("{path}");
-        content = read_file(path).map_err(|e| format!("File couldn't be read: {e}"))?;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-f" => {
+                let path = args.next().ok_or("No file path provided")?;
+                println!("{path}");
+                content = Some(read_file(path).map_err(|e| format!("File couldn't be read: {e}"))?);
+            }
+            // --sweep x=0:10:0.5
+            "--sweep" => {
+                let spec = args
+                    .next()
+                    .ok_or("No sweep provided, expected x=from:to:step")?;
+                options.sweep = Some(Sweep::parse(&spec)?);
+            }
+            "--csv" => options.format = Format::Csv,
+            "--graph" => options.graph = true,
+            "--ieee" => options.ieee = true,
+            "--plot" => {
+                let path = args.next().ok_or("No plot file provided")?;
+                options.plot = Some(path);
+            }
+            _ => content = Some(arg),
+        }
     }
 
-    Ok(content)
+    let content = content.ok_or("Invalid arguments\nEnter text or -f path")?;
+    Ok((content, options))
+}
+
+// methal fmt [--check] files..., with --check only reporting the files that would change
+fn format_files(args: &[String]) -> i32 {
+    let check = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if paths.is_empty() {
+        eprintln!("Invalid arguments\nEnter the files to format: methal fmt [--check] path...");
+        return 2;
+    }
+
+    let mut status = 0;
+    for path in paths {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("{path}: File couldn't be read: {e}");
+                status = 1;
+                continue;
+            }
+        };
+
+        match methal::format::format(&content) {
+            Err(errors) => {
+                errors.iter().for_each(|e| eprintln!("{path}: {e}"));
+                status = 1;
+            }
+            Ok(formatted) if formatted == content => {}
+            Ok(_) if check => {
+                println!("{path} is not formatted");
+                status = 1;
+            }
+            Ok(formatted) => {
+                if let Err(e) = fs::write(path, formatted) {
+                    eprintln!("{path}: File couldn't be written: {e}");
+                    status = 1;
+                }
+            }
+        }
+    }
+    status
 }
 
 fn read_file(path: String) -> Result<String, Box<dyn Error>> {
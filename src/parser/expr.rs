@@ -1,11 +1,21 @@
+use crate::evaluator::BigInt;
 use crate::lexer::Token;
-use crate::parser::{Operator, get_operator};
+use crate::parser::{
+    Associativity, Operator, UnaryOperator, found, get_operator, get_postfix_operator,
+    get_prefix_operator,
+};
 use std::iter::Peekable;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Expression {
     Number(f32),
+    // Whole literals too large for an f32 to hold exactly
+    Integer(BigInt),
     Ident(String),
+    Quantity {
+        value: f32,
+        unit: String,
+    },
 
     Binary {
         lhs: Box<Expression>,
@@ -13,40 +23,181 @@ pub enum Expression {
         rhs: Box<Expression>,
     },
     Unary {
-        op: String,
+        op: UnaryOperator,
+        rhs: Box<Expression>,
+    },
+
+    Vector(Vec<Expression>),
+    Matrix(Vec<Vec<Expression>>),
+    Call {
+        name: String,
+        args: Vec<Expression>,
+    },
+    Convert {
+        expr: Box<Expression>,
+        unit: String,
+    },
+    // x = 2 inside call arguments, as in subs(expr, x = 2)
+    Equation {
+        lhs: Box<Expression>,
         rhs: Box<Expression>,
     },
+    // x from 0 to 10 step 0.5, as in table(expr, x from 0 to 10)
+    Range {
+        name: String,
+        from: Box<Expression>,
+        to: Box<Expression>,
+        step: Option<Box<Expression>>,
+    },
 }
 
+impl Expression {
+    // Every identifier used in the expression, in order of appearance
+    pub fn identifiers(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.collect_identifiers(&mut names);
+        names
+    }
+
+    fn collect_identifiers(&self, names: &mut Vec<String>) {
+        match self {
+            Expression::Number(_) | Expression::Integer(_) | Expression::Quantity { .. } => {}
+            Expression::Ident(name) => names.push(name.clone()),
+            Expression::Binary { lhs, rhs, .. } | Expression::Equation { lhs, rhs } => {
+                lhs.collect_identifiers(names);
+                rhs.collect_identifiers(names);
+            }
+            Expression::Unary { rhs, .. } => rhs.collect_identifiers(names),
+            Expression::Convert { expr, .. } => expr.collect_identifiers(names),
+            Expression::Vector(elements) | Expression::Call { args: elements, .. } => {
+                elements.iter().for_each(|e| e.collect_identifiers(names))
+            }
+            Expression::Matrix(rows) => rows
+                .iter()
+                .flatten()
+                .for_each(|e| e.collect_identifiers(names)),
+            // The range's own name is bound by it, not used
+            Expression::Range { from, to, step, .. } => {
+                from.collect_identifiers(names);
+                to.collect_identifiers(names);
+                if let Some(step) = step {
+                    step.collect_identifiers(names);
+                }
+            }
+        }
+    }
+
+    // Rebuilds the expression with f applied to each direct subexpression
+    pub fn map_children(
+        self,
+        mut f: impl FnMut(Expression) -> Result<Expression, String>,
+    ) -> Result<Expression, String> {
+        let mut boxed = |e: Box<Expression>| f(*e).map(Box::new);
+
+        Ok(match self {
+            Expression::Number(_)
+            | Expression::Integer(_)
+            | Expression::Ident(_)
+            | Expression::Quantity { .. } => self,
+            Expression::Binary { lhs, op, rhs } => Expression::Binary {
+                lhs: boxed(lhs)?,
+                op,
+                rhs: boxed(rhs)?,
+            },
+            Expression::Unary { op, rhs } => Expression::Unary {
+                op,
+                rhs: boxed(rhs)?,
+            },
+            Expression::Convert { expr, unit } => Expression::Convert {
+                expr: boxed(expr)?,
+                unit,
+            },
+            Expression::Equation { lhs, rhs } => Expression::Equation {
+                lhs: boxed(lhs)?,
+                rhs: boxed(rhs)?,
+            },
+            Expression::Vector(elements) => {
+                Expression::Vector(elements.into_iter().map(f).collect::<Result<_, _>>()?)
+            }
+            Expression::Matrix(rows) => Expression::Matrix(
+                rows.into_iter()
+                    .map(|row| row.into_iter().map(&mut f).collect())
+                    .collect::<Result<_, _>>()?,
+            ),
+            Expression::Call { name, args } => Expression::Call {
+                name,
+                args: args.into_iter().map(f).collect::<Result<_, _>>()?,
+            },
+            Expression::Range {
+                name,
+                from,
+                to,
+                step,
+            } => Expression::Range {
+                name,
+                from: boxed(from)?,
+                to: boxed(to)?,
+                step: step.map(&mut boxed).transpose()?,
+            },
+        })
+    }
+}
+
+// Loosest first. Exponent binds tighter than a prefix, so -2^2 is -(2^2)
 #[derive(PartialEq, PartialOrd, Debug, Clone, Copy)]
 pub enum Precedence {
     Lowest,
-    Modulus,  // | |
-    Sum,      // + -
-    Product,  // * /
-    Prefix,   // -x
-    Exponent, // % ^
+    Conversion, // in
+    Modulus,    // | |
+    Sum,        // + -
+    Product,    // * / % .*
+    Prefix,     // -x +x !x
+    Exponent,   // ^
+    Postfix,    // x! x% x°
 }
 
 pub fn token_precedence(token: &Token) -> Precedence {
     match token {
         Token::Plus | Token::Minus => Precedence::Sum,
-        Token::Mult | Token::Div => Precedence::Product,
-        Token::Mod | Token::Pow => Precedence::Exponent,
+        Token::Mult | Token::DotMult | Token::Div | Token::Mod => Precedence::Product,
+        Token::Pow => Precedence::Exponent,
         Token::Pipe => Precedence::Modulus,
+        Token::In => Precedence::Conversion,
+        Token::Bang | Token::Percent | Token::Degree => Precedence::Postfix,
         _ => Precedence::Lowest,
     }
 }
 
+pub fn token_associativity(token: &Token) -> Associativity {
+    get_operator(token).map_or(Associativity::Left, |op| op.associativity())
+}
+
 fn parse_prefix(tokens: &mut Peekable<impl Iterator<Item = Token>>) -> Result<Expression, String> {
     match tokens.next() {
-        Some(Token::Number(n)) => Ok(Expression::Number(n)),
-        Some(Token::Ident(s)) => Ok(Expression::Ident(s)),
+        Some(Token::Number(n)) => match tokens.next_if(|t| matches!(t, Token::Unit(_))) {
+            Some(Token::Unit(unit)) => Ok(Expression::Quantity { value: n, unit }),
+            _ => Ok(Expression::Number(n)),
+        },
+        Some(Token::Integer(n)) => match tokens.next_if(|t| matches!(t, Token::Unit(_))) {
+            Some(Token::Unit(unit)) => Ok(Expression::Quantity {
+                value: n.to_f32(),
+                unit,
+            }),
+            _ => Ok(Expression::Integer(n)),
+        },
+        Some(Token::Ident(s)) => match tokens.peek() {
+            Some(Token::LParen) => {
+                tokens.next();
+                let args = parse_call_args(tokens)?;
+                Ok(Expression::Call { name: s, args })
+            }
+            _ => Ok(Expression::Ident(s)),
+        },
 
-        Some(Token::Minus) => {
+        Some(token @ (Token::Minus | Token::Plus | Token::Bang)) => {
             let rhs = parse_expression_pratt(tokens, Precedence::Prefix)?;
             Ok(Expression::Unary {
-                op: "-".into(),
+                op: get_prefix_operator(&token).unwrap(),
                 rhs: Box::new(rhs),
             })
         }
@@ -56,7 +207,7 @@ fn parse_prefix(tokens: &mut Peekable<impl Iterator<Item = Token>>) -> Result<Ex
 
             match tokens.next() {
                 Some(Token::RParen) => Ok(expr),
-                other => Err(format!("expected ')', got {:?}", other)),
+                other => Err(format!("expected ')', got {}", found(other.as_ref()))),
             }
         }
 
@@ -65,15 +216,125 @@ fn parse_prefix(tokens: &mut Peekable<impl Iterator<Item = Token>>) -> Result<Ex
 
             match tokens.next() {
                 Some(Token::Pipe) => Ok(Expression::Unary {
-                    op: "modulus".into(),
+                    op: UnaryOperator::Abs,
                     rhs: Box::new(rhs),
                 }),
-                other => Err(format!("expected '|', got {:?}", other)),
+                other => Err(format!("expected '|', got {}", found(other.as_ref()))),
+            }
+        }
+
+        Some(Token::LBracket) => parse_matrix(tokens),
+
+        Some(Token::Malformed { message, .. }) => Err(message),
+        other => Err(format!(
+            "unexpected token in expression: {}",
+            found(other.as_ref())
+        )),
+    }
+}
+
+// Arguments of a call, the opening '(' has already been consumed
+pub fn parse_call_args(
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Result<Vec<Expression>, String> {
+    let mut args = Vec::new();
+
+    if let Some(Token::RParen) = tokens.peek() {
+        tokens.next();
+        return Ok(args);
+    }
+
+    loop {
+        let arg = parse_expression_pratt(tokens, Precedence::Lowest)?;
+        args.push(match tokens.peek() {
+            Some(Token::Assign) => {
+                tokens.next();
+                Expression::Equation {
+                    lhs: Box::new(arg),
+                    rhs: Box::new(parse_expression_pratt(tokens, Precedence::Lowest)?),
+                }
+            }
+            Some(Token::From) => parse_range(arg, tokens)?,
+            _ => arg,
+        });
+
+        match tokens.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::RParen) => return Ok(args),
+            other => {
+                return Err(format!(
+                    "expected ',' or ')', got {}",
+                    found(other.as_ref())
+                ));
+            }
+        }
+    }
+}
+
+// x from 0 to 10 step 0.5, with the step optional
+fn parse_range(
+    name: Expression,
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Result<Expression, String> {
+    let Expression::Ident(name) = name else {
+        return Err(format!("expected a name before 'from', got {name}"));
+    };
+    tokens.next(); // consume 'from'
+
+    let from = parse_expression_pratt(tokens, Precedence::Lowest)?;
+    match tokens.next() {
+        Some(Token::To) => {}
+        other => return Err(format!("expected 'to', got {}", found(other.as_ref()))),
+    }
+    let to = parse_expression_pratt(tokens, Precedence::Lowest)?;
+
+    let step = match tokens.next_if_eq(&Token::Step) {
+        Some(_) => Some(Box::new(parse_expression_pratt(
+            tokens,
+            Precedence::Lowest,
+        )?)),
+        None => None,
+    };
+
+    Ok(Expression::Range {
+        name,
+        from: Box::new(from),
+        to: Box::new(to),
+        step,
+    })
+}
+
+// [1, 2, 3] is a vector, [1, 2; 3, 4] is a matrix, the opening '[' has already been consumed
+fn parse_matrix(tokens: &mut Peekable<impl Iterator<Item = Token>>) -> Result<Expression, String> {
+    let mut rows = vec![Vec::new()];
+
+    loop {
+        let expr = parse_expression_pratt(tokens, Precedence::Lowest)?;
+        rows.last_mut().unwrap().push(expr);
+
+        match tokens.next() {
+            Some(Token::Comma) => continue,
+            Some(Token::Semicolon) => rows.push(Vec::new()),
+            Some(Token::RBracket) => break,
+            other => {
+                return Err(format!(
+                    "expected ',', ';' or ']', got {}",
+                    found(other.as_ref())
+                ));
             }
         }
+    }
+
+    if rows.len() == 1 {
+        return Ok(Expression::Vector(rows.pop().unwrap()));
+    }
 
-        other => Err(format!("unexpected token in expression: {:?}", other)),
+    let cols = rows[0].len();
+    if rows.iter().any(|row| row.len() != cols) {
+        return Err(String::from("matrix rows must all have the same length"));
     }
+
+    Ok(Expression::Matrix(rows))
 }
 
 fn parse_infix(
@@ -83,9 +344,34 @@ fn parse_infix(
     let op_token = tokens.next().unwrap();
     let precedence = token_precedence(&op_token);
 
-    let rhs = parse_expression_pratt(tokens, precedence)?;
+    if let Some(op) = get_postfix_operator(&op_token) {
+        return Ok(Expression::Unary {
+            op,
+            rhs: Box::new(lhs),
+        });
+    }
+
+    if op_token == Token::In {
+        return match tokens.next() {
+            Some(Token::Unit(unit)) => Ok(Expression::Convert {
+                expr: Box::new(lhs),
+                unit,
+            }),
+            other => Err(format!(
+                "expected a unit after 'in', got {}",
+                found(other.as_ref())
+            )),
+        };
+    }
 
-    let op = get_operator(&op_token);
+    // A '|' after an operand that does not close an absolute value
+    let Some(op) = get_operator(&op_token) else {
+        return Err(String::from(
+            "'|' cannot follow a value here, write the product as 2*|x|",
+        ));
+    };
+
+    let rhs = parse_expression_pratt(tokens, precedence)?;
 
     Ok(Expression::Binary {
         lhs: Box::new(lhs),
@@ -98,16 +384,40 @@ fn parse_expression_pratt(
     tokens: &mut Peekable<impl Iterator<Item = Token>>,
     min_prec: Precedence,
 ) -> Result<Expression, String> {
-    let mut lhs = parse_prefix(tokens)?;
+    let lhs = parse_prefix(tokens)?;
+    parse_operators(lhs, tokens, min_prec)
+}
 
+// The operators that follow an operand, with the operands they take
+fn parse_operators(
+    mut lhs: Expression,
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+    min_prec: Precedence,
+) -> Result<Expression, String> {
     while let Some(next) = tokens.peek() {
-        let prec = token_precedence(next);
+        // A name or bracket straight after an operand multiplies it, as in 3x or (x - 1)(x + 1)
+        let implicit = matches!(next, Token::Ident(_) | Token::LParen);
+        let (prec, associativity) = if implicit {
+            (Precedence::Product, Associativity::Left)
+        } else {
+            (token_precedence(next), token_associativity(next))
+        };
 
-        if prec <= min_prec {
+        // An operator as tight as the one on our left only joins this operand when it
+        // groups to the right
+        if prec < min_prec || (prec == min_prec && associativity == Associativity::Left) {
             break;
         }
 
-        lhs = parse_infix(lhs, tokens)?;
+        lhs = if implicit {
+            Expression::Binary {
+                lhs: Box::new(lhs),
+                op: Operator::Mult,
+                rhs: Box::new(parse_expression_pratt(tokens, Precedence::Product)?),
+            }
+        } else {
+            parse_infix(lhs, tokens)?
+        };
     }
 
     Ok(lhs)
@@ -118,3 +428,19 @@ pub fn parse_expression(
 ) -> Result<Expression, String> {
     parse_expression_pratt(tokens, Precedence::Lowest)
 }
+
+// An expression that stops before an 'in', so the 'in Z' of assume x > 0 in Z is not read as a
+// unit conversion
+pub fn parse_bound(
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Result<Expression, String> {
+    parse_expression_pratt(tokens, Precedence::Conversion)
+}
+
+// The rest of an expression whose first operand has already been read
+pub fn continue_expression(
+    lhs: Expression,
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Result<Expression, String> {
+    parse_operators(lhs, tokens, Precedence::Lowest)
+}
@@ -0,0 +1,142 @@
+use crate::lexer::greek;
+use crate::parser::printer::binding_power;
+use crate::parser::{Associativity, Expression, Operator, UnaryOperator};
+
+impl Expression {
+    pub fn to_latex(&self) -> String {
+        match self {
+            Expression::Number(n) => format!("{n}"),
+            Expression::Integer(n) => n.to_string(),
+            Expression::Ident(name) => latex_name(name),
+            Expression::Quantity { value, unit } => format!("{value}\\,\\mathrm{{{unit}}}"),
+
+            Expression::Binary { lhs, op, rhs } => {
+                let power = binding_power(self);
+                let right = op.associativity() == Associativity::Right;
+                let (l, r) = (operand(lhs, power, right), operand(rhs, power, !right));
+
+                match op {
+                    Operator::Add => format!("{l} + {r}"),
+                    Operator::Sub => format!("{l} - {r}"),
+                    // Numbers next to each other need a visible product
+                    Operator::Mult if r.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                        format!("{l} \\cdot {r}")
+                    }
+                    Operator::Mult => format!("{l} {r}"),
+                    Operator::ElemMult => format!("{l} \\odot {r}"),
+                    Operator::Div => format!("\\frac{{{}}}{{{}}}", lhs.to_latex(), rhs.to_latex()),
+                    Operator::Rem => format!("{l} \\bmod {r}"),
+                    Operator::Pow => format!("{l}^{{{}}}", rhs.to_latex()),
+                }
+            }
+
+            Expression::Unary { op, rhs } => match op {
+                UnaryOperator::Neg => format!("-{}", operand(rhs, 5, false)),
+                UnaryOperator::Plus => format!("+{}", operand(rhs, 5, false)),
+                UnaryOperator::Not => format!("\\lnot {}", operand(rhs, 5, false)),
+                UnaryOperator::Abs => format!("\\left|{}\\right|", rhs.to_latex()),
+                UnaryOperator::Factorial => format!("{}!", operand(rhs, 6, false)),
+                UnaryOperator::Percent => format!("{}\\%", operand(rhs, 6, false)),
+                UnaryOperator::Degree => format!("{}^{{\\circ}}", operand(rhs, 6, false)),
+            },
+
+            Expression::Vector(elements) => {
+                let rows: Vec<String> = elements.iter().map(|e| e.to_latex()).collect();
+                format!(
+                    "\\begin{{bmatrix}} {} \\end{{bmatrix}}",
+                    rows.join(" \\\\ ")
+                )
+            }
+            Expression::Matrix(rows) => {
+                let rows: Vec<String> = rows
+                    .iter()
+                    .map(|row| {
+                        let cells: Vec<String> = row.iter().map(|e| e.to_latex()).collect();
+                        cells.join(" & ")
+                    })
+                    .collect();
+                format!(
+                    "\\begin{{bmatrix}} {} \\end{{bmatrix}}",
+                    rows.join(" \\\\ ")
+                )
+            }
+            Expression::Call { name, args } => {
+                let args: Vec<String> = args.iter().map(|e| e.to_latex()).collect();
+                format!("\\operatorname{{{name}}}\\left({}\\right)", args.join(", "))
+            }
+
+            Expression::Convert { expr, unit } => {
+                format!("{} \\to \\mathrm{{{unit}}}", expr.to_latex())
+            }
+            Expression::Equation { lhs, rhs } => format!("{} = {}", lhs.to_latex(), rhs.to_latex()),
+            Expression::Range { name, from, to, .. } => format!(
+                "{} \\in \\left[{}, {}\\right]",
+                latex_name(name),
+                from.to_latex(),
+                to.to_latex()
+            ),
+        }
+    }
+}
+
+fn operand(expr: &Expression, power: u8, tied: bool) -> String {
+    let own = binding_power(expr);
+    if own < power || (tied && own == power) {
+        format!("\\left({}\\right)", expr.to_latex())
+    } else {
+        expr.to_latex()
+    }
+}
+
+// θ_max becomes \theta_{\mathrm{max}} and x_1 becomes x_{1}
+fn latex_name(name: &str) -> String {
+    match name.split_once('_') {
+        Some((base, subscript)) if !base.is_empty() && !subscript.is_empty() => {
+            format!("{}_{{{}}}", latex_word(base), latex_word(subscript))
+        }
+        _ => latex_word(name),
+    }
+}
+
+fn latex_word(word: &str) -> String {
+    let mut chars = word.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => match greek::name(c) {
+            Some(name) => format!("\\{name}"),
+            None => c.to_string(),
+        },
+        _ if word.chars().all(|c| c.is_ascii_digit()) => word.to_string(),
+        // Δt is a Greek letter next to a variable rather than one long name
+        _ if word.chars().any(|c| greek::name(c).is_some()) => word
+            .chars()
+            .map(|c| latex_word(&c.to_string()))
+            .collect::<Vec<String>>()
+            .join(" "),
+        _ => format!("\\mathrm{{{word}}}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer;
+    use crate::parser::parse_expression;
+
+    fn latex(input: &str) -> String {
+        let tokens = lexer::tokenize(input.to_string()).unwrap();
+        let expr = parse_expression(&mut tokens.into_iter().peekable()).unwrap();
+        expr.to_latex()
+    }
+
+    #[test]
+    fn renders_names_with_subscripts() {
+        assert_eq!(latex("x_1 + theta_max"), "x_{1} + \\theta_{\\mathrm{max}}");
+        assert_eq!(latex("Δt * v_0"), "\\Delta t v_{0}");
+    }
+
+    #[test]
+    fn renders_fractions_and_powers() {
+        assert_eq!(latex("(a + 1)/2"), "\\frac{a + 1}{2}");
+        assert_eq!(latex("(x + 1)^(n - 1)"), "\\left(x + 1\\right)^{n - 1}");
+        assert_eq!(latex("2 * 3x"), "2 \\cdot 3 x");
+    }
+}
@@ -1,46 +1,237 @@
+pub mod error;
 pub mod expr;
+pub mod latex;
 pub mod operator;
+pub mod printer;
 pub mod statement;
 
+pub use error::ParseError;
 pub use expr::{Expression, parse_expression};
-pub use operator::{Operator, get_operator};
-pub use statement::Statement;
+pub use operator::{
+    Associativity, Operator, UnaryOperator, get_operator, get_postfix_operator, get_prefix_operator,
+};
+pub use statement::{Assumption, Comparison, Constraint, Goal, NumberSet, Statement};
 
-use crate::lexer::token::Token;
+use crate::lexer::{Span, token::Token};
+use std::cell::{Cell, RefCell};
 use std::iter::Peekable;
+use std::rc::Rc;
+
+// Counts the tokens pulled out of the stream, so an error can point at the one the
+// parser stopped on even though the statement parsers only see plain tokens. A 'let' or
+// 'assume' pulled last can be handed back, for a broken statement that ran into one
+pub struct Tracked<I> {
+    tokens: I,
+    position: Rc<Cell<usize>>,
+    separator: Rc<Cell<bool>>,
+    keyword: Rc<Cell<Option<Token>>>,
+    returned: Rc<RefCell<Vec<Token>>>,
+}
+
+impl<I: Iterator<Item = Token>> Iterator for Tracked<I> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .returned
+            .borrow_mut()
+            .pop()
+            .or_else(|| self.tokens.next());
+        self.position.set(self.position.get() + 1);
+        self.separator
+            .set(matches!(token, Some(Token::Semicolon | Token::Newline)));
+        self.keyword.set(match token {
+            Some(Token::Let) => Some(Token::Let),
+            Some(Token::Assume) => Some(Token::Assume),
+            _ => None,
+        });
+        token
+    }
+}
 
 pub struct Parser<I: Iterator<Item = Token>> {
-    tokens: Peekable<I>,
+    tokens: Peekable<Tracked<I>>,
+    position: Rc<Cell<usize>>,
+    separator: Rc<Cell<bool>>,
+    keyword: Rc<Cell<Option<Token>>>,
+    returned: Rc<RefCell<Vec<Token>>>,
+    spans: Vec<Span>,
+    lines: Vec<(usize, usize)>,
+    errors: Vec<ParseError>,
+}
+
+impl Parser<std::vec::IntoIter<Token>> {
+    pub fn with_spans(tokens: Vec<(Token, Span)>) -> Self {
+        let (tokens, spans): (Vec<Token>, Vec<Span>) = tokens.into_iter().unzip();
+        let mut parser = Self::new(tokens.into_iter());
+        parser.spans = spans;
+        parser
+    }
 }
 
 impl<I: Iterator<Item = Token>> Parser<I> {
     pub fn new(tokens: I) -> Self {
+        let position = Rc::new(Cell::new(0));
+        let separator = Rc::new(Cell::new(false));
+        let keyword = Rc::new(Cell::new(None));
+        let returned = Rc::new(RefCell::new(Vec::new()));
         Self {
-            tokens: tokens.peekable(),
+            tokens: Tracked {
+                tokens,
+                position: position.clone(),
+                separator: separator.clone(),
+                keyword: keyword.clone(),
+                returned: returned.clone(),
+            }
+            .peekable(),
+            position,
+            separator,
+            keyword,
+            returned,
+            spans: Vec::new(),
+            lines: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
+    // Every error found by the last run_parser, in source order
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    // The first and last line of each parsed statement, when the tokens came with spans
+    pub fn statement_lines(&self) -> &[(usize, usize)] {
+        &self.lines
+    }
+
+    // Statements end at a ';' or at a line break the lexer kept. A broken one is reported
+    // and skipped up to the next terminator or 'let' so the rest of the file still gets parsed
     pub fn run_parser(&mut self) -> Vec<Statement> {
         let mut statement_vec = Vec::new();
 
         while let Some(token) = self.tokens.peek() {
+            let line = |position: usize| self.spans.get(position).map_or(0, |span| span.line);
+            let start = self.position.get();
+            let first = line(start.wrapping_sub(1));
+
             let statement = match token {
                 Token::Let => statement::parse_let_statement(&mut self.tokens),
+                Token::Assume => statement::parse_assume_statement(&mut self.tokens),
+                Token::Maximize | Token::Minimize => {
+                    statement::parse_optimize_statement(&mut self.tokens)
+                }
 
-                Token::Ident(_) | Token::Number(_) | Token::Minus | Token::Pipe => {
-                    statement::parse_assign_statement(&mut self.tokens)
+                Token::Ident(_)
+                | Token::Number(_)
+                | Token::Integer(_)
+                | Token::Minus
+                | Token::Plus
+                | Token::Bang
+                | Token::Pipe
+                | Token::LParen
+                | Token::LBracket
+                | Token::Malformed { .. } => statement::parse_assign_statement(&mut self.tokens),
+                Token::Semicolon => Err(String::from("extra ';' with no statement before it")),
+                other => Err(format!(
+                    "illegal start of a statement: {}",
+                    found(Some(other))
+                )),
+            }
+            .and_then(|statement| match self.tokens.peek() {
+                None | Some(Token::Semicolon | Token::Newline) => Ok(statement),
+                Some(Token::Malformed { message, .. }) => {
+                    let message = message.clone();
+                    self.tokens.next();
+                    Err(message)
                 }
-                _ => {
-                    parsing_error(String::from("Illegal statement"), &mut self.tokens);
-                    unreachable!("Illegal statement should have been handled")
+                other => Err(format!(
+                    "expected ';' or a line break after a statement, got {}",
+                    found(other)
+                )),
+            });
+
+            match statement {
+                Ok(statement) => {
+                    // The terminator has been peeked, so the statement ended one token before it
+                    let last = self.position.get().wrapping_sub(2);
+                    let last = self.spans.get(last).map_or(first, |span| span.line);
+                    self.lines.push((first, last));
+                    statement_vec.push(statement);
+                    self.tokens.next();
                 }
-            };
-            statement_vec.push(statement);
-            self.tokens.next(); // Make sure to parse without consuming last token, consume it here
+                Err(message) => self.recover(message, start),
+            }
         }
 
         statement_vec
     }
+
+    fn recover(&mut self, message: String, start: usize) {
+        let span = self.error_span();
+        self.errors.push(ParseError { message, span });
+
+        // A 'let' the broken statement consumed, rather than its own, starts the next one.
+        // It is handed back with whatever peeking has pulled in after it
+        let keyword = self.keyword.take();
+        let (pulled, separator) = (self.position.get(), self.separator.get());
+        self.tokens.peek();
+        if let Some(keyword) = keyword
+            && pulled > start
+            && self.position.get() > pulled
+        {
+            let mut returned = self.returned.borrow_mut();
+            returned.extend(self.tokens.next());
+            returned.push(keyword);
+            self.position.set(pulled - 1);
+            return;
+        }
+
+        // Peeking pulls a new token only if the one the error was on was consumed,
+        // and a consumed ';' already ends the broken statement
+        if self.tokens.peek().is_some() && self.position.get() > pulled && separator {
+            return;
+        }
+
+        let mut depth = 0;
+        while let Some(token) = self.tokens.peek() {
+            match token {
+                Token::Let | Token::Assume => return,
+                Token::Semicolon | Token::Newline if depth <= 0 => {
+                    self.tokens.next();
+                    return;
+                }
+                Token::LParen | Token::LBracket | Token::Modulo => depth += 1,
+                Token::RParen | Token::RBracket => depth -= 1,
+                _ => {}
+            }
+            self.tokens.next();
+        }
+    }
+
+    // The last token pulled from the stream, or just past the end of the input
+    fn error_span(&self) -> Span {
+        let position = self.position.get();
+        match self.spans.get(position.wrapping_sub(1)) {
+            Some(span) => *span,
+            None => self
+                .spans
+                .last()
+                .map(|last| Span {
+                    start: last.end,
+                    ..*last
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// A token as error messages quote it, as in "expected ')', got ']'"
+pub fn found(token: Option<&Token>) -> String {
+    match token {
+        None => String::from("the end of the input"),
+        Some(Token::Newline) => String::from("a line break"),
+        Some(token) => format!("'{token}'"),
+    }
 }
 
 //Advances TWICE
@@ -53,19 +244,10 @@ pub fn expect_kind(
             let tok = tokens.next();
             Ok(tok.unwrap())
         }
-        other => Err(format!("expected {:?}, got {:?}", kind, other)),
+        other => Err(format!("expected {kind}, got {}", found(other))),
     }
 }
 
-pub fn parsing_error(error: String, tokens: &mut Peekable<impl Iterator<Item = Token>>) {
-    eprintln!(
-        "{error}: failed_at->{:?}, next->{:?}",
-        tokens.next().unwrap(),
-        tokens.peek().unwrap()
-    );
-    std::process::exit(1);
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,11 +256,97 @@ mod tests {
     use expr::{Expression, parse_expression};
 
     fn parse_expr(input: String) -> Expression {
-        let tokens = lexer::tokenize(input);
+        let tokens = lexer::tokenize(input).unwrap();
         let mut iter = tokens.into_iter().peekable();
         parse_expression(&mut iter).unwrap()
     }
 
+    // Every binary and unary node in brackets, to pin down how an input groups
+    fn grouped(input: &str) -> String {
+        fn group(expr: &Expression) -> String {
+            match expr {
+                Expression::Binary { lhs, op, rhs } => {
+                    let lhs = group(lhs);
+                    let rhs = group(rhs);
+                    let symbol = match op {
+                        Operator::Add => "+",
+                        Operator::Sub => "-",
+                        Operator::Mult => "*",
+                        Operator::ElemMult => ".*",
+                        Operator::Div => "/",
+                        Operator::Rem => "%",
+                        Operator::Pow => "^",
+                    };
+                    format!("({lhs} {symbol} {rhs})")
+                }
+                Expression::Unary { op, rhs } => {
+                    let rhs = group(rhs);
+                    match op {
+                        UnaryOperator::Neg => format!("(-{rhs})"),
+                        UnaryOperator::Plus => format!("(+{rhs})"),
+                        UnaryOperator::Not => format!("(!{rhs})"),
+                        UnaryOperator::Abs => format!("|{rhs}|"),
+                        UnaryOperator::Factorial => format!("({rhs}!)"),
+                        UnaryOperator::Percent => format!("({rhs}%)"),
+                        UnaryOperator::Degree => format!("({rhs}°)"),
+                    }
+                }
+                expr => expr.to_string(),
+            }
+        }
+        group(&parse_expr(input.to_string()))
+    }
+
+    #[test]
+    fn groups_by_precedence_and_associativity() {
+        for (input, expected) in [
+            // left-associative chains
+            ("a - b - c", "((a - b) - c)"),
+            ("a / b / c", "((a / b) / c)"),
+            ("a % b % c", "((a % b) % c)"),
+            ("a / b * c", "((a / b) * c)"),
+            ("a % b * c", "((a % b) * c)"),
+            ("a * b % c", "((a * b) % c)"),
+            ("a .* b / c", "((a .* b) / c)"),
+            // '^' groups to the right
+            ("a ^ b ^ c", "(a ^ (b ^ c))"),
+            ("(a ^ b) ^ c", "((a ^ b) ^ c)"),
+            // sums below products below powers
+            ("a + b % c", "(a + (b % c))"),
+            ("a - b * c ^ d", "(a - (b * (c ^ d)))"),
+            ("a % b ^ c", "(a % (b ^ c))"),
+            ("2x^2", "(2 * (x ^ 2))"),
+            // prefix operators sit between products and powers
+            ("-a ^ b", "(-(a ^ b))"),
+            ("-a * b", "((-a) * b)"),
+            ("a ^ -b", "(a ^ (-b))"),
+            ("a ^ -b ^ c", "(a ^ (-(b ^ c)))"),
+            ("-a - -b", "((-a) - (-b))"),
+            ("!a + b", "((!a) + b)"),
+            ("+a ^ b", "(+(a ^ b))"),
+            // postfix operators bind tightest
+            ("-a!", "(-(a!))"),
+            ("a ^ b!", "(a ^ (b!))"),
+            ("a! ^ b", "((a!) ^ b)"),
+            ("a% * b", "((a%) * b)"),
+            ("a % b%", "(a % (b%))"),
+            ("-30°", "(-(30°))"),
+            ("|a - b|!", "(|(a - b)|!)"),
+            // the same rules with number literals
+            ("2^3^2", "(2 ^ (3 ^ 2))"),
+            ("-2^2", "(-(2 ^ 2))"),
+            ("2^-2", "(2 ^ (-2))"),
+            ("2^3!", "(2 ^ (3!))"),
+            ("-3!", "(-(3!))"),
+            ("7 % 3 * 2", "((7 % 3) * 2)"),
+            ("7 % -2", "(7 % (-2))"),
+            ("1 + 7 % 4 ^ 2", "(1 + (7 % (4 ^ 2)))"),
+            ("50% * 4", "((50%) * 4)"),
+        ] {
+            assert_eq!(grouped(input), expected, "{input}");
+        }
+    }
+
     #[test]
     fn parses_number() {
         let expr = parse_expr("42".to_string());
@@ -125,16 +393,69 @@ mod tests {
         assert_eq!(
             expr,
             Expression::Unary {
-                op: "-".into(),
+                op: UnaryOperator::Neg,
                 rhs: Box::new(Expression::Number(5.0)),
             }
         );
     }
 
+    #[test]
+    fn parses_prefix_operators() {
+        let unary = |op, rhs| Expression::Unary {
+            op,
+            rhs: Box::new(rhs),
+        };
+
+        assert_eq!(
+            parse_expr("+x".to_string()),
+            unary(UnaryOperator::Plus, Expression::Ident("x".into()))
+        );
+        assert_eq!(
+            parse_expr("!-1".to_string()),
+            unary(
+                UnaryOperator::Not,
+                unary(UnaryOperator::Neg, Expression::Number(1.0))
+            )
+        );
+    }
+
+    #[test]
+    fn parses_postfix_operators() {
+        let unary = |op, rhs| Expression::Unary {
+            op,
+            rhs: Box::new(rhs),
+        };
+
+        // -3! is -(3!) and 2^3! is 2^(3!)
+        assert_eq!(
+            parse_expr("-3!".to_string()),
+            unary(
+                UnaryOperator::Neg,
+                unary(UnaryOperator::Factorial, Expression::Number(3.0))
+            )
+        );
+        assert_eq!(
+            parse_expr("2^3!".to_string()),
+            Expression::Binary {
+                lhs: Box::new(Expression::Number(2.0)),
+                op: Operator::Pow,
+                rhs: Box::new(unary(UnaryOperator::Factorial, Expression::Number(3.0))),
+            }
+        );
+        assert_eq!(
+            parse_expr("x%".to_string()),
+            unary(UnaryOperator::Percent, Expression::Ident("x".into()))
+        );
+        assert_eq!(
+            parse_expr("30deg".to_string()),
+            unary(UnaryOperator::Degree, Expression::Number(30.0))
+        );
+    }
+
     #[test]
     fn parses_let_with_expression() {
         let input = String::from("let f -> 6 + 3 * y");
-        let tokens = lexer::tokenize(input);
+        let tokens = lexer::tokenize(input).unwrap();
 
         let mut parser = parser::Parser::new(tokens.into_iter());
         let ast = parser.run_parser();
@@ -157,7 +478,7 @@ mod tests {
     #[test]
     fn parses_let_with_assignment() {
         let input = String::from("let f -> x = 10");
-        let tokens = lexer::tokenize(input);
+        let tokens = lexer::tokenize(input).unwrap();
 
         let mut parser = parser::Parser::new(tokens.into_iter());
         let ast = parser.run_parser();
@@ -173,7 +494,7 @@ mod tests {
     #[test]
     fn parser_assign_statement() {
         let input = String::from("f+g = 10+h;");
-        let tokens = lexer::tokenize(input);
+        let tokens = lexer::tokenize(input).unwrap();
 
         let mut parser = parser::Parser::new(tokens.into_iter());
         let ast = parser.run_parser();
@@ -231,7 +552,7 @@ mod tests {
         assert_eq!(
             expr,
             Expression::Unary {
-                op: "-".into(),
+                op: UnaryOperator::Neg,
                 rhs: Box::new(Expression::Binary {
                     lhs: Box::new(Expression::Number(1.0)),
                     op: Operator::Add,
@@ -240,4 +561,256 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn parses_vector_and_matrix_literals() {
+        let expr = parse_expr("[1, 2; 3, 4] * [5, 6]".to_string());
+
+        assert_eq!(
+            expr,
+            Expression::Binary {
+                lhs: Box::new(Expression::Matrix(vec![
+                    vec![Expression::Number(1.0), Expression::Number(2.0)],
+                    vec![Expression::Number(3.0), Expression::Number(4.0)],
+                ])),
+                op: Operator::Mult,
+                rhs: Box::new(Expression::Vector(vec![
+                    Expression::Number(5.0),
+                    Expression::Number(6.0),
+                ])),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_function_call() {
+        let expr = parse_expr("solve(A, [1, 2])".to_string());
+
+        assert_eq!(
+            expr,
+            Expression::Call {
+                name: "solve".into(),
+                args: vec![
+                    Expression::Ident("A".into()),
+                    Expression::Vector(vec![Expression::Number(1.0), Expression::Number(2.0)]),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn parses_implicit_multiplication() {
+        assert_eq!(
+            parse_expr("3x^2".to_string()),
+            parse_expr("3 * x^2".to_string())
+        );
+        assert_eq!(
+            parse_expr("(x - 2)(x - 3)".to_string()),
+            parse_expr("(x - 2) * (x - 3)".to_string())
+        );
+        assert_eq!(
+            parse_expr("f(x)".to_string()),
+            Expression::Call {
+                name: "f".into(),
+                args: vec![Expression::Ident("x".into())],
+            }
+        );
+    }
+
+    #[test]
+    fn parses_equations_as_arguments() {
+        let expr = parse_expr("subs(x + y, x = 2)".to_string());
+
+        assert_eq!(
+            expr,
+            Expression::Call {
+                name: "subs".into(),
+                args: vec![
+                    parse_expr("x + y".to_string()),
+                    Expression::Equation {
+                        lhs: Box::new(Expression::Ident("x".into())),
+                        rhs: Box::new(Expression::Number(2.0)),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn parses_range_arguments() {
+        let expr = parse_expr("table(x^2, x from 0 to 10 step 0.5)".to_string());
+
+        let Expression::Call { args, .. } = expr else {
+            panic!("expected a call");
+        };
+        assert_eq!(
+            args[1],
+            Expression::Range {
+                name: "x".into(),
+                from: Box::new(Expression::Number(0.0)),
+                to: Box::new(Expression::Number(10.0)),
+                step: Some(Box::new(Expression::Number(0.5))),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_ragged_matrix() {
+        let tokens = lexer::tokenize("[1, 2; 3]".to_string()).unwrap();
+        let mut iter = tokens.into_iter().peekable();
+
+        assert!(parse_expression(&mut iter).is_err());
+    }
+
+    #[test]
+    fn rejects_a_bar_after_a_value() {
+        for input in ["2 |3|", "5! |2|", "30° |2|", "|2| |3|"] {
+            let tokens = lexer::tokenize(input.to_string()).unwrap();
+            let mut iter = tokens.into_iter().peekable();
+
+            assert!(parse_expression(&mut iter).is_err(), "{input}");
+        }
+        assert_eq!(grouped("2 * |3| + |a|"), "((2 * |3|) + |a|)");
+    }
+
+    fn parse_spanned(input: &str) -> (Vec<Statement>, Vec<ParseError>) {
+        let tokens = lexer::tokenize_spanned(input);
+        let mut parser = parser::Parser::with_spans(tokens);
+        let ast = parser.run_parser();
+        (ast, parser.errors().to_vec())
+    }
+
+    #[test]
+    fn reports_malformed_literals_and_carries_on() {
+        let (ast, errors) = parse_spanned("x = 1.2.3\ny = 0x\nz = 2 1e39\nlet w -> 4\nw + )");
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            [
+                "line 1, column 5: malformed number '1.2.3'",
+                "line 2, column 5: 0x needs hex digits after it",
+                "line 3, column 7: 1e39 is too large",
+                "line 5, column 5: unexpected token in expression: ')'",
+            ]
+        );
+        assert_eq!(ast.len(), 1);
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let (ast, errors) = parse_spanned("let a -> 2;\nx = 1 + ;\nlet b -> (3 * ;\na + 1; b +");
+        let places: Vec<(usize, usize)> =
+            errors.iter().map(|e| (e.span.line, e.span.start)).collect();
+
+        assert_eq!(places, vec![(2, 8), (3, 14), (4, 10)]);
+        assert_eq!(ast.len(), 2);
+        assert_eq!(ast[1], Statement::Expr(parse_expr("a + 1".into())));
+    }
+
+    #[test]
+    fn resynchronises_on_let() {
+        let (ast, errors) = parse_spanned("c = 4 let d -> 5; ) + 1; e");
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].message.contains("expected ';'"));
+        assert!(matches!(&ast[0], Statement::Let { name, .. } if name == "d"));
+        assert_eq!(ast[1], Statement::Expr(Expression::Ident("e".into())));
+
+        for input in ["b +\nlet c -> 2\nc + 1", "x = [1, 2\nlet c -> 2\nc + 1"] {
+            let (ast, errors) = parse_spanned(input);
+
+            assert_eq!(errors.len(), 1, "{input}");
+            assert_eq!(errors[0].span.line, 2, "{input}");
+            assert!(matches!(&ast[0], Statement::Let { name, .. } if name == "c"));
+            assert_eq!(ast[1], Statement::Expr(parse_expr("c + 1".into())));
+        }
+    }
+
+    #[test]
+    fn newlines_terminate_statements() {
+        let with = parse_spanned("let f -> 5x + 7y = 10;\n\nf + g = 20;\n");
+        let without = parse_spanned("let f -> 5x + 7y = 10\n\nf + g =\n  20\n");
+
+        assert_eq!(with, without);
+        assert_eq!(with.0.len(), 2);
+        assert!(with.1.is_empty());
+    }
+
+    #[test]
+    fn reports_missing_and_extra_terminators() {
+        let (ast, errors) = parse_spanned("x = 1;;\ny = 2 3\nz");
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "extra ';' with no statement before it",
+                "expected ';' or a line break after a statement, got '3'",
+            ]
+        );
+        assert_eq!(ast.len(), 2);
+    }
+
+    #[test]
+    fn parses_congruences() {
+        let (ast, errors) = parse_spanned("3x + 2 ≡ 5 (mod 11)\nx = 2 (mod 7)\nx ≡ 1\nx = 2 (3)");
+        assert_eq!(ast.len(), 3);
+        assert_eq!(
+            ast[0],
+            Statement::Congruence {
+                lhs: parse_expr("3x + 2".into()),
+                rhs: Expression::Number(5.0),
+                modulus: Expression::Number(11.0),
+            }
+        );
+        assert_eq!(ast[1].to_string(), "x ≡ 2 (mod 7)");
+        assert!(matches!(ast[2], Statement::Assign { .. }));
+        assert_eq!(errors[0].message, "expected (mod m) after x ≡ 1");
+    }
+
+    #[test]
+    fn parses_assumptions() {
+        let (ast, errors) = parse_spanned(
+            "assume x > 0\nassume n ∈ ℤ\nassume m in N\nassume y ≤ -2\nassume k >= 1 in N\nassume z",
+        );
+        let printed: Vec<String> = ast.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            printed,
+            [
+                "assume x > 0",
+                "assume n in Z",
+                "assume m in N",
+                "assume y <= -2",
+                "assume k >= 1 in N"
+            ]
+        );
+        assert_eq!(
+            errors[0].message,
+            "expected a comparison such as > 0 or a set such as in Z, got the end of the input"
+        );
+    }
+
+    #[test]
+    fn parses_optimizations() {
+        let (ast, errors) = parse_spanned(
+            "maximize 3x + 2y subject to x + y <= 4,\n x + 3y <= 6, x >= 0\nminimize x\nminimize x subject to x > 0\nminimize(f, x)\nminimize (x - 1)^2",
+        );
+        assert_eq!(
+            ast[0].to_string(),
+            "maximize 3x + 2y subject to x + y <= 4, x + 3y <= 6, x >= 0"
+        );
+        let Statement::Optimize { constraints, .. } = &ast[0] else {
+            panic!("expected an optimization, got {:?}", ast[0]);
+        };
+        assert_eq!(constraints[1].comparison, Comparison::AtMost);
+        assert_eq!(ast[1].to_string(), "minimize x");
+        assert_eq!(ast[2].to_string(), "minimize(f, x)");
+        assert!(matches!(ast[2], Statement::Expr(Expression::Call { .. })));
+        assert_eq!(ast[3].to_string(), "minimize (x - 1)^2");
+        assert_eq!(
+            errors[0].message,
+            "constraints cannot be strict, use <= or >= instead"
+        );
+    }
 }
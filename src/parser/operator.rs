@@ -5,20 +5,70 @@ pub enum Operator {
     Add,
     Sub,
     Mult,
+    ElemMult,
     Div,
     Rem,
     Pow,
 }
 
-pub fn get_operator(op_token: &Token) -> Operator {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Associativity {
+    Left,  // a - b - c is (a - b) - c
+    Right, // a^b^c is a^(b^c)
+}
+
+impl Operator {
+    pub fn associativity(&self) -> Associativity {
+        match self {
+            Operator::Pow => Associativity::Right,
+            Operator::Add
+            | Operator::Sub
+            | Operator::Mult
+            | Operator::ElemMult
+            | Operator::Div
+            | Operator::Rem => Associativity::Left,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Neg,       // -x
+    Plus,      // +x
+    Not,       // !x, 1 for zero and 0 for anything else
+    Abs,       // |x|
+    Factorial, // x!
+    Percent,   // x%
+    Degree,    // x° or x deg, in radians
+}
+
+pub fn get_operator(op_token: &Token) -> Option<Operator> {
+    match op_token {
+        Token::Plus => Some(Operator::Add),
+        Token::Minus => Some(Operator::Sub),
+        Token::Mult => Some(Operator::Mult),
+        Token::DotMult => Some(Operator::ElemMult),
+        Token::Div => Some(Operator::Div),
+        Token::Mod => Some(Operator::Rem),
+        Token::Pow => Some(Operator::Pow),
+        _ => None,
+    }
+}
+
+pub fn get_prefix_operator(op_token: &Token) -> Option<UnaryOperator> {
     match op_token {
-        Token::Plus => Operator::Add,
-        Token::Minus => Operator::Sub,
-        Token::Mult => Operator::Mult,
-        Token::Div => Operator::Div,
-        Token::Mod => Operator::Rem,
-        Token::Pow => Operator::Pow,
+        Token::Minus => Some(UnaryOperator::Neg),
+        Token::Plus => Some(UnaryOperator::Plus),
+        Token::Bang => Some(UnaryOperator::Not),
+        _ => None,
+    }
+}
 
-        _ => unreachable!(),
+pub fn get_postfix_operator(op_token: &Token) -> Option<UnaryOperator> {
+    match op_token {
+        Token::Bang => Some(UnaryOperator::Factorial),
+        Token::Percent => Some(UnaryOperator::Percent),
+        Token::Degree => Some(UnaryOperator::Degree),
+        _ => None,
     }
 }
@@ -0,0 +1,304 @@
+use crate::lexer::token::is_ident_continue;
+use crate::parser::{
+    Associativity, Assumption, Comparison, Constraint, Expression, Goal, NumberSet, Operator,
+    Statement, UnaryOperator,
+};
+use std::fmt;
+
+// How tightly an expression holds together when printed, mirroring the parser's precedences
+pub(crate) fn binding_power(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Equation { .. } | Expression::Range { .. } => 0,
+        Expression::Convert { .. } => 1,
+        Expression::Binary { op, .. } => match op {
+            Operator::Add | Operator::Sub => 2,
+            Operator::Mult | Operator::ElemMult | Operator::Div | Operator::Rem => 3,
+            Operator::Pow => 5,
+        },
+        Expression::Unary { op, .. } => match op {
+            UnaryOperator::Neg | UnaryOperator::Plus | UnaryOperator::Not => 4,
+            UnaryOperator::Abs
+            | UnaryOperator::Factorial
+            | UnaryOperator::Percent
+            | UnaryOperator::Degree => 6,
+        },
+        Expression::Number(n) if n.is_sign_negative() && *n != 0.0 => 4,
+        Expression::Integer(n) if n.is_negative() => 4,
+        _ => 6,
+    }
+}
+
+// An operand as tight as its operator keeps its brackets on the side the operator does not
+// group towards: the right of a - (b - c) and the left of (a^b)^c
+fn operand(expr: &Expression, power: u8, tied: bool) -> String {
+    let own = binding_power(expr);
+    if own < power || (tied && own == power) {
+        format!("({expr})")
+    } else {
+        expr.to_string()
+    }
+}
+
+// 3x, 2(x + 1) and (x - 2)(x - 3) read back as products, but x(y) is a call, so it
+// keeps its '*'. Units need a space after the number, so 2m is a product too
+fn implicit_product(lhs: &Expression, lhs_text: &str, rhs_text: &str) -> bool {
+    let number = match lhs {
+        Expression::Unary {
+            op: UnaryOperator::Neg,
+            rhs,
+        } => matches!(**rhs, Expression::Number(_)),
+        Expression::Number(n) => *n >= 0.0,
+        Expression::Integer(n) => !n.is_negative(),
+        _ => false,
+    };
+    if !number && !lhs_text.ends_with(')') {
+        return false;
+    }
+
+    match rhs_text.chars().next() {
+        Some('(') => true,
+        Some(c) if c.is_alphabetic() || c == '_' => {
+            let word: String = rhs_text
+                .chars()
+                .take_while(|c| c.is_ascii_alphabetic())
+                .collect();
+            let name: String = rhs_text
+                .chars()
+                .take_while(|&c| is_ident_continue(c))
+                .collect();
+            // 2e would read back as an exponent, 0x as a hex prefix and 3deg as degrees
+            let literal = word.starts_with(['e', 'E']) || lhs_text == "0" || name == "deg";
+            !(number && literal)
+        }
+        _ => false,
+    }
+}
+
+// x_1 prints as x₁, other subscripts such as θ_max keep their underscore
+pub fn pretty_name(name: &str) -> String {
+    match name.split_once('_') {
+        Some((base, digits))
+            if !base.is_empty()
+                && !digits.is_empty()
+                && digits.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            let subscript = digits
+                .chars()
+                .map(|d| char::from_u32('₀' as u32 + d.to_digit(10).unwrap()).unwrap());
+            base.chars().chain(subscript).collect()
+        }
+        _ => name.to_string(),
+    }
+}
+
+// Very large and very small numbers keep the exponent rather than a run of zeros
+fn literal(n: f32) -> String {
+    if n != 0.0 && !(1e-5..1e16).contains(&n.abs()) {
+        format!("{n:e}")
+    } else {
+        n.to_string()
+    }
+}
+
+fn join(elements: &[Expression]) -> String {
+    elements
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Number(n) => write!(f, "{}", literal(*n)),
+            Expression::Integer(n) => write!(f, "{n}"),
+            Expression::Ident(name) => write!(f, "{}", pretty_name(name)),
+            Expression::Quantity { value, unit } => write!(f, "{} {unit}", literal(*value)),
+
+            Expression::Binary { lhs, op, rhs } => {
+                let power = binding_power(self);
+                let right = op.associativity() == Associativity::Right;
+                let (lhs_text, rhs_text) =
+                    (operand(lhs, power, right), operand(rhs, power, !right));
+
+                let symbol = match op {
+                    Operator::Add => " + ",
+                    Operator::Sub => " - ",
+                    Operator::Mult if implicit_product(lhs, &lhs_text, &rhs_text) => "",
+                    Operator::Mult => "*",
+                    Operator::ElemMult => " .* ",
+                    Operator::Div => "/",
+                    Operator::Rem => "%",
+                    Operator::Pow => "^",
+                };
+                write!(f, "{lhs_text}{symbol}{rhs_text}")
+            }
+
+            Expression::Unary { op, rhs } => match op {
+                UnaryOperator::Neg => write!(f, "-{}", operand(rhs, 5, false)),
+                UnaryOperator::Plus => write!(f, "+{}", operand(rhs, 5, false)),
+                UnaryOperator::Not => write!(f, "!{}", operand(rhs, 5, false)),
+                UnaryOperator::Abs => write!(f, "|{rhs}|"),
+                UnaryOperator::Factorial => write!(f, "{}!", operand(rhs, 6, false)),
+                UnaryOperator::Percent => write!(f, "{}%", operand(rhs, 6, false)),
+                UnaryOperator::Degree => write!(f, "{}°", operand(rhs, 6, false)),
+            },
+
+            Expression::Vector(elements) => write!(f, "[{}]", join(elements)),
+            Expression::Matrix(rows) => {
+                let rows: Vec<String> = rows.iter().map(|row| join(row)).collect();
+                write!(f, "[{}]", rows.join("; "))
+            }
+            Expression::Call { name, args } => write!(f, "{name}({})", join(args)),
+            Expression::Equation { lhs, rhs } => write!(f, "{lhs} = {rhs}"),
+            Expression::Range {
+                name,
+                from,
+                to,
+                step,
+            } => {
+                write!(f, "{name} from {from} to {to}")?;
+                match step {
+                    Some(step) => write!(f, " step {step}"),
+                    None => Ok(()),
+                }
+            }
+            Expression::Convert { expr, unit } => {
+                write!(f, "{} in {unit}", operand(expr, 1, false))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Assumption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assumption::Greater(bound) => write!(f, "> {bound}"),
+            Assumption::AtLeast(bound) => write!(f, ">= {bound}"),
+            Assumption::Less(bound) => write!(f, "< {bound}"),
+            Assumption::AtMost(bound) => write!(f, "<= {bound}"),
+            Assumption::In(NumberSet::Integers) => write!(f, "in Z"),
+            Assumption::In(NumberSet::Naturals) => write!(f, "in N"),
+            Assumption::In(NumberSet::Reals) => write!(f, "in R"),
+        }
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Goal::Maximize => write!(f, "maximize"),
+            Goal::Minimize => write!(f, "minimize"),
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.comparison {
+            Comparison::AtMost => "<=",
+            Comparison::AtLeast => ">=",
+            Comparison::Equal => "=",
+        };
+        write!(f, "{} {op} {}", self.lhs, self.rhs)
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Let { name, expr, value } => {
+                write!(f, "let {} -> {expr}", pretty_name(name))?;
+                match value {
+                    Some(value) => write!(f, " = {value}"),
+                    None => Ok(()),
+                }
+            }
+            Statement::Assign { expr, value } => write!(f, "{expr} = {value}"),
+            Statement::Congruence { lhs, rhs, modulus } => {
+                write!(f, "{lhs} ≡ {rhs} (mod {modulus})")
+            }
+            Statement::Expr(expr) => write!(f, "{expr}"),
+            Statement::Interval { name, lo, hi } => {
+                write!(f, "let {} in [{lo}, {hi}]", pretty_name(name))
+            }
+            Statement::Assume { name, assumptions } => {
+                write!(f, "assume {}", pretty_name(name))?;
+                assumptions.iter().try_for_each(|a| write!(f, " {a}"))
+            }
+            Statement::Optimize {
+                goal,
+                objective,
+                constraints,
+            } => {
+                write!(f, "{goal} {objective}")?;
+                let constraints: Vec<String> = constraints.iter().map(|c| c.to_string()).collect();
+                match constraints.is_empty() {
+                    true => Ok(()),
+                    false => write!(f, " subject to {}", constraints.join(", ")),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer;
+    use crate::parser::parse_expression;
+
+    fn reprint(input: &str) -> String {
+        let tokens = lexer::tokenize(input.to_string()).unwrap();
+        let expr = parse_expression(&mut tokens.into_iter().peekable()).unwrap();
+        expr.to_string()
+    }
+
+    #[test]
+    fn prints_minimal_brackets() {
+        assert_eq!(reprint("(1 + 2) * 3"), "(1 + 2)*3");
+        assert_eq!(reprint("1 - (2 - 3)"), "1 - (2 - 3)");
+        assert_eq!(reprint("(1 - 2) - 3"), "1 - 2 - 3");
+        assert_eq!(reprint("-(x^2)"), "-x^2");
+        assert_eq!(reprint("(-x)^2"), "(-x)^2");
+        assert_eq!(reprint("2^(3^2)"), "2^3^2");
+        assert_eq!(reprint("(2^3)^2"), "(2^3)^2");
+        assert_eq!(reprint("(x % 3) * 2"), "x%3*2");
+        assert_eq!(reprint("x % (3 * 2)"), "x%(3*2)");
+    }
+
+    #[test]
+    fn prints_implicit_products() {
+        assert_eq!(reprint("3 * x^2 + 3 * x + 1"), "3x^2 + 3x + 1");
+        assert_eq!(reprint("(x - 2) * (x - 3)"), "(x - 2)(x - 3)");
+        assert_eq!(reprint("x * (x + 1)"), "x*(x + 1)");
+        assert_eq!(reprint("2 * x * y"), "2x*y");
+        assert_eq!(reprint("2 * (3)"), "2*3");
+        assert_eq!(reprint("-2 * (x + 1)"), "-2(x + 1)");
+        assert_eq!(reprint("2 * alpha"), "2α");
+        assert_eq!(reprint("2 * e5 + 0 * x"), "2*e5 + 0*x");
+        assert_eq!(reprint("5 * s + 2 m"), "5s + 2 m");
+        assert_eq!(reprint("3 * deg + 2 * degs"), "3*deg + 2degs");
+    }
+
+    #[test]
+    fn prints_subscripts() {
+        assert_eq!(reprint("x_1 + x₂"), "x₁ + x₂");
+        assert_eq!(reprint("theta_max"), "θ_max");
+    }
+
+    #[test]
+    fn printed_expressions_parse_back() {
+        for input in [
+            "3x^2 - 2(x + 1)",
+            "|x - 1|/2",
+            "[1, 2; 3, 4] .* A",
+            "solve(A, [1, 2])",
+            "!x + +y",
+            "(n - 1)! * 5% + 30°",
+            "3 * deg - 30 deg",
+        ] {
+            assert_eq!(reprint(&reprint(input)), reprint(input));
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::lexer::{Token, TokenKind};
+use crate::parser::expr::{continue_expression, parse_bound, parse_call_args};
 use crate::parser::{Expression, parse_expression};
-use crate::parser::{expect_kind, parsing_error};
+use crate::parser::{expect_kind, found};
 use std::iter::Peekable;
 
 #[derive(PartialEq, Debug)]
@@ -15,60 +16,263 @@ pub enum Statement {
         expr: Expression,
         value: Expression,
     },
+
+    // 3x + 2 ≡ 5 (mod 11), also written with '='
+    Congruence {
+        lhs: Expression,
+        rhs: Expression,
+        modulus: Expression,
+    },
+
+    Expr(Expression),
+
+    Interval {
+        name: String,
+        lo: Expression,
+        hi: Expression,
+    },
+
+    // assume x > 0; assume n in Z; assume k >= 1 in Z
+    Assume {
+        name: String,
+        assumptions: Vec<Assumption>,
+    },
+
+    // maximize 3x + 2y subject to x + y <= 4, x >= 0
+    Optimize {
+        goal: Goal,
+        objective: Expression,
+        constraints: Vec<Constraint>,
+    },
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Goal {
+    Maximize,
+    Minimize,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Constraint {
+    pub lhs: Expression,
+    pub comparison: Comparison,
+    pub rhs: Expression,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Comparison {
+    AtMost,
+    AtLeast,
+    Equal,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Assumption {
+    Greater(Expression),
+    AtLeast(Expression),
+    Less(Expression),
+    AtMost(Expression),
+    In(NumberSet),
+}
+
+impl Assumption {
+    pub fn bound(&self) -> Option<&Expression> {
+        match self {
+            Assumption::Greater(bound)
+            | Assumption::AtLeast(bound)
+            | Assumption::Less(bound)
+            | Assumption::AtMost(bound) => Some(bound),
+            Assumption::In(_) => None,
+        }
+    }
 }
 
-pub fn parse_let_statement(tokens: &mut Peekable<impl Iterator<Item = Token>>) -> Statement {
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum NumberSet {
+    Integers,
+    Naturals,
+    Reals,
+}
+
+pub fn parse_let_statement(
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Result<Statement, String> {
     // let f -> 6x + 3y = 82742; From = its optional
     tokens.next(); //consume 'let', goes to ident 
 
-    let mut name: String = Default::default();
-    let mut expr = Expression::Number(0.0);
-    let mut value: Option<Expression> = None;
+    let name = match expect_kind(TokenKind::Ident, tokens)? {
+        Token::Ident(s) => s,
+        _ => unreachable!("expect_kind checked for an identifier"),
+    };
 
-    match expect_kind(TokenKind::Ident, tokens) {
-        Ok(token) => {
-            if let Token::Ident(s) = token {
-                name = s;
-            }
-        }
-        Err(error) => parsing_error(error, tokens),
+    // let x in [1, 2]; binds x to an interval
+    if tokens.next_if_eq(&Token::In).is_some() {
+        return parse_interval(name, tokens);
     }
 
-    if let Err(error) = expect_kind(TokenKind::Arrow, tokens) {
-        parsing_error(error, tokens);
+    expect_kind(TokenKind::Arrow, tokens)?;
+    let expr = parse_expression(tokens)?;
+
+    let value = match expect_kind(TokenKind::Assign, tokens) {
+        Ok(_) => Some(parse_expression(tokens)?),
+        Err(_) => None,
+    };
+
+    Ok(Statement::Let { name, expr, value })
+}
+
+pub fn parse_assign_statement(
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Result<Statement, String> {
+    // 6x + 3y = 8; Without '=' it is a plain expression
+    let expr = parse_expression(tokens)?;
+
+    let congruent = tokens.next_if_eq(&Token::Congruent).is_some();
+    if !congruent && expect_kind(TokenKind::Assign, tokens).is_err() {
+        return Ok(Statement::Expr(expr));
+    }
+
+    let value = parse_expression(tokens)?;
+    if tokens.next_if_eq(&Token::Modulo).is_none() {
+        return match congruent {
+            true => Err(format!("expected (mod m) after {expr} ≡ {value}")),
+            false => Ok(Statement::Assign { expr, value }),
+        };
     }
 
-    match parse_expression(tokens) {
-        Ok(e) => expr = e,
-        Err(error) => parsing_error(error, tokens),
+    let modulus = parse_expression(tokens)?;
+    if tokens.next_if_eq(&Token::RParen).is_none() {
+        return Err(format!("expected ')' to close (mod {modulus}"));
     }
+    Ok(Statement::Congruence {
+        lhs: expr,
+        rhs: value,
+        modulus,
+    })
+}
+
+pub fn parse_assume_statement(
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Result<Statement, String> {
+    tokens.next(); // consume 'assume'
 
-    if expect_kind(TokenKind::Assign, tokens).is_ok() {
-        match parse_expression(tokens) {
-            Ok(e) => value = Some(e),
-            Err(error) => parsing_error(error, tokens),
+    let name = match expect_kind(TokenKind::Ident, tokens)? {
+        Token::Ident(s) => s,
+        _ => unreachable!("expect_kind checked for an identifier"),
+    };
+
+    let mut assumptions = vec![match tokens.next() {
+        Some(Token::Greater) => Assumption::Greater(parse_bound(tokens)?),
+        Some(Token::GreaterEq) => Assumption::AtLeast(parse_bound(tokens)?),
+        Some(Token::Less) => Assumption::Less(parse_bound(tokens)?),
+        Some(Token::LessEq) => Assumption::AtMost(parse_bound(tokens)?),
+        Some(Token::In) => parse_number_set(tokens)?,
+        other => {
+            return Err(format!(
+                "expected a comparison such as > 0 or a set such as in Z, got {}",
+                found(other.as_ref())
+            ));
         }
+    }];
+    // A comparison can be followed by the set, as in assume k >= 1 in Z
+    if !matches!(assumptions[0], Assumption::In(_)) && tokens.next_if_eq(&Token::In).is_some() {
+        assumptions.push(parse_number_set(tokens)?);
     }
 
-    Statement::Let { name, expr, value }
+    Ok(Statement::Assume { name, assumptions })
 }
 
-pub fn parse_assign_statement(tokens: &mut Peekable<impl Iterator<Item = Token>>) -> Statement {
-    // 6x + 3y = 8;
-    let mut expr = Expression::Number(0.0);
-    let mut value = Expression::Number(0.0);
-
-    match parse_expression(tokens) {
-        Ok(e) => expr = e,
-        Err(error) => parsing_error(error, tokens),
+// The set after an 'in'. N reads as newtons there, so the set names come as units too
+fn parse_number_set(
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Result<Assumption, String> {
+    match tokens.next() {
+        Some(Token::Ident(set) | Token::Unit(set)) => match set.as_str() {
+            "Z" | "ℤ" => Ok(Assumption::In(NumberSet::Integers)),
+            "N" | "ℕ" => Ok(Assumption::In(NumberSet::Naturals)),
+            "R" | "ℝ" => Ok(Assumption::In(NumberSet::Reals)),
+            _ => Err(format!("expected Z, N or R after 'in', got {set}")),
+        },
+        other => Err(format!(
+            "expected Z, N or R after 'in', got {}",
+            found(other.as_ref())
+        )),
     }
+}
+
+pub fn parse_optimize_statement(
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Result<Statement, String> {
+    let goal = match tokens.next() {
+        Some(Token::Maximize) => Goal::Maximize,
+        _ => Goal::Minimize,
+    };
+
+    // minimize(f, x), with the names to vary, is the numeric minimiser rather than a statement.
+    // A single bracketed expression only starts the objective, as in minimize (x - 1)^2
+    let objective = match tokens.next_if_eq(&Token::LParen) {
+        Some(_) => match parse_call_args(tokens)? {
+            mut args if args.len() == 1 => continue_expression(args.pop().unwrap(), tokens)?,
+            args => {
+                return Ok(Statement::Expr(Expression::Call {
+                    name: goal.to_string(),
+                    args,
+                }));
+            }
+        },
+        None => parse_expression(tokens)?,
+    };
 
-    if expect_kind(TokenKind::Assign, tokens).is_ok() {
-        match parse_expression(tokens) {
-            Ok(e) => value = e,
-            Err(error) => parsing_error(error, tokens),
+    let mut constraints = Vec::new();
+    if tokens.next_if_eq(&Token::SubjectTo).is_some() {
+        loop {
+            let lhs = parse_expression(tokens)?;
+            let comparison = match tokens.next() {
+                Some(Token::LessEq) => Comparison::AtMost,
+                Some(Token::GreaterEq) => Comparison::AtLeast,
+                Some(Token::Assign) => Comparison::Equal,
+                Some(Token::Less | Token::Greater) => {
+                    return Err(String::from(
+                        "constraints cannot be strict, use <= or >= instead",
+                    ));
+                }
+                other => {
+                    return Err(format!(
+                        "expected <=, >= or = after {lhs}, got {}",
+                        found(other.as_ref())
+                    ));
+                }
+            };
+            let rhs = parse_expression(tokens)?;
+            constraints.push(Constraint {
+                lhs,
+                comparison,
+                rhs,
+            });
+
+            if tokens.next_if_eq(&Token::Comma).is_none() {
+                break;
+            }
         }
     }
 
-    Statement::Assign { expr, value }
+    Ok(Statement::Optimize {
+        goal,
+        objective,
+        constraints,
+    })
+}
+
+fn parse_interval(
+    name: String,
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+) -> Result<Statement, String> {
+    match parse_expression(tokens)? {
+        Expression::Vector(mut bounds) if bounds.len() == 2 => {
+            let hi = bounds.pop().unwrap();
+            let lo = bounds.pop().unwrap();
+            Ok(Statement::Interval { name, lo, hi })
+        }
+        other => Err(format!("expected an interval [lo, hi], got {other}")),
+    }
 }
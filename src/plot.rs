@@ -0,0 +1,509 @@
+use crate::evaluator::{Environment, Interval, Value, eval_in};
+use crate::parser::Expression;
+use crate::solver::interval::find_roots;
+use std::fmt::Write;
+
+const SAMPLES: usize = 400;
+const ASCII_WIDTH: usize = 64;
+const ASCII_HEIGHT: usize = 20;
+const ASCII_MARKERS: [char; 4] = ['*', 'o', '#', '%'];
+const SVG_WIDTH: f32 = 640.0;
+const SVG_HEIGHT: f32 = 400.0;
+const SVG_MARGIN: f32 = 50.0;
+const SVG_COLORS: [&str; 4] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd"];
+
+// A function sampled over the x range, split wherever it is undefined or jumps
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    pub label: String,
+    pub segments: Vec<Vec<(f32, f32)>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plot {
+    pub x_range: (f32, f32),
+    pub y_range: (f32, f32),
+    pub curves: Vec<Curve>,
+    // Roots of each curve and points where two curves cross
+    pub marks: Vec<(f32, f32)>,
+}
+
+impl Plot {
+    pub fn new(
+        exprs: &[Expression],
+        name: &str,
+        lo: f32,
+        hi: f32,
+        env: &Environment,
+    ) -> Result<Self, String> {
+        if !(lo.is_finite() && hi.is_finite()) || lo >= hi {
+            return Err(format!("cannot plot {name} from {lo} to {hi}"));
+        }
+
+        let functions: Vec<_> = exprs.iter().map(|e| function(e, name, env)).collect();
+        let xs: Vec<f32> = (0..SAMPLES)
+            .map(|i| lo + (hi - lo) * i as f32 / (SAMPLES - 1) as f32)
+            .collect();
+
+        let mut samples = Vec::new();
+        for f in &functions {
+            let ys = xs.iter().map(|&x| f(x)).collect::<Result<Vec<_>, _>>()?;
+            samples.push(ys);
+        }
+
+        let y_range = y_range(samples.iter().flatten().flatten().copied());
+        let curves = exprs
+            .iter()
+            .zip(&functions)
+            .zip(&samples)
+            .map(|((expr, f), ys)| Curve {
+                label: expr.to_string(),
+                segments: segments(&xs, ys, f, y_range),
+            })
+            .collect();
+
+        let mut plot = Plot {
+            x_range: (lo, hi),
+            y_range,
+            curves,
+            marks: Vec::new(),
+        };
+        plot.mark_crossings(exprs, name, env, &functions)?;
+        Ok(plot)
+    }
+
+    // Only verified roots are marked, so poles such as the one of 1/x never are
+    fn mark_crossings(
+        &mut self,
+        exprs: &[Expression],
+        name: &str,
+        env: &Environment,
+        functions: &[impl Fn(f32) -> Result<Option<f32>, String>],
+    ) -> Result<(), String> {
+        let domain = Interval::new(self.x_range.0, self.x_range.1)?;
+        let zero = Expression::Number(0.0);
+
+        for (i, lhs) in exprs.iter().enumerate() {
+            let others = exprs.iter().skip(i + 1);
+            for rhs in std::iter::once(&zero).chain(others) {
+                // Expressions the interval evaluator cannot bound simply go unmarked
+                let Ok(roots) = find_roots(lhs, rhs, name, domain, env) else {
+                    continue;
+                };
+                for root in roots.iter().filter(|root| root.verified) {
+                    let x = root.enclosure.midpoint();
+                    if let Some(y) = functions[i](x)? {
+                        self.marks.push((x, y));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn ascii(&self) -> String {
+        let (w, h) = (ASCII_WIDTH, ASCII_HEIGHT);
+        let mut grid = vec![vec![' '; w]; h];
+        let col =
+            |x: f32| (x - self.x_range.0) / (self.x_range.1 - self.x_range.0) * (w - 1) as f32;
+        let row =
+            |y: f32| (self.y_range.1 - y) / (self.y_range.1 - self.y_range.0) * (h - 1) as f32;
+
+        let (axis_row, axis_col) = (row(0.0).round(), col(0.0).round());
+        if (0.0..h as f32).contains(&axis_row) {
+            grid[axis_row as usize].fill('-');
+        }
+        if (0.0..w as f32).contains(&axis_col) {
+            for line in grid.iter_mut() {
+                line[axis_col as usize] = match line[axis_col as usize] {
+                    '-' => '+',
+                    _ => '|',
+                };
+            }
+        }
+
+        for (curve, &marker) in self.curves.iter().zip(ASCII_MARKERS.iter().cycle()) {
+            for segment in &curve.segments {
+                // Fill the rows between neighbouring samples so steep parts stay connected
+                for pair in segment.windows(2) {
+                    let ((_, y0), (x1, y1)) = (pair[0], pair[1]);
+                    let c = col(x1).round() as usize;
+                    let (r0, r1) = (row(y0).round(), row(y1).round());
+                    let (top, bottom) = (r0.min(r1).max(0.0), r0.max(r1).min((h - 1) as f32));
+                    if top <= bottom {
+                        for line in &mut grid[top as usize..=bottom as usize] {
+                            line[c] = marker;
+                        }
+                    }
+                }
+                if let [(x, y)] = segment.as_slice() {
+                    let r = row(*y).round();
+                    if (0.0..h as f32).contains(&r) {
+                        grid[r as usize][col(*x).round() as usize] = marker;
+                    }
+                }
+            }
+        }
+
+        for &(x, y) in &self.marks {
+            let r = row(y).round();
+            if (0.0..h as f32).contains(&r) {
+                grid[r as usize][col(x).round() as usize] = '@';
+            }
+        }
+
+        let labels = [number(self.y_range.1), number(self.y_range.0)];
+        let margin = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+
+        let mut out = String::new();
+        for (i, line) in grid.iter().enumerate() {
+            let label = match i {
+                0 => &labels[0],
+                i if i == h - 1 => &labels[1],
+                _ => "",
+            };
+            let line: String = line.iter().collect();
+            writeln!(out, "{label:>margin$} |{}", line.trim_end()).unwrap();
+        }
+
+        let (lo, hi) = (number(self.x_range.0), number(self.x_range.1));
+        let gap = (w + 1).saturating_sub(lo.len() + hi.len());
+        writeln!(out, "{:margin$}  {lo}{:gap$}{hi}", "", "").unwrap();
+
+        let legend: Vec<String> = self
+            .curves
+            .iter()
+            .zip(ASCII_MARKERS.iter().cycle())
+            .map(|(curve, marker)| format!("{marker} {}", curve.label))
+            .collect();
+        write!(out, "{}", legend.join("   ")).unwrap();
+        if !self.marks.is_empty() {
+            write!(out, "   @ roots and intersections").unwrap();
+        }
+        out
+    }
+
+    // A standalone SVG document with gridlines, axes and a legend
+    pub fn svg(&self) -> String {
+        let (x0, x1) = self.x_range;
+        let (y0, y1) = self.y_range;
+        let (left, top) = (SVG_MARGIN, SVG_MARGIN / 2.0);
+        let (width, height) = (SVG_WIDTH - 1.5 * SVG_MARGIN, SVG_HEIGHT - 1.5 * SVG_MARGIN);
+        let px = |x: f32| left + (x - x0) / (x1 - x0) * width;
+        let py = |y: f32| top + (y1 - y) / (y1 - y0) * height;
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SVG_WIDTH}" height="{SVG_HEIGHT}" viewBox="0 0 {SVG_WIDTH} {SVG_HEIGHT}" font-family="sans-serif" font-size="11">"#
+        )
+        .unwrap();
+        writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+        writeln!(
+            out,
+            r#"<clipPath id="area"><rect x="{left}" y="{top}" width="{width}" height="{height}"/></clipPath>"#
+        )
+        .unwrap();
+
+        for x in ticks(x0, x1) {
+            let p = px(x);
+            writeln!(
+                out,
+                r##"<line x1="{p:.1}" y1="{top}" x2="{p:.1}" y2="{:.1}" stroke="#ddd"/>"##,
+                top + height
+            )
+            .unwrap();
+            writeln!(
+                out,
+                r#"<text x="{p:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                top + height + 15.0,
+                number(x)
+            )
+            .unwrap();
+        }
+        for y in ticks(y0, y1) {
+            let p = py(y);
+            writeln!(
+                out,
+                r##"<line x1="{left}" y1="{p:.1}" x2="{:.1}" y2="{p:.1}" stroke="#ddd"/>"##,
+                left + width
+            )
+            .unwrap();
+            writeln!(
+                out,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"#,
+                left - 5.0,
+                p + 4.0,
+                number(y)
+            )
+            .unwrap();
+        }
+
+        if x0 <= 0.0 && 0.0 <= x1 {
+            let p = px(0.0);
+            writeln!(
+                out,
+                r#"<line x1="{p:.1}" y1="{top}" x2="{p:.1}" y2="{:.1}" stroke="black"/>"#,
+                top + height
+            )
+            .unwrap();
+        }
+        if y0 <= 0.0 && 0.0 <= y1 {
+            let p = py(0.0);
+            writeln!(
+                out,
+                r#"<line x1="{left}" y1="{p:.1}" x2="{:.1}" y2="{p:.1}" stroke="black"/>"#,
+                left + width
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            r#"<rect x="{left}" y="{top}" width="{width}" height="{height}" fill="none" stroke="black"/>"#
+        )
+        .unwrap();
+
+        writeln!(
+            out,
+            r#"<g clip-path="url(#area)" fill="none" stroke-width="1.5">"#
+        )
+        .unwrap();
+        for (curve, color) in self.curves.iter().zip(SVG_COLORS.iter().cycle()) {
+            for segment in &curve.segments {
+                let points: Vec<String> = segment
+                    .iter()
+                    .map(|&(x, y)| format!("{:.2},{:.2}", px(x), py(y)))
+                    .collect();
+                writeln!(
+                    out,
+                    r#"<polyline stroke="{color}" points="{}"/>"#,
+                    points.join(" ")
+                )
+                .unwrap();
+            }
+        }
+        for &(x, y) in &self.marks {
+            writeln!(
+                out,
+                r#"<circle cx="{:.2}" cy="{:.2}" r="4" fill="black"><title>({}, {})</title></circle>"#,
+                px(x),
+                py(y),
+                x,
+                y
+            )
+            .unwrap();
+        }
+        writeln!(out, "</g>").unwrap();
+
+        for (i, (curve, color)) in self
+            .curves
+            .iter()
+            .zip(SVG_COLORS.iter().cycle())
+            .enumerate()
+        {
+            let y = top + 15.0 + 15.0 * i as f32;
+            writeln!(
+                out,
+                r#"<line x1="{:.1}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}" stroke="{color}" stroke-width="2"/>"#,
+                left + 10.0,
+                left + 30.0
+            )
+            .unwrap();
+            writeln!(
+                out,
+                r#"<text x="{:.1}" y="{:.1}">{}</text>"#,
+                left + 35.0,
+                y + 4.0,
+                escape(&curve.label)
+            )
+            .unwrap();
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+// The expression as a function of name, None where it is undefined
+fn function<'a>(
+    expr: &'a Expression,
+    name: &'a str,
+    env: &'a Environment,
+) -> impl Fn(f32) -> Result<Option<f32>, String> + 'a {
+    move |x| {
+        // Poles and gaps come back as inf and NaN and are left out of the curve
+        let mut env = env.clone();
+        env.set_ieee(true);
+        env.set(name.to_string(), Value::Number(x));
+        let y = eval_in(expr.clone(), &env)?.scalar()?;
+        Ok(y.is_finite().then_some(y))
+    }
+}
+
+// The range of the samples, leaving out the extreme values near poles
+fn y_range(ys: impl Iterator<Item = f32>) -> (f32, f32) {
+    let mut ys: Vec<f32> = ys.collect();
+    if ys.is_empty() {
+        return (-1.0, 1.0);
+    }
+    ys.sort_by(f32::total_cmp);
+
+    let (min, max) = (ys[0], ys[ys.len() - 1]);
+    let (p2, p98) = (ys[ys.len() / 50], ys[ys.len() - 1 - ys.len() / 50]);
+    let (lo, hi) = if max - min > 10.0 * (p98 - p2) && p98 > p2 {
+        (p2, p98)
+    } else {
+        (min, max)
+    };
+
+    if hi - lo <= f32::EPSILON * lo.abs().max(1.0) {
+        return (lo - 1.0, hi + 1.0);
+    }
+    let pad = (hi - lo) * 0.05;
+    (lo - pad, hi + pad)
+}
+
+fn segments(
+    xs: &[f32],
+    ys: &[Option<f32>],
+    f: impl Fn(f32) -> Result<Option<f32>, String>,
+    (lo, hi): (f32, f32),
+) -> Vec<Vec<(f32, f32)>> {
+    let mut segments: Vec<Vec<(f32, f32)>> = Vec::new();
+    let mut current: Vec<(f32, f32)> = Vec::new();
+
+    for (&x, &y) in xs.iter().zip(ys) {
+        let Some(y) = y else {
+            segments.push(std::mem::take(&mut current));
+            continue;
+        };
+
+        if let Some(&(px, py)) = current.last()
+            && jumps(&f, (px, py), (x, y), hi - lo)
+        {
+            segments.push(std::mem::take(&mut current));
+        }
+        current.push((x, y));
+    }
+
+    segments.push(current);
+    segments.retain(|segment| !segment.is_empty());
+    segments
+}
+
+// A large step whose midpoint does not lie between its ends is a discontinuity, like the
+// pole of 1/x, rather than a steep part of the curve
+fn jumps(
+    f: impl Fn(f32) -> Result<Option<f32>, String>,
+    (x0, y0): (f32, f32),
+    (x1, y1): (f32, f32),
+    span: f32,
+) -> bool {
+    if (y1 - y0).abs() < span / 2.0 {
+        return false;
+    }
+    match f((x0 + x1) / 2.0) {
+        Ok(Some(mid)) => mid < y0.min(y1) || mid > y0.max(y1),
+        _ => true,
+    }
+}
+
+// Gridline positions at a round step, about eight across the range
+fn ticks(lo: f32, hi: f32) -> Vec<f32> {
+    let raw = (hi - lo) / 8.0;
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|&s| s >= raw)
+        .unwrap_or(10.0 * magnitude);
+
+    let first = (lo / step).ceil() as i64;
+    let last = (hi / step).floor() as i64;
+    (first..=last).map(|i| i as f32 * step).collect()
+}
+
+// Short labels, 0.30000001 prints as 0.3
+fn number(x: f32) -> String {
+    let text = format!("{:.4}", x);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => String::from("0"),
+        text => text.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+    use crate::parser::parse_expression;
+
+    fn plot(inputs: &[&str], lo: f32, hi: f32) -> Plot {
+        let exprs: Vec<Expression> = inputs
+            .iter()
+            .map(|input| {
+                let tokens = lexer::tokenize(input.to_string()).unwrap();
+                parse_expression(&mut tokens.into_iter().peekable()).unwrap()
+            })
+            .collect();
+        Plot::new(&exprs, "x", lo, hi, &Environment::default()).unwrap()
+    }
+
+    #[test]
+    fn splits_curves_at_poles() {
+        let p = plot(&["1/x"], -1.0, 1.0);
+
+        assert_eq!(p.curves[0].segments.len(), 2);
+        assert!(p.marks.is_empty());
+    }
+
+    #[test]
+    fn keeps_continuous_curves_whole() {
+        let p = plot(&["x^3"], -10.0, 10.0);
+
+        assert_eq!(p.curves[0].segments.len(), 1);
+        assert_eq!(p.curves[0].segments[0].len(), SAMPLES);
+    }
+
+    #[test]
+    fn skips_undefined_samples() {
+        let p = plot(&["x^0.5"], -1.0, 1.0);
+
+        assert_eq!(p.curves[0].segments.len(), 1);
+        assert!(p.curves[0].segments[0].iter().all(|&(x, _)| x >= 0.0));
+    }
+
+    #[test]
+    fn marks_roots_and_intersections() {
+        let p = plot(&["x^2 - 4", "x"], -5.0, 5.0);
+        let near = |x: f32, y: f32| {
+            p.marks
+                .iter()
+                .any(|m| (m.0 - x).abs() < 1e-3 && (m.1 - y).abs() < 1e-2)
+        };
+
+        assert!(near(2.0, 0.0) && near(-2.0, 0.0) && near(0.0, 0.0));
+        // x^2 - 4 = x at (1 ± √17) / 2
+        assert!(near(2.561553, 2.561553) && near(-1.561553, -1.561553));
+    }
+
+    #[test]
+    fn renders_ascii_and_svg() {
+        let p = plot(&["x"], -1.0, 1.0);
+
+        let ascii = p.ascii();
+        assert_eq!(ascii.lines().count(), ASCII_HEIGHT + 2);
+        assert!(ascii.ends_with("* x   @ roots and intersections"));
+
+        let svg = p.svg();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<polyline").count(), 1);
+    }
+}
@@ -0,0 +1,384 @@
+use crate::parser::{Expression, Operator};
+use crate::polynomial::rational;
+use crate::polynomial::{Monomial, Polynomial, Rational, content_and_primitive, gcd, negated};
+use std::fmt;
+
+// Kronecker's method tries every combination of divisors, give up beyond this many
+const MAX_CANDIDATES: usize = 200_000;
+// Larger constant terms make listing their divisors too slow
+const MAX_DIVISOR_SEARCH: i128 = 1_000_000_000_000;
+
+// constant * f1^e1 * f2^e2 * ..., each factor with coprime integer coefficients
+#[derive(Debug, Clone, PartialEq)]
+pub struct Factorization {
+    pub constant: Rational,
+    pub factors: Vec<(Polynomial, u32)>,
+}
+
+impl Factorization {
+    pub fn expand(&self) -> Polynomial {
+        self.factors.iter().fold(
+            Polynomial::constant(self.constant.clone()),
+            |acc, (f, e)| acc * f.pow(*e),
+        )
+    }
+
+    pub fn to_expression(&self) -> Expression {
+        let mut factors: Vec<Expression> = self
+            .factors
+            .iter()
+            .map(|(f, e)| match e {
+                1 => f.to_expression(),
+                e => Expression::Binary {
+                    lhs: Box::new(f.to_expression()),
+                    op: Operator::Pow,
+                    rhs: Box::new(Expression::Number(*e as f32)),
+                },
+            })
+            .collect();
+
+        let constant = Polynomial::constant(self.constant.abs()).to_expression();
+        if self.constant.abs() != Rational::one() || factors.is_empty() {
+            factors.insert(0, constant);
+        }
+
+        let product = factors
+            .into_iter()
+            .reduce(|lhs, rhs| Expression::Binary {
+                lhs: Box::new(lhs),
+                op: Operator::Mult,
+                rhs: Box::new(rhs),
+            })
+            .unwrap();
+
+        if self.constant.signum() < 0 {
+            negated(product)
+        } else {
+            product
+        }
+    }
+}
+
+impl fmt::Display for Factorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_expression())
+    }
+}
+
+// Square-free decomposition: p = c * a1 * a2^2 * a3^3 * ... with the ai pairwise coprime
+// and without repeated factors. Uses Yun's algorithm one variable at a time.
+pub fn square_free(p: &Polynomial) -> Vec<(Polynomial, u32)> {
+    let p = p.normalized();
+    let Some(x) = p.variables().into_iter().next() else {
+        return Vec::new();
+    };
+
+    let (content, primitive) = content_and_primitive(&p, &x);
+    let mut result = square_free(&content);
+
+    let derivative = primitive.derivative(&x);
+    let g = gcd(&primitive, &derivative);
+    let mut c = primitive.div_exact(&g);
+    let mut d = derivative.div_exact(&g) - c.derivative(&x);
+    let mut multiplicity = 1;
+
+    while !c.is_constant() {
+        let a = gcd(&c, &d);
+        if !a.is_constant() {
+            result.push((a.clone(), multiplicity));
+        }
+        c = c.div_exact(&a);
+        d = d.div_exact(&a) - c.derivative(&x);
+        multiplicity += 1;
+    }
+
+    result
+}
+
+// Factorisation over the rationals. Polynomials in one variable are split into irreducible
+// factors; in several variables, homogeneous ones are reduced to fewer variables and
+// anything else is only split by content and repeated factors.
+pub fn factor(p: &Polynomial) -> Factorization {
+    if p.is_zero() {
+        return Factorization {
+            constant: Rational::zero(),
+            factors: Vec::new(),
+        };
+    }
+
+    let constant = p.integer_content();
+    let mut rest = p.normalized();
+    let mut factors = Vec::new();
+
+    // Variables dividing every term
+    for x in rest.variables() {
+        let e = rest.terms().map(|(m, _)| m.exponent(&x)).min().unwrap_or(0);
+        if e > 0 {
+            let monomial = Polynomial::term(Monomial::var(&x, e), Rational::one());
+            rest = rest.div_exact(&monomial);
+            factors.push((Polynomial::var(&x), e));
+        }
+    }
+
+    for (f, e) in square_free(&rest) {
+        for g in factor_square_free(&f) {
+            factors.push((g, e));
+        }
+    }
+
+    factors.sort_by_key(|(f, e)| (f.total_degree(), f.terms().count(), f.to_string(), *e));
+
+    // The same factor can show up once per content split, collect its powers
+    let mut merged: Vec<(Polynomial, u32)> = Vec::new();
+    for (f, e) in factors {
+        match merged.last_mut() {
+            Some((last, total)) if *last == f => *total += e,
+            _ => merged.push((f, e)),
+        }
+    }
+
+    Factorization {
+        constant,
+        factors: merged,
+    }
+}
+
+fn factor_square_free(f: &Polynomial) -> Vec<Polynomial> {
+    let vars: Vec<String> = f.variables().into_iter().collect();
+
+    match vars.as_slice() {
+        [] => Vec::new(),
+        [x] => factor_univariate(f, x),
+
+        // f(x, y) = y^d * f(x/y, 1), so factors of f(x, 1) give factors of f
+        [.., y] if f.is_homogeneous() => {
+            let dehomogenized = f.substitute(y, &Polynomial::one());
+            let parts = factor(&dehomogenized);
+            parts
+                .factors
+                .iter()
+                .flat_map(|(g, e)| std::iter::repeat_n(homogenize(g, y), *e as usize))
+                .collect()
+        }
+
+        _ => vec![f.normalized()],
+    }
+}
+
+fn homogenize(g: &Polynomial, y: &str) -> Polynomial {
+    let degree = g.total_degree();
+    let mut result = Polynomial::zero();
+    for (m, c) in g.terms() {
+        let y_power = Polynomial::term(Monomial::var(y, degree - m.degree()), Rational::one());
+        result = result + Polynomial::term(m.clone(), c.clone()) * y_power;
+    }
+    result.normalized()
+}
+
+// f is square-free with integer coefficients
+fn factor_univariate(f: &Polynomial, x: &str) -> Vec<Polynomial> {
+    let mut factors = Vec::new();
+    let mut rest = f.normalized();
+
+    // Rational roots p/q have p dividing the constant term and q the leading coefficient
+    let lead = rest.coefficient_in(x, rest.degree_in(x));
+    let constant = rest.coefficient_in(x, 0);
+    let (lead, constant) = (
+        lead.constant_value().unwrap().numer().to_i128(),
+        constant.constant_value().unwrap().numer().to_i128(),
+    );
+
+    if let (Some(ps), Some(qs)) = (constant.and_then(divisors), lead.and_then(divisors)) {
+        for &p in &ps {
+            for &q in &qs {
+                for p in [p, -p] {
+                    if rest.degree_in(x) <= 1 || rational::gcd(p, q) != 1 {
+                        continue;
+                    }
+                    // q x - p
+                    let linear = Polynomial::var(x).scale(Rational::integer(q))
+                        - Polynomial::constant(Rational::integer(p));
+                    let (quotient, remainder) = rest.div_rem(&linear).unwrap();
+                    if remainder.is_zero() {
+                        factors.push(linear);
+                        rest = quotient.normalized();
+                    }
+                }
+            }
+        }
+    }
+
+    // Whatever is left has no linear factors, so degree 2 and 3 are irreducible
+    let mut pending = vec![rest];
+    while let Some(g) = pending.pop() {
+        match kronecker_split(&g, x) {
+            Some((a, b)) => {
+                pending.push(a);
+                pending.push(b);
+            }
+            None => factors.push(g),
+        }
+    }
+
+    factors.retain(|f| !f.is_constant());
+    factors.iter().map(|f| f.normalized()).collect()
+}
+
+// Kronecker's method: a factor of degree d is fixed by its values at d + 1 points, and each of
+// those values divides the value of g there. Tries every such combination.
+fn kronecker_split(g: &Polynomial, x: &str) -> Option<(Polynomial, Polynomial)> {
+    let degree = g.degree_in(x);
+
+    for d in 2..=degree / 2 {
+        let mut points = Vec::new();
+        let mut candidates = Vec::new();
+        let mut t: i128 = 0;
+
+        while points.len() <= d as usize {
+            let value = evaluate(g, x, t)?;
+            if value != 0 {
+                let divs = divisors(value)?;
+                candidates.push(divs.iter().flat_map(|&v| [v, -v]).collect::<Vec<i128>>());
+                points.push(t);
+            }
+            // 0, 1, -1, 2, -2, ...
+            t = if t > 0 { -t } else { -t + 1 };
+        }
+
+        let total: usize = candidates.iter().map(|c| c.len()).product();
+        if total > MAX_CANDIDATES {
+            return None;
+        }
+
+        let mut choice = vec![0; candidates.len()];
+        loop {
+            let values: Vec<i128> = choice.iter().zip(&candidates).map(|(&i, c)| c[i]).collect();
+            let h = interpolate(&points, &values, x);
+
+            if h.degree_in(x) == d
+                && h.leading_coefficient().signum() > 0
+                && h.terms().all(|(_, c)| c.is_integer())
+            {
+                let (quotient, remainder) = g.div_rem(&h).unwrap();
+                if remainder.is_zero() {
+                    return Some((h, quotient.normalized()));
+                }
+            }
+
+            // Next combination, like counting in mixed radix
+            let mut i = 0;
+            loop {
+                if i == choice.len() {
+                    break;
+                }
+                choice[i] += 1;
+                if choice[i] < candidates[i].len() {
+                    break;
+                }
+                choice[i] = 0;
+                i += 1;
+            }
+            if i == choice.len() {
+                break;
+            }
+        }
+    }
+
+    None
+}
+
+fn evaluate(g: &Polynomial, x: &str, t: i128) -> Option<i128> {
+    let value = g
+        .substitute(x, &Polynomial::constant(Rational::integer(t)))
+        .constant_value()?;
+    value.numer().to_i128().filter(|_| value.is_integer())
+}
+
+// Lagrange interpolation through (points[i], values[i])
+fn interpolate(points: &[i128], values: &[i128], x: &str) -> Polynomial {
+    let mut result = Polynomial::zero();
+    for (i, (&xi, &yi)) in points.iter().zip(values).enumerate() {
+        let mut basis = Polynomial::constant(Rational::integer(yi));
+        for (j, &xj) in points.iter().enumerate() {
+            if i != j {
+                let factor = Polynomial::var(x) - Polynomial::constant(Rational::integer(xj));
+                basis = basis * factor.scale(Rational::new(1, xi - xj));
+            }
+        }
+        result = result + basis;
+    }
+    result
+}
+
+// Positive divisors of n, None when n is too large to search
+fn divisors(n: i128) -> Option<Vec<i128>> {
+    let n = n.abs();
+    if n == 0 || n > MAX_DIVISOR_SEARCH {
+        return None;
+    }
+
+    let mut small = Vec::new();
+    let mut large = Vec::new();
+    let mut d = 1;
+    while d * d <= n {
+        if n % d == 0 {
+            small.push(d);
+            if d * d != n {
+                large.push(n / d);
+            }
+        }
+        d += 1;
+    }
+    small.extend(large.into_iter().rev());
+    Some(small)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+    use crate::parser::parse_expression;
+
+    fn poly(input: &str) -> Polynomial {
+        let tokens = lexer::tokenize(input.to_string()).unwrap();
+        let expr = parse_expression(&mut tokens.into_iter().peekable()).unwrap();
+        Polynomial::from_expression(&expr).unwrap()
+    }
+
+    #[test]
+    fn factors_univariate_polynomials() {
+        assert_eq!(factor(&poly("x^2 - 5x + 6")).to_string(), "(x - 2)(x - 3)");
+        assert_eq!(
+            factor(&poly("2*x^3 - 2*x")).to_string(),
+            "2x*(x + 1)(x - 1)"
+        );
+        assert_eq!(
+            factor(&poly("x^4 + 4")).to_string(),
+            "(x^2 + 2x + 2)(x^2 - 2x + 2)"
+        );
+        assert_eq!(factor(&poly("x^2 + 1")).to_string(), "x^2 + 1");
+    }
+
+    #[test]
+    fn factors_repeated_and_multivariate_factors() {
+        assert_eq!(
+            factor(&poly("x^3 + 3*x^2 + 3*x + 1")).to_string(),
+            "(x + 1)^3"
+        );
+        assert_eq!(factor(&poly("x^2 - y^2")).to_string(), "(x + y)(x - y)");
+        assert_eq!(factor(&poly("x/2 - 1/2")).to_string(), "1/2*(x - 1)");
+    }
+
+    #[test]
+    fn square_free_decomposition() {
+        let parts = square_free(&poly("(x - 1)^2 * (x + 2)"));
+
+        assert_eq!(parts, vec![(poly("x + 2"), 1), (poly("x - 1"), 2)]);
+    }
+
+    #[test]
+    fn factorization_expands_back() {
+        let p = poly("6*x^4 + 5*x^3 - 23*x^2 - 20*x + 12");
+        assert_eq!(factor(&p).expand(), p);
+    }
+}
@@ -0,0 +1,638 @@
+pub mod factor;
+pub mod rational;
+
+pub use factor::{Factorization, factor, square_free};
+pub use rational::Rational;
+
+use crate::evaluator::{BigInt, Value};
+use crate::parser::{Expression, Operator, UnaryOperator};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+// Variable exponents of a single term, zero exponents are never stored
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+pub struct Monomial(BTreeMap<String, u32>);
+
+impl Monomial {
+    pub fn var(name: &str, exponent: u32) -> Self {
+        let mut vars = BTreeMap::new();
+        if exponent > 0 {
+            vars.insert(name.to_string(), exponent);
+        }
+        Self(vars)
+    }
+
+    pub fn degree(&self) -> u32 {
+        self.0.values().sum()
+    }
+
+    pub fn exponent(&self, var: &str) -> u32 {
+        self.0.get(var).copied().unwrap_or(0)
+    }
+
+    pub fn vars(&self) -> impl Iterator<Item = (&String, &u32)> {
+        self.0.iter()
+    }
+
+    fn mul(&self, other: &Monomial) -> Monomial {
+        let mut vars = self.0.clone();
+        for (name, e) in &other.0 {
+            *vars.entry(name.clone()).or_insert(0) += e;
+        }
+        Monomial(vars)
+    }
+
+    fn divides(&self, other: &Monomial) -> bool {
+        self.0.iter().all(|(name, &e)| other.exponent(name) >= e)
+    }
+
+    // Assumes other divides self
+    fn div(&self, other: &Monomial) -> Monomial {
+        let mut vars = self.0.clone();
+        for (name, e) in &other.0 {
+            let remaining = vars[name] - e;
+            if remaining == 0 {
+                vars.remove(name);
+            } else {
+                vars.insert(name.clone(), remaining);
+            }
+        }
+        Monomial(vars)
+    }
+
+    fn without(&self, var: &str) -> Monomial {
+        let mut vars = self.0.clone();
+        vars.remove(var);
+        Monomial(vars)
+    }
+
+    // Lexicographic order with variables ranked alphabetically, used to pick leading terms
+    fn lex_cmp(&self, other: &Monomial) -> Ordering {
+        let names: BTreeSet<&String> = self.0.keys().chain(other.0.keys()).collect();
+        names
+            .into_iter()
+            .map(|name| self.exponent(name).cmp(&other.exponent(name)))
+            .find(|ord| ord.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    // Highest total degree first, the order terms are printed in
+    fn display_cmp(&self, other: &Monomial) -> Ordering {
+        other
+            .degree()
+            .cmp(&self.degree())
+            .then_with(|| other.lex_cmp(self))
+    }
+}
+
+// Expanding a power past this degree is refused rather than left to run
+pub const MAX_DEGREE: i128 = 10_000;
+
+// A sparse multivariate polynomial with rational coefficients
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Polynomial {
+    terms: BTreeMap<Monomial, Rational>,
+}
+
+impl Polynomial {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn one() -> Self {
+        Self::constant(Rational::one())
+    }
+
+    pub fn constant(c: Rational) -> Self {
+        Self::term(Monomial::default(), c)
+    }
+
+    pub fn var(name: &str) -> Self {
+        Self::term(Monomial::var(name, 1), Rational::one())
+    }
+
+    pub fn term(monomial: Monomial, c: Rational) -> Self {
+        let mut terms = BTreeMap::new();
+        if !c.is_zero() {
+            terms.insert(monomial, c);
+        }
+        Self { terms }
+    }
+
+    pub fn terms(&self) -> impl Iterator<Item = (&Monomial, &Rational)> {
+        self.terms.iter()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn constant_value(&self) -> Option<Rational> {
+        match self.terms.len() {
+            0 => Some(Rational::zero()),
+            1 => self.terms.get(&Monomial::default()).cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn is_constant(&self) -> bool {
+        self.constant_value().is_some()
+    }
+
+    pub fn variables(&self) -> BTreeSet<String> {
+        self.terms
+            .keys()
+            .flat_map(|m| m.0.keys().cloned())
+            .collect()
+    }
+
+    pub fn degree_in(&self, var: &str) -> u32 {
+        self.terms
+            .keys()
+            .map(|m| m.exponent(var))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn total_degree(&self) -> u32 {
+        self.terms.keys().map(|m| m.degree()).max().unwrap_or(0)
+    }
+
+    pub fn is_homogeneous(&self) -> bool {
+        let degree = self.total_degree();
+        self.terms.keys().all(|m| m.degree() == degree)
+    }
+
+    pub fn leading_term(&self) -> Option<(Monomial, Rational)> {
+        self.terms
+            .iter()
+            .max_by(|a, b| a.0.lex_cmp(b.0))
+            .map(|(m, c)| (m.clone(), c.clone()))
+    }
+
+    pub fn leading_coefficient(&self) -> Rational {
+        self.leading_term().map_or(Rational::zero(), |(_, c)| c)
+    }
+
+    pub fn scale(&self, factor: Rational) -> Self {
+        if factor.is_zero() {
+            return Self::zero();
+        }
+        let terms = (self.terms.iter()).map(|(m, c)| (m.clone(), c.clone() * factor.clone()));
+        Self {
+            terms: terms.collect(),
+        }
+    }
+
+    // Square-and-multiply
+    pub fn pow(&self, exponent: u32) -> Self {
+        let (mut base, mut result, mut bits) = (self.clone(), Self::one(), exponent);
+        while bits > 0 {
+            if bits & 1 == 1 {
+                result = result * base.clone();
+            }
+            bits >>= 1;
+            if bits > 0 {
+                base = base.clone() * base;
+            }
+        }
+        result
+    }
+
+    // The polynomial multiplying var^k, with var removed
+    pub fn coefficient_in(&self, var: &str, k: u32) -> Self {
+        let mut result = Self::zero();
+        for (m, c) in &self.terms {
+            if m.exponent(var) == k {
+                result.add_term(m.without(var), c.clone());
+            }
+        }
+        result
+    }
+
+    pub fn derivative(&self, var: &str) -> Self {
+        let mut result = Self::zero();
+        for (m, c) in &self.terms {
+            let e = m.exponent(var);
+            if e > 0 {
+                let lowered = m.div(&Monomial::var(var, 1));
+                result.add_term(lowered, c.clone() * Rational::integer(e as i128));
+            }
+        }
+        result
+    }
+
+    pub fn substitute(&self, var: &str, value: &Polynomial) -> Self {
+        let mut result = Self::zero();
+        for (m, c) in &self.terms {
+            let rest = Self::term(m.without(var), c.clone());
+            result = result + rest * value.pow(m.exponent(var));
+        }
+        result
+    }
+
+    // Division with remainder by the lexicographic leading terms. For a single variable this is
+    // ordinary polynomial long division, and an exact divisor always leaves a zero remainder.
+    pub fn div_rem(&self, divisor: &Polynomial) -> Result<(Polynomial, Polynomial), String> {
+        let Some((lead_m, lead_c)) = divisor.leading_term() else {
+            return Err(String::from("polynomial division by zero"));
+        };
+
+        let mut quotient = Self::zero();
+        let mut remainder = Self::zero();
+        let mut rest = self.clone();
+
+        while let Some((m, c)) = rest.leading_term() {
+            if lead_m.divides(&m) {
+                let t = Self::term(m.div(&lead_m), c / lead_c.clone());
+                rest = rest - t.clone() * divisor.clone();
+                quotient = quotient + t;
+            } else {
+                rest.add_term(m.clone(), -c.clone());
+                remainder.add_term(m, c);
+            }
+        }
+
+        Ok((quotient, remainder))
+    }
+
+    // Exact division, for when the divisor is known to be a factor
+    pub fn div_exact(&self, divisor: &Polynomial) -> Polynomial {
+        let (quotient, remainder) = self.div_rem(divisor).expect("division by zero");
+        debug_assert!(remainder.is_zero(), "inexact polynomial division");
+        quotient
+    }
+
+    // The positive rational c such that self / c has coprime integer coefficients,
+    // signed so that the leading coefficient of self / c is positive
+    pub fn integer_content(&self) -> Rational {
+        let mut num = BigInt::zero();
+        let mut den = BigInt::one();
+        for c in self.terms.values() {
+            num = num.gcd(c.numer());
+            den = den.clone() * c.denom().div_rem(&den.gcd(c.denom())).0;
+        }
+        if num.is_zero() {
+            return Rational::one();
+        }
+        let content = Rational::new(num, den);
+        if self.leading_coefficient().signum() < 0 {
+            -content
+        } else {
+            content
+        }
+    }
+
+    // Integer coefficients without a common factor and a positive leading coefficient
+    pub fn normalized(&self) -> Polynomial {
+        if self.is_zero() {
+            return Self::zero();
+        }
+        self.scale(self.integer_content().recip())
+    }
+
+    fn add_term(&mut self, m: Monomial, c: Rational) {
+        let sum = self.terms.get(&m).cloned().unwrap_or_else(Rational::zero) + c;
+        if sum.is_zero() {
+            self.terms.remove(&m);
+        } else {
+            self.terms.insert(m, sum);
+        }
+    }
+
+    pub fn from_expression(expr: &Expression) -> Result<Polynomial, String> {
+        match expr {
+            Expression::Number(n) => Ok(Self::constant(Rational::from_f32(*n)?)),
+            Expression::Integer(n) => Ok(Self::constant(Rational::integer(n.clone()))),
+            Expression::Ident(name) => Ok(Self::var(name)),
+
+            Expression::Unary { op, rhs } => match op {
+                UnaryOperator::Neg => Ok(-Self::from_expression(rhs)?),
+                UnaryOperator::Plus => Self::from_expression(rhs),
+                UnaryOperator::Not
+                | UnaryOperator::Abs
+                | UnaryOperator::Factorial
+                | UnaryOperator::Percent
+                | UnaryOperator::Degree => Err(format!("{expr} is not a polynomial")),
+            },
+
+            Expression::Binary { lhs, op, rhs } => {
+                let l = Self::from_expression(lhs)?;
+                match op {
+                    Operator::Add => Ok(l + Self::from_expression(rhs)?),
+                    Operator::Sub => Ok(l - Self::from_expression(rhs)?),
+                    Operator::Mult => Ok(l * Self::from_expression(rhs)?),
+                    Operator::Div => match Self::from_expression(rhs)?.constant_value() {
+                        Some(c) if !c.is_zero() => Ok(l.scale(c.recip())),
+                        _ => Err(String::from(
+                            "not a polynomial: only division by a non-zero constant is allowed",
+                        )),
+                    },
+                    Operator::Pow => {
+                        let exponent = Self::from_expression(rhs)?.constant_value();
+                        match exponent.filter(|e| e.is_integer() && e.signum() >= 0) {
+                            Some(e) => match (e.numer().to_i128())
+                                .filter(|&e| e * l.total_degree().max(1) as i128 <= MAX_DEGREE)
+                            {
+                                Some(e) => Ok(l.pow(e as u32)),
+                                None => Err(format!(
+                                    "not a polynomial: {expr} has a degree above {MAX_DEGREE}"
+                                )),
+                            },
+                            None => Err(format!(
+                                "not a polynomial: exponents must be non-negative integers in {expr}"
+                            )),
+                        }
+                    }
+                    _ => Err(format!("not a polynomial: {expr}")),
+                }
+            }
+
+            other => Err(format!("not a polynomial: {other}")),
+        }
+    }
+
+    pub fn to_expression(&self) -> Expression {
+        let mut terms: Vec<(&Monomial, &Rational)> = self.terms.iter().collect();
+        terms.sort_by(|a, b| a.0.display_cmp(b.0));
+
+        let mut result: Option<Expression> = None;
+        for (m, c) in terms {
+            let term = term_expression(m, &c.abs());
+            result = Some(match result {
+                None if c.signum() < 0 => negated(term),
+                None => term,
+                Some(lhs) => Expression::Binary {
+                    lhs: Box::new(lhs),
+                    op: if c.signum() < 0 {
+                        Operator::Sub
+                    } else {
+                        Operator::Add
+                    },
+                    rhs: Box::new(term),
+                },
+            });
+        }
+
+        result.unwrap_or(Expression::Number(0.0))
+    }
+}
+
+fn rational_expression(c: &Rational) -> Expression {
+    let num = integer_expression(c.numer());
+    match c.is_integer() {
+        true => num,
+        false => Expression::Binary {
+            lhs: Box::new(num),
+            op: Operator::Div,
+            rhs: Box::new(integer_expression(c.denom())),
+        },
+    }
+}
+
+// Coefficients past 2^24 would lose digits as an f32 literal
+fn integer_expression(n: &BigInt) -> Expression {
+    match Value::integer(n.clone()) {
+        Value::Number(n) => Expression::Number(n),
+        _ => Expression::Integer(n.clone()),
+    }
+}
+
+// -(2*x) would print with brackets, so the sign goes on the leftmost factor instead
+pub(crate) fn negated(expr: Expression) -> Expression {
+    match expr {
+        Expression::Binary {
+            lhs,
+            op: op @ (Operator::Mult | Operator::Div),
+            rhs,
+        } => Expression::Binary {
+            lhs: Box::new(negated(*lhs)),
+            op,
+            rhs,
+        },
+        expr => Expression::Unary {
+            op: UnaryOperator::Neg,
+            rhs: Box::new(expr),
+        },
+    }
+}
+
+fn term_expression(m: &Monomial, c: &Rational) -> Expression {
+    let mut factors: Vec<Expression> = m
+        .vars()
+        .map(|(name, &e)| match e {
+            1 => Expression::Ident(name.clone()),
+            e => Expression::Binary {
+                lhs: Box::new(Expression::Ident(name.clone())),
+                op: Operator::Pow,
+                rhs: Box::new(Expression::Number(e as f32)),
+            },
+        })
+        .collect();
+
+    if *c != Rational::one() || factors.is_empty() {
+        factors.insert(0, rational_expression(c));
+    }
+
+    factors
+        .into_iter()
+        .reduce(|lhs, rhs| Expression::Binary {
+            lhs: Box::new(lhs),
+            op: Operator::Mult,
+            rhs: Box::new(rhs),
+        })
+        .unwrap()
+}
+
+impl Add for Polynomial {
+    type Output = Polynomial;
+
+    fn add(mut self, rhs: Polynomial) -> Polynomial {
+        for (m, c) in rhs.terms {
+            self.add_term(m, c);
+        }
+        self
+    }
+}
+
+impl Sub for Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, rhs: Polynomial) -> Polynomial {
+        self + -rhs
+    }
+}
+
+impl Mul for Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, rhs: Polynomial) -> Polynomial {
+        let mut result = Polynomial::zero();
+        for (a, x) in &self.terms {
+            for (b, y) in &rhs.terms {
+                result.add_term(a.mul(b), x.clone() * y.clone());
+            }
+        }
+        result
+    }
+}
+
+impl Neg for Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        self.scale(-Rational::one())
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_expression())
+    }
+}
+
+// Greatest common divisor over the rationals, normalized to integer coefficients.
+// Works one variable at a time: the gcd of the contents times that of the primitive parts,
+// the latter from a primitive pseudo-remainder sequence.
+pub fn gcd(a: &Polynomial, b: &Polynomial) -> Polynomial {
+    if a.is_zero() {
+        return b.normalized();
+    }
+    if b.is_zero() {
+        return a.normalized();
+    }
+
+    let vars: BTreeSet<String> = a.variables().union(&b.variables()).cloned().collect();
+    let Some(x) = vars.first() else {
+        return Polynomial::one();
+    };
+
+    let (content_a, primitive_a) = content_and_primitive(a, x);
+    let (content_b, primitive_b) = content_and_primitive(b, x);
+    let content = gcd(&content_a, &content_b);
+
+    let (mut r0, mut r1) = if primitive_a.degree_in(x) >= primitive_b.degree_in(x) {
+        (primitive_a, primitive_b)
+    } else {
+        (primitive_b, primitive_a)
+    };
+
+    while !r1.is_zero() {
+        if r1.degree_in(x) == 0 {
+            r0 = Polynomial::one();
+            break;
+        }
+        let r = pseudo_remainder(&r0, &r1, x);
+        r0 = r1;
+        r1 = content_and_primitive(&r, x).1;
+    }
+
+    (content * content_and_primitive(&r0, x).1).normalized()
+}
+
+// Splits p into its content, the gcd of its coefficients as a polynomial in x,
+// and the primitive part p / content
+pub fn content_and_primitive(p: &Polynomial, x: &str) -> (Polynomial, Polynomial) {
+    if p.is_zero() {
+        return (Polynomial::one(), Polynomial::zero());
+    }
+
+    let content = (0..=p.degree_in(x))
+        .map(|k| p.coefficient_in(x, k))
+        .filter(|c| !c.is_zero())
+        .fold(Polynomial::zero(), |acc, c| gcd(&acc, &c));
+
+    let primitive = p.div_exact(&content);
+    (content, primitive)
+}
+
+// lc(b)^(deg a - deg b + 1) * a reduced modulo b, keeping everything polynomial in the other variables
+fn pseudo_remainder(a: &Polynomial, b: &Polynomial, x: &str) -> Polynomial {
+    let db = b.degree_in(x);
+    let lead_b = b.coefficient_in(x, db);
+    let mut r = a.clone();
+
+    while !r.is_zero() && r.degree_in(x) >= db {
+        let dr = r.degree_in(x);
+        let lead_r = r.coefficient_in(x, dr);
+        let shift = Polynomial::term(Monomial::var(x, dr - db), Rational::one());
+        r = r * lead_b.clone() - lead_r * shift * b.clone();
+    }
+
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+    use crate::parser::parse_expression;
+
+    fn poly(input: &str) -> Polynomial {
+        let tokens = lexer::tokenize(input.to_string()).unwrap();
+        let expr = parse_expression(&mut tokens.into_iter().peekable()).unwrap();
+        Polynomial::from_expression(&expr).unwrap()
+    }
+
+    #[test]
+    fn expands_products() {
+        assert_eq!(poly("(x + 1)^3").to_string(), "x^3 + 3x^2 + 3x + 1");
+        assert_eq!(poly("(x - y)*(x + y)").to_string(), "x^2 - y^2");
+        assert_eq!(poly("x/2 + 0.25").to_string(), "1/2*x + 1/4");
+    }
+
+    #[test]
+    fn divides_with_remainder() {
+        let (q, r) = poly("x^3 - 2*x + 5").div_rem(&poly("x - 1")).unwrap();
+
+        assert_eq!(q, poly("x^2 + x - 1"));
+        assert_eq!(r, poly("4"));
+    }
+
+    #[test]
+    fn computes_gcd() {
+        assert_eq!(gcd(&poly("x^2 - 1"), &poly("x^2 + 2*x + 1")), poly("x + 1"));
+        assert_eq!(
+            gcd(&poly("x^2*y - y^3"), &poly("x*y + y^2")),
+            poly("x*y + y^2")
+        );
+        assert_eq!(gcd(&poly("x + 1"), &poly("x + 2")), poly("1"));
+    }
+
+    #[test]
+    fn expands_large_powers_exactly() {
+        let p = poly("(x + 1)^200");
+        assert_eq!(p.total_degree(), 200);
+        assert_eq!(
+            p.coefficient_in("x", 100).to_string(),
+            "90548514656103281165404177077484163874504589675413336841320"
+        );
+        assert_eq!(
+            poly("1e30 x * 1e30 x").to_string(),
+            format!("1{}x^2", "0".repeat(60))
+        );
+    }
+
+    #[test]
+    fn rejects_non_polynomials() {
+        let from = |input: &str| {
+            let tokens = lexer::tokenize(input.to_string()).unwrap();
+            let expr = parse_expression(&mut tokens.into_iter().peekable()).unwrap();
+            Polynomial::from_expression(&expr)
+        };
+
+        assert!(from("1 / x").is_err());
+        assert_eq!(
+            from("sin(x) + 1"),
+            Err(String::from("not a polynomial: sin(x)"))
+        );
+        assert_eq!(
+            from("x^1000000000"),
+            Err(String::from(
+                "not a polynomial: x^1000000000 has a degree above 10000"
+            ))
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use crate::evaluator::BigInt;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// An exact fraction in lowest terms with a positive denominator. Both parts are big integers,
+// so no coefficient can overflow however large expansion makes it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: BigInt,
+    den: BigInt,
+}
+
+impl Rational {
+    pub fn zero() -> Self {
+        Self::integer(0)
+    }
+
+    pub fn one() -> Self {
+        Self::integer(1)
+    }
+
+    pub fn new(num: impl Into<BigInt>, den: impl Into<BigInt>) -> Self {
+        let (num, den) = (num.into(), den.into());
+        assert!(!den.is_zero(), "rational with a zero denominator");
+        let mut g = num.gcd(&den);
+        if den.is_negative() {
+            g = -g;
+        }
+        Self {
+            num: num.div_rem(&g).0,
+            den: den.div_rem(&g).0,
+        }
+    }
+
+    pub fn integer(n: impl Into<BigInt>) -> Self {
+        Self {
+            num: n.into(),
+            den: BigInt::one(),
+        }
+    }
+
+    // Takes the shortest decimal that prints as x, so 0.1 becomes 1/10 rather than its binary value
+    pub fn from_f32(x: f32) -> Result<Self, String> {
+        if !x.is_finite() {
+            return Err(format!("{x} is not a finite number"));
+        }
+
+        let text = x.abs().to_string();
+        let (int, frac) = text.split_once('.').unwrap_or((&text, ""));
+        let num = BigInt::parse_radix(&format!("{int}{frac}"), 10)
+            .ok_or(format!("{x} is not a decimal number"))?;
+        let den = BigInt::from(10).pow(frac.len() as u64);
+
+        let num = if x < 0.0 { -num } else { num };
+        Ok(Self::new(num, den))
+    }
+
+    pub fn numer(&self) -> &BigInt {
+        &self.num
+    }
+
+    pub fn denom(&self) -> &BigInt {
+        &self.den
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num.is_zero()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.den == BigInt::one()
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            num: self.num.clone().abs(),
+            den: self.den.clone(),
+        }
+    }
+
+    pub fn signum(&self) -> i128 {
+        match (self.num.is_negative(), self.num.is_zero()) {
+            (true, _) => -1,
+            (_, true) => 0,
+            _ => 1,
+        }
+    }
+
+    pub fn recip(&self) -> Self {
+        Self::new(self.den.clone(), self.num.clone())
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.num.to_f64() / self.den.to_f64()
+    }
+
+    pub fn to_f32(&self) -> f32 {
+        self.to_f64() as f32
+    }
+}
+
+pub fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl Add for Rational {
+    type Output = Rational;
+
+    fn add(self, rhs: Rational) -> Rational {
+        Rational::new(
+            self.num * rhs.den.clone() + rhs.num * self.den.clone(),
+            self.den * rhs.den,
+        )
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+
+    fn sub(self, rhs: Rational) -> Rational {
+        self + -rhs
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+
+    fn mul(self, rhs: Rational) -> Rational {
+        Rational::new(self.num * rhs.num, self.den * rhs.den)
+    }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+
+    fn div(self, rhs: Rational) -> Rational {
+        assert!(!rhs.is_zero(), "rational division by zero");
+        self * rhs.recip()
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    // Denominators are positive, so cross-multiplying keeps the order
+    fn cmp(&self, other: &Rational) -> Ordering {
+        (self.num.clone() * other.den.clone()).cmp(&(other.num.clone() * self.den.clone()))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.is_integer() {
+            true => write!(f, "{}", self.num),
+            false => write!(f, "{}/{}", self.num, self.den),
+        }
+    }
+}
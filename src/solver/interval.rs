@@ -0,0 +1,78 @@
+use crate::evaluator::{Environment, Interval, Value, eval_in};
+use crate::parser::Expression;
+
+const MAX_BOXES: usize = 200_000;
+// Boxes narrower than this, relative to their magnitude, are not split any further
+const RELATIVE_WIDTH: f32 = 1e-5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Root {
+    pub enclosure: Interval,
+    // The sign of the equation changes across a box where it is continuous, so a root exists
+    pub verified: bool,
+}
+
+// Branch and prune: boxes on which lhs - rhs cannot be zero are discarded, the rest are
+// bisected. No root inside the domain is ever lost, every one lies in some returned enclosure.
+pub fn find_roots(
+    lhs: &Expression,
+    rhs: &Expression,
+    unknown: &str,
+    domain: Interval,
+    env: &Environment,
+) -> Result<Vec<Root>, String> {
+    let mut env = env.clone();
+    let mut residual = |x: Interval| -> Result<Interval, String> {
+        env.set(unknown.to_string(), Value::Interval(x));
+        let l = eval_in(lhs.clone(), &env)?;
+        let r = eval_in(rhs.clone(), &env)?;
+        l.try_sub(r)?.as_interval()
+    };
+
+    let mut boxes = vec![domain];
+    let mut candidates: Vec<Interval> = Vec::new();
+    let mut visited = 0;
+
+    while let Some(x) = boxes.pop() {
+        visited += 1;
+        if visited > MAX_BOXES {
+            return Err(format!(
+                "gave up after {MAX_BOXES} boxes, try a smaller interval for {unknown}"
+            ));
+        }
+
+        if !residual(x)?.contains(0.0) {
+            continue;
+        }
+
+        let mid = x.midpoint();
+        let narrow = x.width() <= RELATIVE_WIDTH * x.lo.abs().max(x.hi.abs()).max(1.0);
+        if narrow || mid <= x.lo || mid >= x.hi {
+            match candidates.last_mut() {
+                Some(last) if last.hi >= x.lo => last.hi = x.hi,
+                _ => candidates.push(x),
+            }
+            continue;
+        }
+
+        // Left half last so it is popped first and the roots come out in order
+        let (left, right) = x.bisect();
+        boxes.push(right);
+        boxes.push(left);
+    }
+
+    candidates
+        .into_iter()
+        .map(|enclosure| {
+            let at_lo = residual(Interval::point(enclosure.lo))?;
+            let at_hi = residual(Interval::point(enclosure.hi))?;
+            let changes_sign =
+                (at_lo.hi < 0.0 && at_hi.lo > 0.0) || (at_lo.lo > 0.0 && at_hi.hi < 0.0);
+
+            Ok(Root {
+                enclosure,
+                verified: changes_sign && residual(enclosure)?.is_bounded(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,467 @@
+use crate::evaluator::{Environment, partial};
+use crate::parser::{Comparison, Constraint, Expression, Goal, Operator};
+use crate::polynomial::Polynomial;
+use std::collections::BTreeSet;
+
+// Pivots smaller than this are treated as zero
+const EPSILON: f64 = 1e-9;
+
+// Branch and bound gives up after this many relaxations
+const MAX_NODES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Relation {
+    AtMost,
+    AtLeast,
+    Equal,
+}
+
+// Maximise objective · x subject to each row · x (relation) bound, with every x free
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearProgram {
+    pub objective: Vec<f64>,
+    pub constraints: Vec<(Vec<f64>, Relation, f64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Optimal { value: f64, point: Vec<f64> },
+    Infeasible,
+    Unbounded,
+}
+
+// A dictionary of rows over the columns, the last entry of each row being its right-hand side
+struct Tableau {
+    rows: Vec<Vec<f64>>,
+    basis: Vec<usize>,
+}
+
+impl Tableau {
+    fn pivot(&mut self, row: usize, col: usize) {
+        let p = self.rows[row][col];
+        self.rows[row].iter_mut().for_each(|v| *v /= p);
+        let pivot = self.rows[row].clone();
+
+        for (i, r) in self.rows.iter_mut().enumerate() {
+            let factor = r[col];
+            if i != row && factor != 0.0 {
+                r.iter_mut().zip(&pivot).for_each(|(v, p)| *v -= factor * p);
+            }
+        }
+        self.basis[row] = col;
+    }
+
+    fn value(&self, cost: &[f64]) -> f64 {
+        let rhs = self.rows.first().map_or(0, |r| r.len() - 1);
+        (self.rows.iter().zip(&self.basis))
+            .map(|(r, &b)| cost[b] * r[rhs])
+            .sum()
+    }
+
+    // Climbs to the best basis for cost using the first `columns` columns. Bland's rule of
+    // always taking the lowest index keeps degenerate pivots from cycling. False if unbounded
+    fn maximize(&mut self, cost: &[f64], columns: usize) -> bool {
+        let Some(rhs) = self.rows.first().map(|r| r.len() - 1) else {
+            return !cost[..columns].iter().any(|&c| c > EPSILON);
+        };
+
+        loop {
+            let reduced = |j: usize| {
+                cost[j]
+                    - (self.rows.iter().zip(&self.basis))
+                        .map(|(r, &b)| cost[b] * r[j])
+                        .sum::<f64>()
+            };
+            let Some(col) =
+                (0..columns).find(|&j| !self.basis.contains(&j) && reduced(j) > EPSILON)
+            else {
+                return true;
+            };
+
+            let leaving = (0..self.rows.len())
+                .filter(|&i| self.rows[i][col] > EPSILON)
+                .min_by(|&a, &b| {
+                    let ratio = |i: usize| self.rows[i][rhs] / self.rows[i][col];
+                    ratio(a)
+                        .total_cmp(&ratio(b))
+                        .then(self.basis[a].cmp(&self.basis[b]))
+                });
+            match leaving {
+                Some(row) => self.pivot(row, col),
+                None => return false,
+            }
+        }
+    }
+}
+
+// The two-phase simplex method. Each free variable x is split into x+ - x- with both parts
+// at least 0, and phase one finds a feasible basis by driving artificial variables to zero
+pub fn simplex(lp: &LinearProgram) -> Outcome {
+    let n = lp.objective.len();
+    let slacks = lp
+        .constraints
+        .iter()
+        .filter(|(_, relation, _)| *relation != Relation::Equal)
+        .count();
+    let artificials = lp
+        .constraints
+        .iter()
+        .filter(|(_, relation, bound)| match relation {
+            Relation::AtMost => *bound < 0.0,
+            Relation::AtLeast => *bound >= 0.0,
+            Relation::Equal => true,
+        })
+        .count();
+    let columns = 2 * n + slacks + artificials;
+
+    let mut tableau = Tableau {
+        rows: Vec::new(),
+        basis: Vec::new(),
+    };
+    let (mut slack, mut artificial) = (2 * n, 2 * n + slacks);
+    for (coefficients, relation, bound) in &lp.constraints {
+        // Rows start with a right-hand side of at least 0, flipping the relation if need be
+        let sign = if *bound < 0.0 { -1.0 } else { 1.0 };
+        let mut row = vec![0.0; columns + 1];
+        for (j, &a) in coefficients.iter().enumerate() {
+            row[2 * j] = sign * a;
+            row[2 * j + 1] = -sign * a;
+        }
+        row[columns] = sign * bound;
+
+        let relation = match (relation, sign < 0.0) {
+            (Relation::AtMost, true) => Relation::AtLeast,
+            (Relation::AtLeast, true) => Relation::AtMost,
+            (relation, _) => *relation,
+        };
+        let basic = match relation {
+            Relation::AtMost => {
+                row[slack] = 1.0;
+                slack += 1;
+                slack - 1
+            }
+            Relation::AtLeast => {
+                row[slack] = -1.0;
+                row[artificial] = 1.0;
+                slack += 1;
+                artificial += 1;
+                artificial - 1
+            }
+            Relation::Equal => {
+                row[artificial] = 1.0;
+                artificial += 1;
+                artificial - 1
+            }
+        };
+        tableau.rows.push(row);
+        tableau.basis.push(basic);
+    }
+
+    let first_artificial = 2 * n + slacks;
+    let phase_one: Vec<f64> = (0..columns)
+        .map(|j| if j >= first_artificial { -1.0 } else { 0.0 })
+        .collect();
+    tableau.maximize(&phase_one, columns);
+    if tableau.value(&phase_one) < -EPSILON {
+        return Outcome::Infeasible;
+    }
+
+    // Artificials left in the basis at zero are swapped out where a real column can take
+    // their place, the rows where none can are redundant
+    for i in 0..tableau.rows.len() {
+        if tableau.basis[i] >= first_artificial
+            && let Some(col) = (0..first_artificial).find(|&j| tableau.rows[i][j].abs() > EPSILON)
+        {
+            tableau.pivot(i, col);
+        }
+    }
+
+    let mut cost = vec![0.0; columns];
+    for (j, &c) in lp.objective.iter().enumerate() {
+        cost[2 * j] = c;
+        cost[2 * j + 1] = -c;
+    }
+    if !tableau.maximize(&cost, first_artificial) {
+        return Outcome::Unbounded;
+    }
+
+    let mut values = vec![0.0; columns];
+    for (row, &b) in tableau.rows.iter().zip(&tableau.basis) {
+        values[b] = row[columns];
+    }
+    Outcome::Optimal {
+        value: tableau.value(&cost),
+        point: (0..n).map(|j| values[2 * j] - values[2 * j + 1]).collect(),
+    }
+}
+
+// The best point with the given variables whole numbers. Each relaxation that lands on a
+// fraction is split into x <= floor and x >= ceil, and a branch is dropped once its
+// relaxation cannot beat the best whole point found so far
+pub fn branch_and_bound(lp: &LinearProgram, integers: &[usize]) -> Result<Outcome, String> {
+    let mut best: Option<(f64, Vec<f64>)> = None;
+    let mut pending = vec![lp.clone()];
+    let mut nodes = 0;
+
+    while let Some(node) = pending.pop() {
+        nodes += 1;
+        if nodes > MAX_NODES {
+            return Err(format!(
+                "branch and bound gave up after {MAX_NODES} relaxations"
+            ));
+        }
+
+        let (value, point) = match simplex(&node) {
+            Outcome::Optimal { value, point } => (value, point),
+            Outcome::Infeasible => continue,
+            // A relaxation with no bound at the root leaves nothing to branch on
+            Outcome::Unbounded if nodes == 1 => return Ok(Outcome::Unbounded),
+            Outcome::Unbounded => continue,
+        };
+        if best
+            .as_ref()
+            .is_some_and(|(best, _)| value <= best + EPSILON)
+        {
+            continue;
+        }
+
+        let fractional = integers
+            .iter()
+            .copied()
+            .find(|&j| (point[j] - point[j].round()).abs() > 1e-6);
+        let Some(j) = fractional else {
+            let point = point
+                .iter()
+                .enumerate()
+                .map(|(i, &x)| if integers.contains(&i) { x.round() } else { x })
+                .collect();
+            best = Some((value, point));
+            continue;
+        };
+
+        let mut unit = vec![0.0; node.objective.len()];
+        unit[j] = 1.0;
+        for (relation, bound) in [
+            (Relation::AtMost, point[j].floor()),
+            (Relation::AtLeast, point[j].ceil()),
+        ] {
+            let mut branch = node.clone();
+            branch.constraints.push((unit.clone(), relation, bound));
+            pending.push(branch);
+        }
+    }
+
+    Ok(match best {
+        Some((value, point)) => Outcome::Optimal { value, point },
+        None => Outcome::Infeasible,
+    })
+}
+
+// The best value of a linear objective over the names it leaves unknown, with the constraints
+// and what is assumed about those names as the linear program. Names assumed to be integers are
+// found by branch and bound. The optimum is given as the objective's value, with the names in
+// the order of the point
+pub fn optimize(
+    goal: Goal,
+    objective: &Expression,
+    constraints: &[Constraint],
+    env: &Environment,
+) -> Result<(Vec<String>, Outcome), String> {
+    const ZERO: Expression = Expression::Number(0.0);
+    let linear = |lhs: &Expression, rhs: &Expression| -> Result<Polynomial, String> {
+        let difference = Expression::Binary {
+            lhs: Box::new(lhs.clone()),
+            op: Operator::Sub,
+            rhs: Box::new(rhs.clone()),
+        };
+        let p = Polynomial::from_expression(&partial::partial_eval(difference, env)?)?;
+        match p.total_degree() {
+            0 | 1 => Ok(p),
+            _ => Err(format!("{goal}: expected a linear expression, got {p}")),
+        }
+    };
+
+    let target = linear(objective, &ZERO)?;
+    let rows = constraints
+        .iter()
+        .map(|c| Ok((linear(&c.lhs, &c.rhs)?, c.comparison)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut names: BTreeSet<String> = target.variables();
+    for (row, _) in &rows {
+        names.extend(row.variables());
+    }
+    let names: Vec<String> = names.into_iter().collect();
+    if names.is_empty() {
+        return Err(format!(
+            "{goal}: nothing to optimize, every name in the objective is known"
+        ));
+    }
+
+    let constant = |p: &Polynomial| {
+        p.terms()
+            .find(|(m, _)| m.degree() == 0)
+            .map_or(0.0, |(_, c)| c.to_f64())
+    };
+    let coefficients = |p: &Polynomial| -> Vec<f64> {
+        (names.iter())
+            .map(|x| (p.coefficient_in(x, 1).constant_value()).map_or(0.0, |c| c.to_f64()))
+            .collect()
+    };
+
+    // Maximizing -f minimizes f
+    let sign = match goal {
+        Goal::Maximize => 1.0,
+        Goal::Minimize => -1.0,
+    };
+    let mut lp = LinearProgram {
+        objective: coefficients(&target).iter().map(|c| sign * c).collect(),
+        constraints: Vec::new(),
+    };
+    for (row, comparison) in &rows {
+        let relation = match comparison {
+            Comparison::AtMost => Relation::AtMost,
+            Comparison::AtLeast => Relation::AtLeast,
+            Comparison::Equal => Relation::Equal,
+        };
+        lp.constraints
+            .push((coefficients(row), relation, -constant(row)));
+    }
+
+    // A strict assumed bound is taken as closed, as an open region has no optimum on its edge
+    let mut integers = Vec::new();
+    for (j, name) in names.iter().enumerate() {
+        let Some(domain) = env.domain(name) else {
+            continue;
+        };
+        let mut unit = vec![0.0; names.len()];
+        unit[j] = 1.0;
+        if let Some(b) = domain.lower {
+            lp.constraints
+                .push((unit.clone(), Relation::AtLeast, b.value as f64));
+        }
+        if let Some(b) = domain.upper {
+            lp.constraints
+                .push((unit, Relation::AtMost, b.value as f64));
+        }
+        if domain.integer {
+            integers.push(j);
+        }
+    }
+
+    let outcome = match integers.is_empty() {
+        true => simplex(&lp),
+        false => branch_and_bound(&lp, &integers).map_err(|e| format!("{goal}: {e}"))?,
+    };
+    let outcome = match outcome {
+        Outcome::Optimal { value, point } => Outcome::Optimal {
+            value: sign * value + constant(&target),
+            point,
+        },
+        outcome => outcome,
+    };
+    Ok((names, outcome))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(objective: &[f64], constraints: &[(&[f64], Relation, f64)]) -> LinearProgram {
+        LinearProgram {
+            objective: objective.to_vec(),
+            constraints: constraints
+                .iter()
+                .map(|(a, relation, b)| (a.to_vec(), *relation, *b))
+                .collect(),
+        }
+    }
+
+    fn assert_optimal(outcome: Outcome, value: f64, point: &[f64]) {
+        let Outcome::Optimal { value: v, point: p } = outcome else {
+            panic!("expected an optimum, got {outcome:?}");
+        };
+        assert!((v - value).abs() < 1e-6, "value {v}, expected {value}");
+        assert!(
+            p.iter().zip(point).all(|(a, b)| (a - b).abs() < 1e-6),
+            "point {p:?}, expected {point:?}"
+        );
+    }
+
+    #[test]
+    fn solves_linear_programs() {
+        use Relation::*;
+        let positive: [(&[f64], Relation, f64); 2] =
+            [(&[1.0, 0.0], AtLeast, 0.0), (&[0.0, 1.0], AtLeast, 0.0)];
+        let constraints = [
+            (&[1.0, 1.0][..], AtMost, 4.0),
+            (&[1.0, 3.0][..], AtMost, 6.0),
+            positive[0],
+            positive[1],
+        ];
+        assert_optimal(
+            simplex(&program(&[3.0, 2.0], &constraints)),
+            12.0,
+            &[4.0, 0.0],
+        );
+
+        // Minimising x + y with x + 2y >= 4 and 3x + y >= 6
+        let constraints = [
+            (&[1.0, 2.0][..], AtLeast, 4.0),
+            (&[3.0, 1.0][..], AtLeast, 6.0),
+            positive[0],
+            positive[1],
+        ];
+        assert_optimal(
+            simplex(&program(&[-1.0, -1.0], &constraints)),
+            -2.8,
+            &[1.6, 1.2],
+        );
+
+        // Free variables and an equality: x = y - 3 with y <= 1
+        let constraints = [
+            (&[1.0, -1.0][..], Equal, -3.0),
+            (&[0.0, 1.0][..], AtMost, 1.0),
+        ];
+        assert_optimal(
+            simplex(&program(&[1.0, 0.0], &constraints)),
+            -2.0,
+            &[-2.0, 1.0],
+        );
+
+        let infeasible = [(&[1.0][..], AtMost, 1.0), (&[1.0][..], AtLeast, 2.0)];
+        assert_eq!(simplex(&program(&[1.0], &infeasible)), Outcome::Infeasible);
+        let unbounded = [(&[1.0, -1.0][..], AtMost, 1.0), positive[0], positive[1]];
+        assert_eq!(
+            simplex(&program(&[1.0, 1.0], &unbounded)),
+            Outcome::Unbounded
+        );
+    }
+
+    #[test]
+    fn branches_to_integer_points() {
+        use Relation::*;
+        // The relaxation peaks at (1.8, 2.8), and no whole point has y = 3
+        let constraints = [
+            (&[-1.0, 1.0][..], AtMost, 1.0),
+            (&[3.0, 2.0][..], AtMost, 12.0),
+            (&[2.0, 3.0][..], AtMost, 12.0),
+            (&[1.0, 0.0][..], AtLeast, 0.0),
+            (&[0.0, 1.0][..], AtLeast, 0.0),
+        ];
+        let lp = program(&[0.0, 1.0], &constraints);
+        assert_optimal(simplex(&lp), 2.8, &[1.8, 2.8]);
+
+        let Outcome::Optimal { value, point } = branch_and_bound(&lp, &[0, 1]).unwrap() else {
+            panic!("expected an optimum");
+        };
+        assert_eq!(value, 2.0);
+        assert!(point.iter().all(|x| x.fract() == 0.0));
+
+        let odd = [(&[2.0][..], Equal, 3.0)];
+        assert_eq!(
+            branch_and_bound(&program(&[1.0], &odd), &[0]).unwrap(),
+            Outcome::Infeasible
+        );
+    }
+}
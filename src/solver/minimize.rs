@@ -0,0 +1,502 @@
+use crate::evaluator::{Environment, Value, derivative, eval_in, partial};
+use crate::parser::Expression;
+use std::fmt;
+
+// Minimisation stops once the gradient, or the simplex, is this small relative to the values
+const TOLERANCE: f64 = 1e-6;
+
+const MAX_ITERATIONS: usize = 1000;
+
+// A value below minus this, or a point further out than it, is taken to be running off to infinity
+const DIVERGED: f64 = 1e12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Bfgs,
+    NelderMead,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Minimum {
+    pub point: Vec<f64>,
+    pub value: f64,
+    pub method: Method,
+    pub iterations: usize,
+    pub evaluations: usize,
+    // False when the iterations ran out first
+    pub converged: bool,
+    // True when f kept falling as the point ran off towards infinity
+    pub unbounded: bool,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::Bfgs => write!(f, "BFGS"),
+            Method::NelderMead => write!(f, "Nelder–Mead"),
+        }
+    }
+}
+
+// The lowest value of objective as the given names vary, each name either free or kept to a
+// range such as x from 0 to 1, or to the interval it is bound to. A let's name stands for its
+// formula. BFGS needs the gradient, and Nelder–Mead takes over where it cannot be found
+pub fn minimum(
+    objective: &Expression,
+    args: &[Expression],
+    env: &Environment,
+) -> Result<(Vec<String>, Minimum), String> {
+    let mut names = Vec::new();
+    let mut bounds = Vec::new();
+    let mut start = Vec::new();
+    for arg in args {
+        let number = |e: &Expression| -> Result<f64, String> {
+            Ok(eval_in(e.clone(), env)?.as_number()? as f64)
+        };
+        let (name, (lo, hi)) = match arg {
+            Expression::Ident(name) => match env.get(name) {
+                Some(Value::Interval(i)) => (name, (i.lo as f64, i.hi as f64)),
+                _ => (name, (f64::NEG_INFINITY, f64::INFINITY)),
+            },
+            Expression::Range {
+                name,
+                from,
+                to,
+                step: None,
+            } => (name, (number(from)?, number(to)?)),
+            _ => {
+                return Err(format!(
+                    "expected a name, or a range such as x from 0 to 1, to vary, got {arg}"
+                ));
+            }
+        };
+
+        let assumed = env.domain(name).copied().unwrap_or_default();
+        let lo = assumed.lower.map_or(lo, |b| lo.max(b.value as f64));
+        let hi = assumed.upper.map_or(hi, |b| hi.min(b.value as f64));
+        if lo > hi {
+            return Err(format!("{name} has no room between {lo} and {hi}"));
+        }
+
+        // The search starts from the name's value, or from the middle of its range
+        let x = match env.get(name) {
+            Some(Value::Number(x)) => *x as f64,
+            _ if lo.is_finite() && hi.is_finite() => (lo + hi) / 2.0,
+            _ => 0.0,
+        };
+        names.push(name.clone());
+        bounds.push((lo, hi));
+        start.push(x.max(lo).min(hi));
+    }
+    if names.is_empty() {
+        return Err(format!("nothing to vary in {objective}, name at least one"));
+    }
+
+    let formulas: Vec<(String, Expression)> = (objective.identifiers().into_iter())
+        .filter_map(|name| Some((name.clone(), env.formula(&name)?.clone())))
+        .collect();
+    let mut scope = env.clone();
+    names.iter().for_each(|name| _ = scope.remove(name));
+    let objective =
+        partial::partial_eval(partial::substitute(objective.clone(), &formulas), &scope)?;
+    if let Some(unknown) = (objective.identifiers().into_iter()).find(|n| !names.contains(n)) {
+        return Err(format!("{unknown} has no value, name it to vary it too"));
+    }
+
+    let at = |expr: &Expression, x: &[f64]| -> Result<f64, String> {
+        let mut env = scope.clone();
+        for (name, &x) in names.iter().zip(x) {
+            env.set(name.clone(), Value::Number(x as f32));
+        }
+        Ok(eval_in(expr.clone(), &env)?.as_number()? as f64)
+    };
+    let f = |x: &[f64]| at(&objective, x);
+
+    let gradient = (names.iter())
+        .map(|name| derivative::derivative(&objective, name))
+        .collect::<Result<Vec<Expression>, String>>();
+    let minimum = match gradient {
+        Ok(gradient) => {
+            let slope = |x: &[f64]| gradient.iter().map(|d| at(d, x)).collect();
+            bfgs(f, slope, &start, &bounds).or_else(|_| nelder_mead(f, &start, &bounds))
+        }
+        Err(_) => nelder_mead(f, &start, &bounds),
+    }?;
+    Ok((names, minimum))
+}
+
+// Quasi-Newton descent, building up an inverse Hessian from how the gradient changes between
+// steps. Bounds are kept by clamping each step into the box, with a variable held at a bound
+// while the gradient pushes it outward
+pub fn bfgs(
+    f: impl Fn(&[f64]) -> Result<f64, String>,
+    gradient: impl Fn(&[f64]) -> Result<Vec<f64>, String>,
+    start: &[f64],
+    bounds: &[(f64, f64)],
+) -> Result<Minimum, String> {
+    let n = start.len();
+    let mut evaluations = 0;
+    let mut value = |x: &[f64]| {
+        evaluations += 1;
+        f(x)
+    };
+    let slope = |x: &[f64]| match gradient(x)? {
+        g if g.iter().all(|v| v.is_finite()) => Ok(g),
+        _ => Err(String::from("the gradient is not finite")),
+    };
+
+    let mut x = clamp(start, bounds);
+    let mut fx = finite(value(&x)?)?;
+    let mut g = slope(&x)?;
+    let mut h = identity(n);
+    let mut iterations = 0;
+    let mut converged = false;
+
+    while iterations < MAX_ITERATIONS {
+        let pg = projected(&x, &g, bounds);
+        if norm(&pg) <= TOLERANCE * (1.0 + fx.abs()) {
+            converged = true;
+            break;
+        }
+        iterations += 1;
+
+        let mut d: Vec<f64> = (0..n)
+            .map(|i| if pg[i] == 0.0 { 0.0 } else { -dot(&h[i], &pg) })
+            .collect();
+        if dot(&d, &pg) >= 0.0 {
+            h = identity(n);
+            d = pg.iter().map(|v| -v).collect();
+        }
+
+        // Backtracks until the step lowers f by a fair share of what the slope promised. A
+        // point f cannot be evaluated at counts as no lower. Close to the minimum f can be too
+        // flat for its precision to show any decrease, so there a step that keeps f and
+        // flattens the slope is taken as well
+        let mut t = 1.0;
+        let step = loop {
+            let next: Vec<f64> = (0..n).map(|i| x[i] + t * d[i]).collect();
+            let next = clamp(&next, bounds);
+            let f_next = value(&next).unwrap_or(f64::INFINITY);
+            let promised = dot(&g, &difference(&next, &x));
+            if f_next <= fx + 1e-4 * promised {
+                break Some((next, f_next));
+            }
+            if f_next <= fx
+                && let Ok(g_next) = slope(&next)
+                && norm(&projected(&next, &g_next, bounds)) < norm(&pg)
+            {
+                break Some((next, f_next));
+            }
+            t /= 2.0;
+            if t < 1e-12 {
+                break None;
+            }
+        };
+        // Nothing along the direction is lower, so x is a minimum as far as f can tell
+        let Some((next, f_next)) = step else {
+            converged = true;
+            break;
+        };
+
+        let g_next = slope(&next)?;
+        let s = difference(&next, &x);
+        let y = difference(&g_next, &g);
+        let sy = dot(&s, &y);
+        if sy > 1e-12 {
+            let rho = 1.0 / sy;
+            let hy: Vec<f64> = h.iter().map(|row| dot(row, &y)).collect();
+            let scale = rho * rho * dot(&y, &hy) + rho;
+            for i in 0..n {
+                for j in 0..n {
+                    h[i][j] += scale * s[i] * s[j] - rho * (s[i] * hy[j] + hy[i] * s[j]);
+                }
+            }
+        }
+
+        if diverged(&next, f_next) {
+            (x, fx) = (next, f_next);
+            break;
+        }
+        let settled = (fx - f_next).abs() <= TOLERANCE * (1.0 + fx.abs())
+            && norm(&s) <= TOLERANCE * (1.0 + norm(&x));
+        (x, fx, g) = (next, f_next, g_next);
+        if settled {
+            converged = true;
+            break;
+        }
+    }
+
+    // The last descent direction is followed out, in case f keeps falling along it for ever
+    let pg = projected(&x, &g, bounds);
+    let downhill: Vec<f64> = (0..n).map(|i| -dot(&h[i], &pg)).collect();
+    let unbounded = diverged(&x, fx) || runs_off(&mut value, &x, fx, &downhill, bounds);
+    converged &= !unbounded;
+
+    Ok(Minimum {
+        point: x,
+        value: fx,
+        method: Method::Bfgs,
+        iterations,
+        evaluations,
+        converged,
+        unbounded,
+    })
+}
+
+// The downhill simplex, which only compares values of f and so needs no gradient. The simplex
+// reflects, expands and contracts its worst vertex, and shrinks towards its best when none
+// of those helps
+pub fn nelder_mead(
+    f: impl Fn(&[f64]) -> Result<f64, String>,
+    start: &[f64],
+    bounds: &[(f64, f64)],
+) -> Result<Minimum, String> {
+    let n = start.len();
+    let mut evaluations = 0;
+    let mut value = |x: &[f64]| {
+        evaluations += 1;
+        f(x).unwrap_or(f64::INFINITY)
+    };
+
+    let first = clamp(start, bounds);
+    let mut simplex = vec![(first.clone(), finite(f(&first)?)?)];
+    for i in 0..n {
+        let mut vertex = first.clone();
+        let step = if vertex[i] == 0.0 {
+            0.25
+        } else {
+            0.05 * vertex[i]
+        };
+        vertex[i] += if vertex[i] + step > bounds[i].1 {
+            -step
+        } else {
+            step
+        };
+        let vertex = clamp(&vertex, bounds);
+        let fv = value(&vertex);
+        simplex.push((vertex, fv));
+    }
+
+    let mut iterations = 0;
+    let mut converged = false;
+    let mut unbounded = false;
+    while iterations < MAX_ITERATIONS {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let (best, worst) = (simplex[0].1, simplex[n].1);
+        if diverged(&simplex[0].0, best) {
+            unbounded = true;
+            break;
+        }
+        let size = (simplex.iter())
+            .map(|(v, _)| norm(&difference(v, &simplex[0].0)))
+            .fold(0.0, f64::max);
+        if (worst - best).abs() <= TOLERANCE * (1.0 + best.abs())
+            && size <= TOLERANCE * (1.0 + norm(&simplex[0].0))
+        {
+            converged = true;
+            break;
+        }
+        iterations += 1;
+
+        let centroid: Vec<f64> = (0..n)
+            .map(|j| simplex[..n].iter().map(|(v, _)| v[j]).sum::<f64>() / n as f64)
+            .collect();
+        // centroid + t (centroid - worst)
+        let along = |t: f64, worst: &[f64]| -> Vec<f64> {
+            let point: Vec<f64> = (0..n)
+                .map(|j| centroid[j] + t * (centroid[j] - worst[j]))
+                .collect();
+            clamp(&point, bounds)
+        };
+
+        let reflected = along(1.0, &simplex[n].0);
+        let f_reflected = value(&reflected);
+        if f_reflected < best {
+            let expanded = along(2.0, &simplex[n].0);
+            let f_expanded = value(&expanded);
+            simplex[n] = match f_expanded < f_reflected {
+                true => (expanded, f_expanded),
+                false => (reflected, f_reflected),
+            };
+            continue;
+        }
+        if f_reflected < simplex[n - 1].1 {
+            simplex[n] = (reflected, f_reflected);
+            continue;
+        }
+
+        let contracted = match f_reflected < worst {
+            true => along(0.5, &simplex[n].0),
+            false => along(-0.5, &simplex[n].0),
+        };
+        let f_contracted = value(&contracted);
+        if f_contracted < f_reflected.min(worst) {
+            simplex[n] = (contracted, f_contracted);
+            continue;
+        }
+
+        let best = simplex[0].0.clone();
+        for (vertex, fv) in simplex.iter_mut().skip(1) {
+            *vertex = (0..n)
+                .map(|j| best[j] + 0.5 * (vertex[j] - best[j]))
+                .collect();
+            *fv = value(vertex);
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let (point, value) = simplex.swap_remove(0);
+    Ok(Minimum {
+        point,
+        value,
+        method: Method::NelderMead,
+        iterations,
+        evaluations: evaluations + 1,
+        converged,
+        unbounded,
+    })
+}
+
+fn diverged(x: &[f64], value: f64) -> bool {
+    value < -DIVERGED || norm(x) > DIVERGED
+}
+
+// Whether f falls at every step along direction, each step twice as far out as the last,
+// until the value or the point diverges
+fn runs_off(
+    f: &mut impl FnMut(&[f64]) -> Result<f64, String>,
+    x: &[f64],
+    fx: f64,
+    direction: &[f64],
+    bounds: &[(f64, f64)],
+) -> bool {
+    let length = norm(direction);
+    if length == 0.0 {
+        return false;
+    }
+
+    let (mut last, mut t) = (fx, 1.0);
+    loop {
+        let point: Vec<f64> = (x.iter().zip(direction))
+            .map(|(x, d)| x + t * d / length)
+            .collect();
+        let point = clamp(&point, bounds);
+        match f(&point) {
+            Ok(v) if v < last && diverged(&point, v) => return true,
+            Ok(v) if v < last => (last, t) = (v, 2.0 * t),
+            _ => return false,
+        }
+    }
+}
+
+fn finite(value: f64) -> Result<f64, String> {
+    match value.is_finite() {
+        true => Ok(value),
+        false => Err(format!("the objective is {value} at the starting point")),
+    }
+}
+
+// The gradient with the parts that push a variable out through its bound left out
+fn projected(x: &[f64], g: &[f64], bounds: &[(f64, f64)]) -> Vec<f64> {
+    (0..x.len())
+        .map(
+            |i| match (x[i] <= bounds[i].0 && g[i] > 0.0) || (x[i] >= bounds[i].1 && g[i] < 0.0) {
+                true => 0.0,
+                false => g[i],
+            },
+        )
+        .collect()
+}
+
+fn clamp(x: &[f64], bounds: &[(f64, f64)]) -> Vec<f64> {
+    (x.iter().zip(bounds))
+        .map(|(&x, &(lo, hi))| x.max(lo).min(hi))
+        .collect()
+}
+
+fn identity(n: usize) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn norm(a: &[f64]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn difference(a: &[f64], b: &[f64]) -> Vec<f64> {
+    a.iter().zip(b).map(|(a, b)| a - b).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FREE: [(f64, f64); 2] = [(f64::NEG_INFINITY, f64::INFINITY); 2];
+
+    fn rosenbrock(x: &[f64]) -> Result<f64, String> {
+        Ok((1.0 - x[0]).powi(2) + 100.0 * (x[1] - x[0] * x[0]).powi(2))
+    }
+
+    fn rosenbrock_gradient(x: &[f64]) -> Result<Vec<f64>, String> {
+        let inner = x[1] - x[0] * x[0];
+        Ok(vec![
+            -2.0 * (1.0 - x[0]) - 400.0 * x[0] * inner,
+            200.0 * inner,
+        ])
+    }
+
+    fn assert_near(minimum: &Minimum, point: &[f64], tolerance: f64) {
+        assert!(minimum.converged, "{minimum:?}");
+        assert!(
+            (minimum.point.iter().zip(point)).all(|(a, b)| (a - b).abs() < tolerance),
+            "{minimum:?}, expected {point:?}"
+        );
+    }
+
+    #[test]
+    fn minimizes_the_rosenbrock_valley() {
+        let start = [-1.2, 1.0];
+        let minimum = bfgs(rosenbrock, rosenbrock_gradient, &start, &FREE).unwrap();
+        assert_near(&minimum, &[1.0, 1.0], 1e-5);
+        assert!(minimum.iterations < 100);
+
+        let minimum = nelder_mead(rosenbrock, &start, &FREE).unwrap();
+        assert_near(&minimum, &[1.0, 1.0], 1e-3);
+    }
+
+    #[test]
+    fn keeps_within_bounds() {
+        // (x - 3)^2 + (y + 1)^2 is lowest at (3, -1), outside the box
+        let f = |x: &[f64]| Ok((x[0] - 3.0).powi(2) + (x[1] + 1.0).powi(2));
+        let gradient = |x: &[f64]| Ok(vec![2.0 * (x[0] - 3.0), 2.0 * (x[1] + 1.0)]);
+        let bounds = [(0.0, 1.0), (0.0, 1.0)];
+
+        let minimum = bfgs(f, gradient, &[0.5, 0.5], &bounds).unwrap();
+        assert_near(&minimum, &[1.0, 0.0], 1e-9);
+        assert_eq!(minimum.value, 5.0);
+
+        let minimum = nelder_mead(f, &[0.5, 0.5], &bounds).unwrap();
+        assert_near(&minimum, &[1.0, 0.0], 1e-4);
+    }
+
+    #[test]
+    fn reports_unbounded_objectives() {
+        // x + y^2 falls without end as x does
+        let f = |x: &[f64]| Ok(x[0] + x[1] * x[1]);
+        let gradient = |x: &[f64]| Ok(vec![1.0, 2.0 * x[1]]);
+
+        let minimum = bfgs(f, gradient, &[0.0, 1.0], &FREE).unwrap();
+        assert!(minimum.unbounded, "{minimum:?}");
+        let minimum = nelder_mead(f, &[0.0, 1.0], &FREE).unwrap();
+        assert!(minimum.unbounded, "{minimum:?}");
+
+        let bounded = [(-1.0, 1.0), (f64::NEG_INFINITY, f64::INFINITY)];
+        let minimum = bfgs(f, gradient, &[0.0, 1.0], &bounded).unwrap();
+        assert_near(&minimum, &[-1.0, 0.0], 1e-6);
+    }
+}
@@ -0,0 +1,369 @@
+pub mod interval;
+pub mod linear;
+pub mod minimize;
+pub mod modular;
+
+use crate::evaluator::{Environment, Value, eval_in};
+use crate::parser::{Expression, Operator};
+use crate::units::{Dimension, exponent_in_range};
+
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f32 = 1e-6;
+const STARTING_POINTS: [f32; 10] = [1.0, 0.0, -1.0, 10.0, -10.0, 100.0, -100.0, 1e3, -1e3, 1e4];
+
+// Identifiers of the expression that have no value in the environment, in order of appearance
+pub fn unknowns(expr: &Expression, env: &Environment) -> Vec<String> {
+    let mut names = Vec::new();
+    for name in expr.identifiers() {
+        if env.get(&name).is_none() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+// Newton's method runs in IEEE mode, which would turn 1/0 in x = 1/0 into a failed search,
+// so whatever does not involve the unknown is evaluated once beforehand to report its error
+fn check_known_parts(expr: &Expression, unknown: &str, env: &Environment) -> Result<(), String> {
+    if !expr.identifiers().iter().any(|name| name == unknown) {
+        return eval_in(expr.clone(), env).map(drop).map_err(String::from);
+    }
+    expr.clone().map_children(|child| {
+        check_known_parts(&child, unknown, env)?;
+        Ok(child)
+    })?;
+    Ok(())
+}
+
+// Solves lhs = rhs for a single unknown, which takes whatever unit makes the equation consistent.
+// Roots outside what is assumed about the unknown are passed over for the next starting point
+pub fn solve_equation(
+    lhs: &Expression,
+    rhs: &Expression,
+    unknown: &str,
+    env: &Environment,
+) -> Result<Value, String> {
+    check_known_parts(lhs, unknown, env)?;
+    check_known_parts(rhs, unknown, env)?;
+    let dim = unknown_dimension(lhs, rhs, unknown, env)?;
+    let domain = env.domain(unknown).copied().unwrap_or_default();
+    // Newton's method gives up on a NaN step by itself, so stepping outside the domain
+    // only ends that start rather than the whole search
+    let mut env = env.clone();
+    env.set_ieee(true);
+
+    // Both sides as floats, since an exact integer such as 2^70 would make the difference
+    // an integer operation that f32 steps cannot get close enough to
+    let mut sides = |t: f32| -> Result<(f32, f32), String> {
+        env.set(unknown.to_string(), Value::quantity(t, dim));
+        let l = eval_in(lhs.clone(), &env)?.float();
+        let r = eval_in(rhs.clone(), &env)?.float();
+        Ok((l.scalar()?, r.scalar()?))
+    };
+
+    // Starting just inside a bound finds roots far from the usual starting points
+    let starts = STARTING_POINTS
+        .into_iter()
+        .chain(domain.lower.map(|b| b.value + 1.0))
+        .chain(domain.upper.map(|b| b.value - 1.0));
+
+    for start in starts {
+        let Some(mut t) = newton(&mut sides, start)? else {
+            continue;
+        };
+        // An integer unknown takes the nearest whole number, if that still solves the equation
+        if domain.integer {
+            let (l, r) = sides(t.round())?;
+            if (l - r).abs() > 1e-3 * (l.abs() + r.abs()).max(1.0) {
+                continue;
+            }
+            t = t.round();
+        }
+        if domain.contains(t) {
+            return Ok(Value::quantity(t, dim));
+        }
+    }
+
+    match env.domain(unknown) {
+        Some(domain) => Err(format!(
+            "could not find a solution with {}",
+            domain.describe(unknown)
+        )),
+        None => Err(format!("could not find a solution for {unknown}")),
+    }
+}
+
+// Newton's method on the two sides of an equation, with a numerical derivative. None if it
+// does not converge from this start
+fn newton(
+    f: &mut impl FnMut(f32) -> Result<(f32, f32), String>,
+    start: f32,
+) -> Result<Option<f32>, String> {
+    let mut t = start;
+
+    for _ in 0..MAX_ITERATIONS {
+        let (l, r) = f(t)?;
+        let (y, scale) = (l - r, l.abs() + r.abs());
+        if y == 0.0 {
+            return Ok(Some(t));
+        }
+
+        // Each side changes on its own, so a large constant on the other one cannot drown
+        // the change out, as 1e21 would in 3x - 1e21
+        let h = 1e-3 * t.abs().max(1.0);
+        let ((l_up, r_up), (l_down, r_down)) = (f(t + h)?, f(t - h)?);
+        let slope = ((l_up - l_down) - (r_up - r_down)) / (2.0 * h);
+        let next = t - y / slope;
+        if !next.is_finite() {
+            return Ok(None);
+        }
+
+        // Once the steps stop shrinking the root is as precise as f32 allows
+        if (next - t).abs() <= TOLERANCE * t.abs().max(1.0) {
+            return Ok((y.abs() <= 1e-3 * scale.max(1.0)).then_some(next));
+        }
+        t = next;
+    }
+
+    Ok(None)
+}
+
+// The dimension of an expression as power * D + base, where D is the unknown's dimension
+#[derive(Clone, Copy, Default)]
+struct Affine {
+    power: f32,
+    base: [f32; 5],
+}
+
+impl Affine {
+    fn from_dimension(dim: Dimension) -> Self {
+        Self {
+            power: 0.0,
+            base: dim.0.map(|e| e as f32),
+        }
+    }
+
+    fn combine(self, other: Affine, sign: f32) -> Self {
+        Self {
+            power: self.power + sign * other.power,
+            base: std::array::from_fn(|i| self.base[i] + sign * other.base[i]),
+        }
+    }
+
+    fn scale(self, k: f32) -> Self {
+        Self {
+            power: self.power * k,
+            base: self.base.map(|e| e * k),
+        }
+    }
+}
+
+// Each constraint reads power * D = base
+type Constraint = (f32, [f32; 5]);
+
+fn unknown_dimension(
+    lhs: &Expression,
+    rhs: &Expression,
+    unknown: &str,
+    env: &Environment,
+) -> Result<Dimension, String> {
+    let mut constraints = Vec::new();
+    let l = infer(lhs, unknown, env, &mut constraints);
+    let r = infer(rhs, unknown, env, &mut constraints);
+    constraints.push(equal(l, r));
+
+    let dim = match constraints.iter().find(|(power, _)| *power != 0.0) {
+        Some((power, base)) => base.map(|e| e / power),
+        None => [0.0; 5],
+    };
+
+    let consistent = constraints
+        .iter()
+        .all(|(power, base)| (0..5).all(|i| (power * dim[i] - base[i]).abs() < 1e-4));
+    if !consistent || dim.iter().any(|e| e.fract() != 0.0) {
+        return Err(String::from(
+            "dimension mismatch: the equation has no consistent units",
+        ));
+    }
+
+    let mut exponents = [0; 5];
+    for (exponent, e) in exponents.iter_mut().zip(dim) {
+        *exponent = exponent_in_range(e)?;
+    }
+    Ok(Dimension(exponents))
+}
+
+// Both sides must have the same dimension
+fn equal(a: Affine, b: Affine) -> Constraint {
+    let diff = a.combine(b, -1.0);
+    (diff.power, diff.base.map(|e| -e))
+}
+
+fn infer(
+    expr: &Expression,
+    unknown: &str,
+    env: &Environment,
+    constraints: &mut Vec<Constraint>,
+) -> Affine {
+    let contains_unknown = expr.identifiers().iter().any(|name| name == unknown);
+
+    match expr {
+        Expression::Ident(name) if name == unknown => Affine {
+            power: 1.0,
+            base: [0.0; 5],
+        },
+
+        Expression::Binary { lhs, op, rhs } if contains_unknown => {
+            let l = infer(lhs, unknown, env, constraints);
+            match op {
+                Operator::Add | Operator::Sub | Operator::Rem => {
+                    let r = infer(rhs, unknown, env, constraints);
+                    constraints.push(equal(l, r));
+                    l
+                }
+                Operator::Mult | Operator::ElemMult => {
+                    l.combine(infer(rhs, unknown, env, constraints), 1.0)
+                }
+                Operator::Div => l.combine(infer(rhs, unknown, env, constraints), -1.0),
+                Operator::Pow => match eval_in(*rhs.clone(), env)
+                    .map_err(String::from)
+                    .and_then(|v| v.as_number())
+                {
+                    Ok(k) => l.scale(k),
+                    // An unknown exponent needs a dimensionless base
+                    Err(_) => {
+                        constraints.push(equal(l, Affine::default()));
+                        Affine::default()
+                    }
+                },
+            }
+        }
+
+        Expression::Unary { rhs, .. } if contains_unknown => infer(rhs, unknown, env, constraints),
+        Expression::Convert { expr, .. } if contains_unknown => {
+            infer(expr, unknown, env, constraints)
+        }
+
+        // Anything else with the unknown inside, such as a function call, is taken as dimensionless
+        _ if contains_unknown => Affine::default(),
+
+        _ => match eval_in(expr.clone(), env) {
+            Ok(value) => Affine::from_dimension(value.dimension()),
+            Err(_) => Affine::default(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::Domain;
+    use crate::lexer;
+    use crate::parser::parse_expression;
+
+    fn parse(input: &str) -> Expression {
+        let tokens = lexer::tokenize(input.to_string()).unwrap();
+        parse_expression(&mut tokens.into_iter().peekable()).unwrap()
+    }
+
+    #[test]
+    fn solves_linear_equation() {
+        let env = Environment::default();
+        let value = solve_equation(&parse("2 * x + 1"), &parse("7"), "x", &env);
+
+        assert_eq!(value.unwrap(), Value::Number(3.0));
+    }
+
+    #[test]
+    fn solves_against_large_constants() {
+        let env = Environment::default();
+        let solve = |rhs: &str| solve_equation(&parse("3x"), &parse(rhs), "x", &env).unwrap();
+        let close =
+            |value: Value, expected: f32| (value.scalar().unwrap() / expected - 1.0).abs() < 1e-6;
+
+        assert!(close(solve("2^70"), 2f32.powi(70) / 3.0));
+        assert!(close(solve("1e21"), 1e21 / 3.0));
+    }
+
+    #[test]
+    fn solution_carries_units() {
+        let env = Environment::default();
+        let value = solve_equation(&parse("5 kN"), &parse("m * 10 m/s^2"), "m", &env).unwrap();
+
+        assert_eq!(
+            value.dimension(),
+            crate::units::parse_unit("kg").unwrap().dim
+        );
+        assert_eq!(value.scalar().unwrap(), 500.0);
+    }
+
+    #[test]
+    fn rejects_inconsistent_units() {
+        let env = Environment::default();
+        let value = solve_equation(&parse("x + 2 m"), &parse("3 s"), "x", &env);
+
+        assert!(value.is_err());
+    }
+
+    #[test]
+    fn reports_errors_outside_the_unknown() {
+        let env = Environment::default();
+        let error = |lhs: &str, rhs: &str| solve_equation(&parse(lhs), &parse(rhs), "x", &env);
+
+        assert_eq!(error("x", "1/0").unwrap_err(), "division by zero in 1/0");
+        assert_eq!(
+            error("x + 0/0", "2").unwrap_err(),
+            "0/0 is undefined, its result is not a number"
+        );
+        assert_eq!(
+            error("x * (1/0 + 1)", "2").unwrap_err(),
+            "division by zero in 1/0"
+        );
+        assert_eq!(error("x / 2", "3/2").unwrap(), Value::Number(3.0));
+    }
+
+    #[test]
+    fn encloses_every_root() {
+        let env = Environment::default();
+        let domain = crate::evaluator::Interval::new(-3.0, 3.0).unwrap();
+        let roots =
+            interval::find_roots(&parse("x^2 - 2"), &parse("0"), "x", domain, &env).unwrap();
+
+        assert_eq!(roots.len(), 2);
+        assert!(roots[0].enclosure.contains(-2f32.sqrt()));
+        assert!(roots[1].enclosure.contains(2f32.sqrt()));
+        assert!(roots.iter().all(|root| root.verified));
+    }
+
+    #[test]
+    fn reports_no_roots() {
+        let env = Environment::default();
+        let domain = crate::evaluator::Interval::new(-3.0, 3.0).unwrap();
+        let roots =
+            interval::find_roots(&parse("x^2 + 1"), &parse("0"), "x", domain, &env).unwrap();
+
+        assert!(roots.is_empty());
+    }
+
+    #[test]
+    fn finds_unknowns() {
+        let mut env = Environment::default();
+        env.set("a".into(), Value::Number(1.0));
+
+        assert_eq!(unknowns(&parse("a * x + y * x"), &env), vec!["x", "y"]);
+    }
+
+    #[test]
+    fn keeps_to_assumed_domain() {
+        let mut env = Environment::default();
+        let solve = |env: &Environment| solve_equation(&parse("x^2"), &parse("4"), "x", env);
+        assert_eq!(solve(&env).unwrap(), Value::Number(2.0));
+
+        env.assume("x".into(), Domain::below(0.0, true));
+        assert_eq!(solve(&env).unwrap(), Value::Number(-2.0));
+
+        env.assume("x".into(), Domain::integers());
+        let value = solve_equation(&parse("x^3"), &parse("27.5"), "x", &env);
+        assert_eq!(value.unwrap_err(), "could not find a solution with x in Z");
+    }
+}
@@ -0,0 +1,278 @@
+use crate::evaluator::integer::{chinese_remainder, extended_gcd};
+use crate::evaluator::{BigInt, Environment, partial};
+use crate::parser::{Expression, Operator};
+use crate::polynomial::{Polynomial, Rational};
+use std::fmt;
+
+// Congruences above degree 1 are solved by trying every residue, up to this modulus
+const MAX_SEARCH: i128 = 1_000_000;
+
+// x ≡ r (mod modulus) for each r in residues, none of them when there is no solution
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classes {
+    pub residues: Vec<BigInt>,
+    pub modulus: BigInt,
+}
+
+impl Classes {
+    // The integers that meet both sets of classes, each pair joined by the remainder theorem
+    pub fn intersect(&self, other: &Classes) -> Classes {
+        let mut residues = Vec::new();
+        let mut modulus = self.modulus.clone();
+        for a in &self.residues {
+            for b in &other.residues {
+                if let Some((r, m)) = chinese_remainder((a, &self.modulus), (b, &other.modulus)) {
+                    residues.push(r);
+                    modulus = m;
+                }
+            }
+        }
+        residues.sort();
+        residues.dedup();
+        Classes { residues, modulus }
+    }
+}
+
+impl fmt::Display for Classes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let residues: Vec<String> = self.residues.iter().map(|r| r.to_string()).collect();
+        write!(f, "{} (mod {})", residues.join(", "), self.modulus)
+    }
+}
+
+// The classes of unknown with lhs ≡ rhs (mod modulus), where every other name is known. The
+// linear ax + b ≡ 0 has its solutions in one class mod m/gcd(a, m), higher degrees are searched
+pub fn solve_congruence(
+    lhs: &Expression,
+    rhs: &Expression,
+    modulus: &BigInt,
+    unknown: &str,
+    env: &Environment,
+) -> Result<Classes, String> {
+    let m = modulus.clone().abs();
+    if m.is_zero() {
+        return Err(String::from("a congruence needs a modulus other than 0"));
+    }
+
+    let p = integer_polynomial(lhs, rhs, env)?;
+    if let Some(other) = p.variables().into_iter().find(|v| v != unknown) {
+        return Err(format!("cannot solve for both {unknown} and {other}"));
+    }
+    let coefficients: Vec<BigInt> = (0..=p.degree_in(unknown))
+        .map(|k| coefficient(&p.coefficient_in(unknown, k)))
+        .collect();
+
+    if let [b, rest @ ..] = coefficients.as_slice()
+        && rest.len() <= 1
+    {
+        let a = rest.first().cloned().unwrap_or(BigInt::zero());
+        return Ok(solve_linear(&a, &-b.clone(), &m));
+    }
+
+    let small = m.to_i128().filter(|&m| m <= MAX_SEARCH).ok_or(format!(
+        "congruences of degree {} are only solved up to mod {MAX_SEARCH}",
+        coefficients.len() - 1
+    ))?;
+    let reduced: Vec<i128> = coefficients
+        .iter()
+        .map(|c| {
+            c.rem_euclid(&m)
+                .to_i128()
+                .expect("reduced below the modulus")
+        })
+        .collect();
+    let residues = (0..small)
+        .filter(|&x| reduced.iter().rev().fold(0, |acc, c| (acc * x + c) % small) == 0)
+        .map(BigInt::from)
+        .collect();
+    Ok(Classes {
+        residues,
+        modulus: m,
+    })
+}
+
+// ax ≡ b (mod m), solvable when gcd(a, m) divides b
+fn solve_linear(a: &BigInt, b: &BigInt, m: &BigInt) -> Classes {
+    let (g, _, _) = extended_gcd(&a.rem_euclid(m), m);
+    let n = m.div_rem(&g).0;
+    let (k, r) = b.div_rem(&g);
+    if !r.is_zero() {
+        return Classes {
+            residues: Vec::new(),
+            modulus: n,
+        };
+    }
+
+    let inverse = a
+        .div_rem(&g)
+        .0
+        .mod_inverse(&n)
+        .expect("a/g and m/g are coprime");
+    Classes {
+        residues: vec![(k * inverse).rem_euclid(&n)],
+        modulus: n,
+    }
+}
+
+// Every integer solution of a linear equation in one or two unknowns, as each unknown in
+// terms of a free integer parameter. None when there is no integer solution
+pub fn solve_diophantine(
+    lhs: &Expression,
+    rhs: &Expression,
+    env: &Environment,
+) -> Result<Option<Vec<(String, Polynomial)>>, String> {
+    let p = integer_polynomial(lhs, rhs, env)?;
+    if p.total_degree() > 1 {
+        return Err(String::from(
+            "only linear equations are solved over the integers",
+        ));
+    }
+
+    // ax + by = c
+    let unknowns: Vec<String> = p.variables().into_iter().collect();
+    let c = -p
+        .terms()
+        .find(|(m, _)| m.degree() == 0)
+        .map_or(BigInt::zero(), |(_, c)| c.numer().clone());
+    let coefficient_of = |x: &str| coefficient(&p.coefficient_in(x, 1));
+
+    match unknowns.as_slice() {
+        [] => Err(String::from(
+            "nothing to solve for, every name in the equation is known",
+        )),
+        [x] => {
+            let (q, r) = c.div_rem(&coefficient_of(x));
+            Ok(r.is_zero().then(|| vec![(x.clone(), integer(q))]))
+        }
+        [x, y] => {
+            let (a, b) = (coefficient_of(x), coefficient_of(y));
+            let (g, _, _) = extended_gcd(&a, &b);
+            let (k, r) = c.div_rem(&g);
+            if !r.is_zero() {
+                return Ok(None);
+            }
+
+            // The smallest x0 >= 0 of the solutions, then y0 from it
+            let (a, b) = (a.div_rem(&g).0, b.div_rem(&g).0);
+            let period = b.clone().abs();
+            let inverse = a.mod_inverse(&period).expect("a/g and b/g are coprime");
+            let x0 = (k.clone() * inverse).rem_euclid(&period);
+            let y0 = (k - a.clone() * x0.clone()).div_rem(&b).0;
+            let slope = if b.is_negative() { -a } else { a };
+
+            let t = ["t", "k", "n"]
+                .into_iter()
+                .find(|t| !unknowns.iter().any(|u| u == t))
+                .expect("two unknowns leave one of three names free");
+            let step = |c: BigInt| Polynomial::var(t).scale(Rational::integer(c));
+            Ok(Some(vec![
+                (x.clone(), integer(x0) + step(period)),
+                (y.clone(), integer(y0) - step(slope)),
+            ]))
+        }
+        _ => Err(format!(
+            "only one or two unknowns are solved over the integers, got {}",
+            unknowns.join(", ")
+        )),
+    }
+}
+
+// lhs - rhs with the known names filled in, which must leave whole coefficients
+fn integer_polynomial(
+    lhs: &Expression,
+    rhs: &Expression,
+    env: &Environment,
+) -> Result<Polynomial, String> {
+    let difference = Expression::Binary {
+        lhs: Box::new(lhs.clone()),
+        op: Operator::Sub,
+        rhs: Box::new(rhs.clone()),
+    };
+    let p = Polynomial::from_expression(&partial::partial_eval(difference, env)?)?;
+    if let Some((_, c)) = p.terms().find(|(_, c)| !c.is_integer()) {
+        return Err(format!("expected whole coefficients, got {c}"));
+    }
+    Ok(p)
+}
+
+fn coefficient(p: &Polynomial) -> BigInt {
+    let c = p
+        .constant_value()
+        .expect("a coefficient of a single unknown");
+    c.numer().clone()
+}
+
+fn integer(n: BigInt) -> Polynomial {
+    Polynomial::constant(Rational::integer(n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+    use crate::parser::parse_expression;
+
+    fn expr(input: &str) -> Expression {
+        let tokens = lexer::tokenize(input.to_string()).unwrap();
+        parse_expression(&mut tokens.into_iter().peekable()).unwrap()
+    }
+
+    fn classes(lhs: &str, rhs: &str, m: i128) -> String {
+        let env = Environment::default();
+        solve_congruence(&expr(lhs), &expr(rhs), &BigInt::from(m), "x", &env)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn solves_congruences() {
+        assert_eq!(classes("3x + 2", "5", 11), "1 (mod 11)");
+        assert_eq!(classes("6x", "4", 10), "4 (mod 5)");
+        let env = Environment::default();
+        let none = solve_congruence(&expr("6x"), &expr("3"), &BigInt::from(10), "x", &env);
+        assert!(none.unwrap().residues.is_empty());
+        assert_eq!(classes("x^2", "1", 8), "1, 3, 5, 7 (mod 8)");
+        assert_eq!(classes("x^2", "-1", 13), "5, 8 (mod 13)");
+
+        let system = Classes {
+            residues: vec![BigInt::from(2)],
+            modulus: BigInt::from(3),
+        }
+        .intersect(&Classes {
+            residues: vec![BigInt::from(1), BigInt::from(4)],
+            modulus: BigInt::from(5),
+        });
+        assert_eq!(system.to_string(), "11, 14 (mod 15)");
+    }
+
+    #[test]
+    fn solves_linear_diophantine_equations() {
+        let solve = |lhs: &str, rhs: &str| {
+            let env = Environment::default();
+            solve_diophantine(&expr(lhs), &expr(rhs), &env)
+                .unwrap()
+                .map(|solutions| {
+                    let solutions: Vec<String> = solutions
+                        .iter()
+                        .map(|(name, p)| format!("{name} = {p}"))
+                        .collect();
+                    solutions.join(", ")
+                })
+        };
+
+        assert_eq!(
+            solve("3x + 5y", "1").as_deref(),
+            Some("x = 5t + 2, y = -3t - 1")
+        );
+        assert_eq!(
+            solve("6x - 4y", "2").as_deref(),
+            Some("x = 2t + 1, y = 3t + 1")
+        );
+        assert_eq!(solve("6x + 4y", "3"), None);
+        assert_eq!(solve("4x", "12").as_deref(), Some("x = 3"));
+        assert_eq!(
+            solve("3t + 2k", "1").as_deref(),
+            Some("k = 3n + 2, t = -2n - 1")
+        );
+    }
+}
@@ -0,0 +1,216 @@
+// More rows than this is almost certainly a step that was meant to be larger
+const MAX_ROWS: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Format {
+    #[default]
+    Text,
+    Csv,
+}
+
+// One parameter stepped from `from` to `to`, both included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sweep {
+    pub name: String,
+    pub from: f32,
+    pub to: f32,
+    pub step: f32,
+}
+
+impl Sweep {
+    pub fn new(name: String, from: f32, to: f32, step: f32) -> Result<Self, String> {
+        if !(from.is_finite() && to.is_finite()) || from > to {
+            return Err(format!("cannot sweep {name} from {from} to {to}"));
+        }
+        if !step.is_finite() || step <= 0.0 {
+            return Err(format!("the step for {name} must be positive, got {step}"));
+        }
+        if (to - from) / step >= MAX_ROWS as f32 {
+            return Err(format!(
+                "sweeping {name} from {from} to {to} in steps of {step} gives more than {MAX_ROWS} rows"
+            ));
+        }
+
+        Ok(Self {
+            name,
+            from,
+            to,
+            step,
+        })
+    }
+
+    // x=0:10:0.5, the step defaults to 1
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid sweep '{spec}', expected name=from:to:step");
+
+        let (name, range) = spec.split_once('=').ok_or_else(invalid)?;
+        let bounds = range
+            .split(':')
+            .map(|part| part.trim().parse::<f32>().map_err(|_| invalid()))
+            .collect::<Result<Vec<f32>, String>>()?;
+
+        match bounds.as_slice() {
+            [from, to] => Self::new(name.trim().to_string(), *from, *to, 1.0),
+            [from, to, step] => Self::new(name.trim().to_string(), *from, *to, *step),
+            _ => Err(invalid()),
+        }
+    }
+
+    // Multiplying rather than adding up the steps keeps rounding errors from piling up, and
+    // rounding to as many decimals as the start and step have drops what is left, so steps
+    // of 0.1 reach 0.9 rather than 0.90000004
+    pub fn values(&self) -> Vec<f32> {
+        let count = ((self.to - self.from) / self.step + 1e-4).floor() as usize;
+        let scale = 10f64.powi(decimals(self.from).max(decimals(self.step)) as i32);
+        (0..=count)
+            .map(|i| self.from as f64 + i as f64 * self.step as f64)
+            .map(|x| ((x * scale).round() / scale) as f32)
+            .collect()
+    }
+}
+
+// Digits after the point in the shortest decimal that prints as x
+fn decimals(x: f32) -> usize {
+    x.to_string()
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: Vec<String>) -> Self {
+        Self {
+            headers,
+            rows: Vec::new(),
+        }
+    }
+
+    // Adds a column to every row so far, for outputs that only show up partway through a sweep
+    pub fn column(&mut self, header: &str) -> usize {
+        match self.headers.iter().position(|h| h == header) {
+            Some(i) => i,
+            None => {
+                self.headers.push(header.to_string());
+                self.headers.len() - 1
+            }
+        }
+    }
+
+    pub fn push_row(&mut self, mut row: Vec<String>) {
+        row.resize(self.headers.len(), String::new());
+        self.rows.push(row);
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Text => self.render_text(),
+            Format::Csv => self.render_csv(),
+        }
+    }
+
+    // Columns right-aligned so the numbers line up
+    fn render_text(&self) -> String {
+        let cell = |row: &[String], i: usize| row.get(i).cloned().unwrap_or_default();
+        let widths: Vec<usize> = (0..self.headers.len())
+            .map(|i| {
+                self.rows
+                    .iter()
+                    .map(|row| cell(row, i).chars().count())
+                    .chain([self.headers[i].chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let line = |row: &[String]| {
+            widths
+                .iter()
+                .enumerate()
+                .map(|(i, &w)| format!("{:>w$}", cell(row, i)))
+                .collect::<Vec<String>>()
+                .join("  ")
+        };
+
+        let rule = widths
+            .iter()
+            .map(|&w| "-".repeat(w))
+            .collect::<Vec<String>>()
+            .join("  ");
+
+        let mut lines = vec![line(&self.headers), rule];
+        lines.extend(self.rows.iter().map(|row| line(row)));
+        lines.join("\n")
+    }
+
+    fn render_csv(&self) -> String {
+        let line = |row: &[String]| {
+            (0..self.headers.len())
+                .map(|i| csv_field(row.get(i).map_or("", |s| s.as_str())))
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+
+        let mut lines = vec![line(&self.headers)];
+        lines.extend(self.rows.iter().map(|row| line(row)));
+        lines.join("\n")
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_includes_both_ends() {
+        let sweep = Sweep::parse("x=0:1:0.25").unwrap();
+
+        assert_eq!(sweep.values(), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(Sweep::parse("t=1:3").unwrap().values(), vec![1.0, 2.0, 3.0]);
+        assert_eq!(Sweep::parse("x=0:1:0.1").unwrap().values().len(), 11);
+        let tenths = Sweep::parse("x=0:1:0.1").unwrap().values();
+        assert_eq!(tenths[9].to_string(), "0.9");
+        assert_eq!(Sweep::parse("x=-0.3:0.3:0.15").unwrap().values()[3], 0.15);
+    }
+
+    #[test]
+    fn rejects_invalid_sweeps() {
+        assert!(Sweep::parse("x=0:10:0").is_err());
+        assert!(Sweep::parse("x=10:0:1").is_err());
+        assert!(Sweep::parse("x=0").is_err());
+        assert!(Sweep::parse("0:10").is_err());
+    }
+
+    #[test]
+    fn renders_aligned_text_and_csv() {
+        let mut table = Table::new(vec!["x".into(), "x^2".into()]);
+        table.push_row(vec!["1".into(), "1".into()]);
+        table.push_row(vec!["10".into(), "100".into()]);
+
+        assert_eq!(
+            table.render(Format::Text),
+            " x  x^2\n--  ---\n 1    1\n10  100"
+        );
+        assert_eq!(table.render(Format::Csv), "x,x^2\n1,1\n10,100");
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        let mut table = Table::new(vec!["v".into()]);
+        table.push_row(vec!["[1, 2]".into()]);
+
+        assert_eq!(table.render(Format::Csv), "v\n\"[1, 2]\"");
+    }
+}
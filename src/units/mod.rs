@@ -0,0 +1,268 @@
+use std::fmt;
+
+// Exponents of the SI base units: metre, kilogram, second, ampere, kelvin
+const BASE_SYMBOLS: [&str; 5] = ["m", "kg", "s", "A", "K"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Dimension(pub [i8; 5]);
+
+impl Dimension {
+    pub fn is_dimensionless(&self) -> bool {
+        self.0.iter().all(|&e| e == 0)
+    }
+
+    // Fractional powers are only allowed when every exponent stays whole, e.g. sqrt of m^2
+    pub fn pow(self, exponent: f32) -> Result<Dimension, String> {
+        let mut result = [0; 5];
+        for (i, &e) in self.0.iter().enumerate() {
+            let scaled = e as f32 * exponent;
+            if scaled.fract() != 0.0 {
+                return Err(format!("cannot raise {self} to the power {exponent}"));
+            }
+            result[i] = exponent_in_range(scaled)?;
+        }
+        Ok(Dimension(result))
+    }
+
+    pub fn checked_mul(self, other: Dimension) -> Result<Dimension, String> {
+        self.combine(other, i8::checked_add)
+    }
+
+    pub fn checked_div(self, other: Dimension) -> Result<Dimension, String> {
+        self.combine(other, i8::checked_sub)
+    }
+
+    fn combine(self, other: Dimension, op: fn(i8, i8) -> Option<i8>) -> Result<Dimension, String> {
+        let mut result = [0; 5];
+        for (e, (a, b)) in result.iter_mut().zip(self.0.into_iter().zip(other.0)) {
+            *e = op(a, b).ok_or(OUT_OF_RANGE)?;
+        }
+        Ok(Dimension(result))
+    }
+}
+
+const OUT_OF_RANGE: &str = "unit exponent out of range";
+
+// A whole exponent that fits the i8 each base unit keeps
+pub fn exponent_in_range(e: f32) -> Result<i8, String> {
+    match e >= i8::MIN as f32 && e <= i8::MAX as f32 {
+        true => Ok(e as i8),
+        false => Err(String::from(OUT_OF_RANGE)),
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(unit) = UNITS
+            .iter()
+            .find(|u| u.derived && u.factor == 1.0 && u.dim == self.0)
+        {
+            return write!(f, "{}", unit.name);
+        }
+
+        let term = |i: usize, e: i8| match e.abs() {
+            1 => BASE_SYMBOLS[i].to_string(),
+            e => format!("{}^{}", BASE_SYMBOLS[i], e),
+        };
+        let num: Vec<String> = (0..5)
+            .filter(|&i| self.0[i] > 0)
+            .map(|i| term(i, self.0[i]))
+            .collect();
+        let den: Vec<String> = (0..5)
+            .filter(|&i| self.0[i] < 0)
+            .map(|i| term(i, self.0[i]))
+            .collect();
+
+        let num = if num.is_empty() {
+            String::from("1")
+        } else {
+            num.join("*")
+        };
+        match den.len() {
+            0 => write!(f, "{num}"),
+            _ => write!(f, "{num}/{}", den.join("/")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unit {
+    pub factor: f32,
+    pub dim: Dimension,
+}
+
+// A value in SI base units, optionally displayed in the unit it was converted to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f32,
+    pub dim: Dimension,
+    pub display: Option<String>,
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.display.as_ref().map(|name| (name, parse_unit(name))) {
+            Some((name, Ok(unit))) => write!(f, "{} {}", self.value / unit.factor, name),
+            _ => write!(f, "{} {}", self.value, self.dim),
+        }
+    }
+}
+
+struct UnitDef {
+    name: &'static str,
+    factor: f32,
+    dim: [i8; 5],
+    // Named combinations of base units, preferred when printing
+    derived: bool,
+}
+
+const fn unit(name: &'static str, factor: f32, dim: [i8; 5]) -> UnitDef {
+    UnitDef {
+        name,
+        factor,
+        dim,
+        derived: false,
+    }
+}
+
+const fn derived(name: &'static str, dim: [i8; 5]) -> UnitDef {
+    UnitDef {
+        name,
+        factor: 1.0,
+        dim,
+        derived: true,
+    }
+}
+
+const LENGTH: [i8; 5] = [1, 0, 0, 0, 0];
+const MASS: [i8; 5] = [0, 1, 0, 0, 0];
+const TIME: [i8; 5] = [0, 0, 1, 0, 0];
+const FORCE: [i8; 5] = [1, 1, -2, 0, 0];
+const ENERGY: [i8; 5] = [2, 1, -2, 0, 0];
+const POWER: [i8; 5] = [2, 1, -3, 0, 0];
+const PRESSURE: [i8; 5] = [-1, 1, -2, 0, 0];
+
+const UNITS: &[UnitDef] = &[
+    unit("m", 1.0, LENGTH),
+    unit("km", 1e3, LENGTH),
+    unit("cm", 1e-2, LENGTH),
+    unit("mm", 1e-3, LENGTH),
+    unit("ft", 0.3048, LENGTH),
+    unit("inch", 0.0254, LENGTH),
+    unit("yd", 0.9144, LENGTH),
+    unit("mi", 1609.344, LENGTH),
+    unit("kg", 1.0, MASS),
+    unit("g", 1e-3, MASS),
+    unit("mg", 1e-6, MASS),
+    unit("lb", 0.45359237, MASS),
+    unit("s", 1.0, TIME),
+    unit("ms", 1e-3, TIME),
+    unit("min", 60.0, TIME),
+    unit("h", 3600.0, TIME),
+    unit("L", 1e-3, [3, 0, 0, 0, 0]),
+    unit("K", 1.0, [0, 0, 0, 0, 1]),
+    derived("N", FORCE),
+    unit("kN", 1e3, FORCE),
+    unit("lbf", 4.448222, FORCE),
+    derived("J", ENERGY),
+    unit("kJ", 1e3, ENERGY),
+    derived("W", POWER),
+    unit("kW", 1e3, POWER),
+    derived("Pa", PRESSURE),
+    unit("kPa", 1e3, PRESSURE),
+    unit("bar", 1e5, PRESSURE),
+    unit("Hz", 1.0, [0, 0, -1, 0, 0]),
+];
+
+pub fn is_unit(name: &str) -> bool {
+    UNITS.iter().any(|u| u.name == name)
+}
+
+fn lookup(name: &str) -> Result<Unit, String> {
+    UNITS
+        .iter()
+        .find(|u| u.name == name)
+        .map(|u| Unit {
+            factor: u.factor,
+            dim: Dimension(u.dim),
+        })
+        .ok_or(format!("unknown unit '{name}'"))
+}
+
+// Parses unit expressions such as "kN", "m/s^2" or "kg*m^2/s^2"
+pub fn parse_unit(text: &str) -> Result<Unit, String> {
+    let mut result = Unit {
+        factor: 1.0,
+        dim: Dimension::default(),
+    };
+    let mut divide = false;
+    let mut rest = text;
+
+    loop {
+        let end = rest.find(['*', '/']).unwrap_or(rest.len());
+        let (term, tail) = rest.split_at(end);
+
+        let (name, exponent) = match term.split_once('^') {
+            Some((name, exp)) => (
+                name,
+                exp.parse::<i8>()
+                    .map_err(|_| format!("invalid exponent in unit '{text}'"))?,
+            ),
+            None => (term, 1),
+        };
+        let unit = lookup(name)?;
+        let exponent = match divide {
+            true => exponent.checked_neg().ok_or(OUT_OF_RANGE)?,
+            false => exponent,
+        };
+
+        result.factor *= unit.factor.powi(exponent as i32);
+        result.dim = result.dim.checked_mul(unit.dim.pow(exponent as f32)?)?;
+
+        match tail.chars().next() {
+            Some(op) => {
+                divide = op == '/';
+                rest = &tail[1..];
+            }
+            None => return Ok(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_compound_units() {
+        let unit = parse_unit("m/s^2").unwrap();
+        assert_eq!(unit.dim, Dimension([1, 0, -2, 0, 0]));
+
+        let unit = parse_unit("kN").unwrap();
+        assert_eq!(unit.factor, 1000.0);
+        assert_eq!(unit.dim, Dimension(FORCE));
+
+        assert!(parse_unit("furlong").is_err());
+    }
+
+    #[test]
+    fn rejects_exponents_out_of_range() {
+        let metres = Dimension([1, 0, 0, 0, 0]);
+        let m100 = metres.pow(100.0).unwrap();
+        assert_eq!(
+            m100.checked_mul(m100),
+            Err(String::from("unit exponent out of range"))
+        );
+        assert_eq!(m100.checked_div(m100), Ok(Dimension::default()));
+        assert!(metres.pow(1000.0).is_err());
+        assert!(metres.pow(-128.0).is_ok());
+        assert!(parse_unit("m^-128").is_ok());
+    }
+
+    #[test]
+    fn displays_dimensions() {
+        assert_eq!(Dimension(FORCE).to_string(), "N");
+        assert_eq!(Dimension([1, 0, -2, 0, 0]).to_string(), "m/s^2");
+        assert_eq!(Dimension([0, 0, -1, 0, 0]).to_string(), "1/s");
+    }
+}